@@ -108,3 +108,17 @@ type BinaryTests () =
   member this.BytesToString_Empty () =
     let result = bytesToString [||]
     Assert.AreEqual ("", result)
+
+  [<Test>]
+  member this.TryReadInt32_InBounds () =
+    let buffer = Array.zeroCreate<byte> 6
+    writeInt32LittleEndian -2 buffer 2
+    Assert.AreEqual (Some -2, tryReadInt32LittleEndian buffer 2)
+
+  [<Test>]
+  member this.TryReadInt32_OutOfBounds () =
+    let buffer = Array.zeroCreate<byte> 6
+    Assert.AreEqual (None, tryReadInt32LittleEndian buffer 3)
+    Assert.AreEqual (None, tryReadInt32LittleEndian buffer -1)
+    Assert.AreEqual (None, tryReadUInt32LittleEndian buffer System.Int32.MaxValue)
+    Assert.AreEqual (None, tryReadByte buffer 6)
@@ -96,6 +96,47 @@ type ProtocolTests () =
     let result = TryParseRequestPacket buffer
     Assert.That (result.IsNone, Is.True)
 
+  [<Test>]
+  member this.TryParseRequestPacket_NegativeKeyLength () =
+    let buffer = Array.zeroCreate<byte> (HEADER_SIZE + 4)
+    System.BitConverter.GetBytes(MAGIC_NUMBER).CopyTo (buffer, 0)
+    buffer.[4] <- PROTOCOL_VERSION
+    buffer.[5] <- byte CommandType.Set
+    System.BitConverter.GetBytes(HEADER_SIZE + 4).CopyTo (buffer, 6)
+    System.BitConverter.GetBytes(-4).CopyTo (buffer, 10)
+    System.BitConverter.GetBytes(8).CopyTo (buffer, 14)
+
+    let result = TryParseRequestPacket buffer
+    Assert.That (result.IsNone, Is.True)
+
+  [<Test>]
+  member this.TryParseRequestPacket_LengthMismatch () =
+    let buffer = Array.zeroCreate<byte> (HEADER_SIZE + 4)
+    System.BitConverter.GetBytes(MAGIC_NUMBER).CopyTo (buffer, 0)
+    buffer.[4] <- PROTOCOL_VERSION
+    buffer.[5] <- byte CommandType.Set
+    System.BitConverter.GetBytes(HEADER_SIZE + 4).CopyTo (buffer, 6)
+    System.BitConverter.GetBytes(2).CopyTo (buffer, 10)
+    System.BitConverter.GetBytes(System.Int32.MaxValue).CopyTo (buffer, 14)
+
+    let result = TryParseRequestPacket buffer
+    Assert.That (result.IsNone, Is.True)
+
+  [<Test>]
+  member this.DeserializeResponse_OverflowingLengths () =
+    let buffer = Array.zeroCreate<byte> HEADER_SIZE
+    System.BitConverter.GetBytes(MAGIC_NUMBER).CopyTo (buffer, 0)
+    buffer.[4] <- PROTOCOL_VERSION
+    buffer.[5] <- byte StatusCode.Success
+    // 18 + MaxValue + (MaxValue - 1) wraps to 15 in 32-bit arithmetic.
+    System.BitConverter.GetBytes(15).CopyTo (buffer, 6)
+    System.BitConverter.GetBytes(System.Int32.MaxValue).CopyTo (buffer, 10)
+    System.BitConverter.GetBytes(System.Int32.MaxValue - 1).CopyTo (buffer, 14)
+
+    match DeserializeResponse buffer with
+    | ParseError msg -> Assert.AreEqual ("Packet length mismatch", msg)
+    | _ -> Assert.Fail ("Expected ParseError")
+
   [<Test>]
   member this.SerializeRequest_EmptyKey () =
     let packet =
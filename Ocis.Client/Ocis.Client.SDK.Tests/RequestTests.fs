@@ -111,6 +111,17 @@ type RequestTests () =
     let valueLength = System.BitConverter.ToInt32 (bytes, 14)
     Assert.AreEqual (0, valueLength)
 
+  [<Test>]
+  member this.CreateExistsRequest_VerifyHeader () =
+    let key = "existsKey"
+    let bytes = createExistsRequest key
+
+    Assert.AreEqual (HEADER_SIZE + key.Length, bytes.Length)
+    Assert.AreEqual (byte CommandType.Exists, bytes.[5])
+
+    let valueLength = System.BitConverter.ToInt32 (bytes, 14)
+    Assert.AreEqual (0, valueLength)
+
   [<Test>]
   member this.CreateSetRequest_UnicodeKey () =
     let key = "键"
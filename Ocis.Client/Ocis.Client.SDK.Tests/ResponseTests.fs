@@ -260,6 +260,24 @@ type ResponseTests () =
     | Error msg -> Assert.AreEqual (errorMsg, msg)
     | _ -> Assert.Fail ("Expected Error")
 
+  [<Test>]
+  member this.ToClientResultExists_MapsNotFoundToFalse () =
+    let present =
+      CreateSuccessResponse None
+      |> SerializeResponse
+      |> parseResponse
+      |> toClientResultExists
+
+    let absent =
+      CreateNotFoundResponse ()
+      |> SerializeResponse
+      |> parseResponse
+      |> toClientResultExists
+
+    match present, absent with
+    | Success true, Success false -> ()
+    | _ -> Assert.Fail (sprintf "Unexpected results: %A, %A" present absent)
+
   [<Test>]
   member this.ClientResult_Type () =
     let success : ClientResult<unit> = Success ()
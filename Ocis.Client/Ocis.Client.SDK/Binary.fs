@@ -30,6 +30,28 @@ let readInt32LittleEndian (buffer : byte[]) (offset : int) : int32 =
 
 let readByte (buffer : byte[]) (offset : int) : byte = buffer.[offset]
 
+let private isInBounds (buffer : byte[]) (offset : int) (size : int) =
+  offset >= 0 && offset <= buffer.Length - size
+
+/// Bounds-checked variants for reading untrusted input.
+let tryReadUInt32LittleEndian (buffer : byte[]) (offset : int) : uint32 option =
+  if isInBounds buffer offset 4 then
+    Some (readUInt32LittleEndian buffer offset)
+  else
+    None
+
+let tryReadInt32LittleEndian (buffer : byte[]) (offset : int) : int32 option =
+  if isInBounds buffer offset 4 then
+    Some (readInt32LittleEndian buffer offset)
+  else
+    None
+
+let tryReadByte (buffer : byte[]) (offset : int) : byte option =
+  if isInBounds buffer offset 1 then
+    Some (readByte buffer offset)
+  else
+    None
+
 let writeUInt32LittleEndian
   (value : uint32)
   (buffer : byte[])
@@ -8,6 +8,19 @@ let inline IsValidHeader (magicNumber : uint32) (version : byte) =
   magicNumber = MAGIC_NUMBER
   && version = PROTOCOL_VERSION
 
+/// Whether the two body length fields are non-negative and add up to the
+/// declared total. Summed in 64 bits so hostile headers cannot overflow.
+let HasConsistentLengths
+  (totalPacketLength : int32)
+  (firstLength : int32)
+  (secondLength : int32)
+  =
+  firstLength >= 0
+  && secondLength >= 0
+  && int64 totalPacketLength = int64 HEADER_SIZE
+                               + int64 firstLength
+                               + int64 secondLength
+
 let TryParseRequestHeader (buffer : byte[]) =
   if buffer.Length < HEADER_SIZE then
     None
@@ -38,7 +51,16 @@ let TryParseRequestHeader (buffer : byte[]) =
 let TryParseRequestPacket (buffer : byte[]) =
   match TryParseRequestHeader buffer with
   | Some header ->
-    if buffer.Length >= header.TotalPacketLength then
+    if
+      not (
+        HasConsistentLengths
+          header.TotalPacketLength
+          header.KeyLength
+          header.ValueLength
+      )
+    then
+      None
+    elif buffer.Length >= header.TotalPacketLength then
       try
         let keyOffset = HEADER_SIZE
         let key = Array.sub buffer keyOffset header.KeyLength
@@ -232,8 +254,9 @@ let DeserializeResponse (buffer : byte[]) : ParseResult<ResponsePacket> =
       elif valueLength < 0 || errorMessageLength < 0 then
         ParseError "Invalid length field"
       elif
-        totalPacketLength
-        <> HEADER_SIZE + valueLength + errorMessageLength
+        not (
+          HasConsistentLengths totalPacketLength valueLength errorMessageLength
+        )
       then
         ParseError "Packet length mismatch"
       else
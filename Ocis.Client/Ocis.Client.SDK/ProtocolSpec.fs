@@ -16,6 +16,7 @@ type CommandType =
   | Set = 1
   | Get = 2
   | Delete = 3
+  | Exists = 5
 
 type StatusCode =
   | Success = 0uy
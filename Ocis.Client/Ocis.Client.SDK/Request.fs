@@ -41,3 +41,7 @@ let createGetRequest (key : string) : byte[] =
 let createDeleteRequest (key : string) : byte[] =
   let packet = createPacket CommandType.Delete key None
   SerializeRequest packet
+
+let createExistsRequest (key : string) : byte[] =
+  let packet = createPacket CommandType.Exists key None
+  SerializeRequest packet
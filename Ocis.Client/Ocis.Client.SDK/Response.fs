@@ -49,3 +49,22 @@ let toClientResultValue
     | _ -> Error "Invalid status code"
   | ParseError msg -> Error msg
   | InsufficientData -> Error "Insufficient data"
+
+/// Map an EXISTS response: NotFound is a successful `false`.
+let toClientResultExists
+  (parseResult : ParseResult<ResponsePacket>)
+  : ClientResult<bool>
+  =
+  match parseResult with
+  | ParseSuccess response ->
+    match response.StatusCode with
+    | StatusCode.Success -> Success true
+    | StatusCode.NotFound -> Success false
+    | StatusCode.Error ->
+      Error (
+        response.ErrorMessage
+        |> Option.defaultValue "Unknown error"
+      )
+    | _ -> Error "Invalid status code"
+  | ParseError msg -> Error msg
+  | InsufficientData -> Error "Insufficient data"
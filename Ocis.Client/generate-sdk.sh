@@ -25,7 +25,7 @@ echo ""
 mkdir -p "$SCRIPT_DIR/sdk/ts"
 mkdir -p "$SCRIPT_DIR/sdk/py"
 mkdir -p "$SCRIPT_DIR/sdk/dart"
-mkdir -p "$SCRIPT_DIR/sdk/rust"
+mkdir -p "$SCRIPT_DIR/sdk/rust/fable"
 
 # Generate TypeScript SDK
 echo "Generating TypeScript SDK..."
@@ -46,9 +46,9 @@ echo "Python SDK generated: $SCRIPT_DIR/sdk/dart"
 echo ""
 
 # Generate rust SDK
-echo "Generating Python SDK..."
-fable "$PROJECT_DIR" --lang rust -o "$SCRIPT_DIR/sdk/rust"
-echo "Python SDK generated: $SCRIPT_DIR/sdk/rust"
+echo "Generating Rust SDK..."
+fable "$PROJECT_DIR" --lang rust -o "$SCRIPT_DIR/sdk/rust/fable"
+echo "Rust SDK generated: $SCRIPT_DIR/sdk/rust/fable"
 echo ""
 
 echo "=== SDK Generation Complete ==="
@@ -1,5 +1,6 @@
 // ignore_for_file: camel_case_types, constant_identifier_names, non_constant_identifier_names, unnecessary_this
 import './fable_modules/fable_library/Encoding.dart' as encoding;
+import './fable_modules/fable_library/Types.dart' as types;
 
 int getUint32(int b0, int b1, int b2, int b3) => (((((b0 | ((b1 << 8) >>> 0)) >>> 0) | ((b2 << 16) >>> 0)) >>> 0) | ((b3 << 24) >>> 0)) >>> 0;
 
@@ -11,6 +12,33 @@ int readInt32LittleEndian(List<int> buffer, int offset) => getInt32(buffer[offse
 
 int readByte(List<int> buffer, int offset) => buffer[offset];
 
+bool isInBounds(List<int> buffer, int offset, int size) => (offset >= 0) && (offset <= (buffer.length - size));
+
+/// Bounds-checked variants for reading untrusted input.
+types.Some<int>? tryReadUInt32LittleEndian(List<int> buffer, int offset) {
+    if (isInBounds(buffer, offset, 4)) {
+        return types.Some(readUInt32LittleEndian(buffer, offset));
+    } else {
+        return null;
+    }
+}
+
+types.Some<int>? tryReadInt32LittleEndian(List<int> buffer, int offset) {
+    if (isInBounds(buffer, offset, 4)) {
+        return types.Some(readInt32LittleEndian(buffer, offset));
+    } else {
+        return null;
+    }
+}
+
+types.Some<int>? tryReadByte(List<int> buffer, int offset) {
+    if (isInBounds(buffer, offset, 1)) {
+        return types.Some(readByte(buffer, offset));
+    } else {
+        return null;
+    }
+}
+
 void writeUInt32LittleEndian(int value, List<int> buffer, int offset) {
     buffer[offset] = value;
     buffer[offset + 1] = value >>> 8;
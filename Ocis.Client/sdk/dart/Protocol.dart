@@ -8,6 +8,10 @@ import './fable_modules/fable_library/Types.dart' as types;
 import './fable_modules/fable_library/Util.dart' as util;
 import './Binary.dart' as binary;
 
+/// Whether the two body length fields are non-negative and add up to the
+/// declared total. Summed in 64 bits so hostile headers cannot overflow.
+bool HasConsistentLengths(int totalPacketLength, int firstLength, int secondLength) => ((firstLength >= 0) && (secondLength >= 0)) && (totalPacketLength == ((18 + firstLength) + secondLength));
+
 types.Some<protocol_spec.RequestPacket>? TryParseRequestHeader(List<int> buffer) {
     if (buffer.length < 18) {
         return null;
@@ -32,7 +36,9 @@ types.Some<protocol_spec.RequestPacket>? TryParseRequestPacket(List<int> buffer)
         return null;
     } else {
         final header = types.value(matchValue);
-        if (buffer.length >= header.TotalPacketLength) {
+        if (!HasConsistentLengths(header.TotalPacketLength, header.KeyLength, header.ValueLength)) {
+            return null;
+        } else if (buffer.length >= header.TotalPacketLength) {
             try {
                 return types.Some(protocol_spec.RequestPacket(header.MagicNumber, header.Version, header.CommandType, header.TotalPacketLength, header.KeyLength, header.ValueLength, array.getSubArray<int>(buffer, 18, header.KeyLength), (header.ValueLength > 0) ? types.Some(array.getSubArray<int>(buffer, 18 + header.KeyLength, header.ValueLength)) : null));
             } catch (matchValue_1) {
@@ -201,7 +207,7 @@ ParseResult$1<protocol_spec.ResponsePacket> DeserializeResponse(List<int> buffer
                 return const ParseResult$1_ParseError<protocol_spec.ResponsePacket>('Invalid header');
             } else if ((valueLength < 0) || (errorMessageLength < 0)) {
                 return const ParseResult$1_ParseError<protocol_spec.ResponsePacket>('Invalid length field');
-            } else if (totalPacketLength != ((18 + valueLength) + errorMessageLength)) {
+            } else if (!HasConsistentLengths(totalPacketLength, valueLength, errorMessageLength)) {
                 return const ParseResult$1_ParseError<protocol_spec.ResponsePacket>('Packet length mismatch');
             } else {
                 final types.Some<List<int>>? value = (valueLength > 0) ? types.Some(array.getSubArray<int>(buffer, offset, valueLength)) : null;
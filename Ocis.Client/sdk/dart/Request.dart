@@ -18,3 +18,5 @@ List<int> createGetRequest(String key) => protocol.SerializeRequest(createPacket
 
 List<int> createDeleteRequest(String key) => protocol.SerializeRequest(createPacket(3, key, null));
 
+List<int> createExistsRequest(String key) => protocol.SerializeRequest(createPacket(5, key, null));
+
@@ -105,3 +105,28 @@ ClientResult$1<List<int>> toClientResultValue(protocol.ParseResult$1<protocol_sp
     }
 }
 
+/// Map an EXISTS response: NotFound is a successful `false`.
+ClientResult$1<bool> toClientResultExists(protocol.ParseResult$1<protocol_spec.ResponsePacket> parseResult) {
+    switch (parseResult.tag) {
+        case 1:
+            final parseResult_1 = parseResult as protocol.ParseResult$1_ParseError<protocol_spec.ResponsePacket>;
+            return ClientResult$1_Error<bool>(parseResult_1.Item);
+        case 2:
+            return const ClientResult$1_Error<bool>('Insufficient data');
+        default:
+            final parseResult_2 = parseResult as protocol.ParseResult$1_ParseSuccess<protocol_spec.ResponsePacket>;
+            final response = parseResult_2.Item;
+            final matchValue = response.StatusCode;
+            if (matchValue == 0) {
+                return const ClientResult$1_Success<bool>(true);
+            } else if (matchValue == 1) {
+                return const ClientResult$1_Success<bool>(false);
+            } else if (matchValue == 2) {
+                return ClientResult$1_Error<bool>(option_1.defaultValue('Unknown error', response.ErrorMessage));
+            } else {
+                return const ClientResult$1_Error<bool>('Invalid status code');
+            }
+            ;
+    }
+}
+
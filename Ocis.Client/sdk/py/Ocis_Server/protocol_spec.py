@@ -3,14 +3,14 @@ from ..fable_modules.fable_library.reflection import (enum_type, TypeInfo, uint3
 from ..fable_modules.fable_library.types import (Record, uint8, uint32)
 
 def _expr0() -> TypeInfo:
-    return record_type("Ocis.Server.ProtocolSpec.RequestPacket", [], RequestPacket, lambda: [("MagicNumber", uint32_type), ("Version", uint8_type), ("CommandType", enum_type("Ocis.Server.ProtocolSpec.CommandType", int32_type, [("Set", 1.0), ("Get", 2.0), ("Delete", 3.0)])), ("TotalPacketLength", int32_type), ("KeyLength", int32_type), ("ValueLength", int32_type), ("Key", array_type(uint8_type)), ("Value", option_type(array_type(uint8_type)))])
+    return record_type("Ocis.Server.ProtocolSpec.RequestPacket", [], RequestPacket, lambda: [("MagicNumber", uint32_type), ("Version", uint8_type), ("CommandType", enum_type("Ocis.Server.ProtocolSpec.CommandType", int32_type, [("Set", 1.0), ("Get", 2.0), ("Delete", 3.0), ("Exists", 5.0)])), ("TotalPacketLength", int32_type), ("KeyLength", int32_type), ("ValueLength", int32_type), ("Key", array_type(uint8_type)), ("Value", option_type(array_type(uint8_type)))])
 
 
 @dataclass(eq = False, repr = False, slots = True)
 class RequestPacket(Record):
     MagicNumber: uint32
     Version: uint8
-    CommandType: enum_type("Ocis.Server.ProtocolSpec.CommandType", int, [("Set", 1.0), ("Get", 2.0), ("Delete", 3.0)])
+    CommandType: enum_type("Ocis.Server.ProtocolSpec.CommandType", int, [("Set", 1.0), ("Get", 2.0), ("Delete", 3.0), ("Exists", 5.0)])
     TotalPacketLength: int
     KeyLength: int
     ValueLength: int
@@ -21,6 +21,43 @@ def read_byte(buffer: bytearray, offset: int) -> uint8:
     return buffer[offset]
 
 
+def is_in_bounds(buffer: bytearray, offset: int, size: int) -> bool:
+    if offset >= 0:
+        return offset <= (len(buffer) - size)
+
+    else: 
+        return False
+
+
+
+def try_read_uint32little_endian(buffer: bytearray, offset: int) -> uint32 | None:
+    """Bounds-checked variants for reading untrusted input."""
+    if is_in_bounds(buffer, offset, 4):
+        return read_uint32little_endian(buffer, offset)
+
+    else: 
+        return None
+
+
+
+def try_read_int32little_endian(buffer: bytearray, offset: int) -> int | None:
+    if is_in_bounds(buffer, offset, 4):
+        return read_int32little_endian(buffer, offset)
+
+    else: 
+        return None
+
+
+
+def try_read_byte(buffer: bytearray, offset: int) -> uint8 | None:
+    if is_in_bounds(buffer, offset, 1):
+        return read_byte(buffer, offset)
+
+    else: 
+        return None
+
+
+
 def write_uint32little_endian(value: uint32, buffer: bytearray, offset: int) -> None:
     buffer[offset] = int(value+0x100 if value < 0 else value) & 0xFF
     buffer[offset + 1] = int((value >> 8)+0x100 if (value >> 8) < 0 else (value >> 8)) & 0xFF
@@ -48,5 +85,5 @@ def bytes_to_string(bytes: bytearray) -> str:
     return get_utf8().get_string(bytes)
 
 
-__all__ = ["get_uint32", "get_int32", "read_uint32little_endian", "read_int32little_endian", "read_byte", "write_uint32little_endian", "write_int32little_endian", "write_byte", "string_to_bytes", "bytes_to_string"]
+__all__ = ["get_uint32", "get_int32", "read_uint32little_endian", "read_int32little_endian", "read_byte", "try_read_uint32little_endian", "try_read_int32little_endian", "try_read_byte", "write_uint32little_endian", "write_int32little_endian", "write_byte", "string_to_bytes", "bytes_to_string"]
 
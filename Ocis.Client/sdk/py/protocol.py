@@ -6,11 +6,23 @@ from .fable_modules.fable_library.encoding import get_utf8
 from .fable_modules.fable_library.option import (default_arg, map)
 from .fable_modules.fable_library.reflection import (TypeInfo, string_type, union_type)
 from .fable_modules.fable_library.string_ import (to_text, printf)
-from .fable_modules.fable_library.types import (uint32, uint8, Array, Union)
+from .fable_modules.fable_library.types import (uint32, uint8, int64, Array, Union)
 from .binary import (read_uint32little_endian, read_byte, read_int32little_endian, write_uint32little_endian, write_byte, write_int32little_endian)
 
 _T = TypeVar("_T")
 
+def HasConsistentLengths(total_packet_length: int, first_length: int, second_length: int) -> bool:
+    """Whether the two body length fields are non-negative and add up to the
+    declared total. Summed in 64 bits so hostile headers cannot overflow.
+    """
+    if (second_length >= 0) if (first_length >= 0) else False:
+        return int64(total_packet_length) == ((int64(18) + int64(first_length)) + int64(second_length))
+
+    else: 
+        return False
+
+
+
 def TryParseRequestHeader(buffer: bytearray) -> RequestPacket | None:
     if len(buffer) < 18:
         return None
@@ -34,7 +46,10 @@ def TryParseRequestPacket(buffer: bytearray) -> RequestPacket | None:
 
     else: 
         header: RequestPacket = match_value
-        if len(buffer) >= header.TotalPacketLength:
+        if not HasConsistentLengths(header.TotalPacketLength, header.KeyLength, header.ValueLength):
+            return None
+
+        elif len(buffer) >= header.TotalPacketLength:
             try: 
                 return RequestPacket(header.MagicNumber, header.Version, header.CommandType, header.TotalPacketLength, header.KeyLength, header.ValueLength, get_sub_array(buffer, 18, header.KeyLength), get_sub_array(buffer, 18 + header.KeyLength, header.ValueLength) if (header.ValueLength > 0) else None)
 
@@ -199,7 +214,7 @@ def DeserializeResponse(buffer: bytearray) -> ParseResult_1[ResponsePacket]:
             elif True if (value_length < 0) else (error_message_length < 0):
                 return ParseResult_1(1, "Invalid length field")
 
-            elif total_packet_length != ((18 + value_length) + error_message_length):
+            elif not HasConsistentLengths(total_packet_length, value_length, error_message_length):
                 return ParseResult_1(1, "Packet length mismatch")
 
             else: 
@@ -222,5 +237,5 @@ def DeserializeResponse(buffer: bytearray) -> ParseResult_1[ResponsePacket]:
 
 
 
-__all__ = ["TryParseRequestHeader", "TryParseRequestPacket", "SerializeRequest", "CreateSuccessResponse", "CreateNotFoundResponse", "CreateErrorResponse", "IsValidPacketSize", "SerializeResponse", "ParseResult_1_reflection", "DeserializeResponse"]
+__all__ = ["HasConsistentLengths", "TryParseRequestHeader", "TryParseRequestPacket", "SerializeRequest", "CreateSuccessResponse", "CreateNotFoundResponse", "CreateErrorResponse", "IsValidPacketSize", "SerializeResponse", "ParseResult_1_reflection", "DeserializeResponse"]
 
@@ -7,7 +7,7 @@ from .fable_modules.fable_library.reflection import enum_type
 from .fable_modules.fable_library.types import (uint32, uint8)
 from .protocol import SerializeRequest
 
-def create_packet(command_type: enum_type("Ocis.Server.ProtocolSpec.CommandType", int, [("Set", 1.0), ("Get", 2.0), ("Delete", 3.0)]), key: str, value: bytearray | None=None) -> RequestPacket:
+def create_packet(command_type: enum_type("Ocis.Server.ProtocolSpec.CommandType", int, [("Set", 1.0), ("Get", 2.0), ("Delete", 3.0), ("Exists", 5.0)]), key: str, value: bytearray | None=None) -> RequestPacket:
     key_bytes: bytearray = get_utf8().get_bytes(key)
     key_len: int = len(key_bytes) or 0
     def mapping(v: bytearray, command_type: Any=command_type, key: Any=key, value: Any=value) -> int:
@@ -29,5 +29,9 @@ def create_delete_request(key: str) -> bytearray:
     return SerializeRequest(create_packet(3, key, None))
 
 
-__all__ = ["create_packet", "create_set_request", "create_get_request", "create_delete_request"]
+def create_exists_request(key: str) -> bytearray:
+    return SerializeRequest(create_packet(5, key, None))
+
+
+__all__ = ["create_packet", "create_set_request", "create_get_request", "create_delete_request", "create_exists_request"]
 
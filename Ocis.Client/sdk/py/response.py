@@ -85,5 +85,30 @@ def to_client_result_value(parse_result: ParseResult_1[ResponsePacket]) -> Clien
 
 
 
-__all__ = ["ClientResult_1_reflection", "parse_response", "to_client_result", "to_client_result_value"]
+def to_client_result_exists(parse_result: ParseResult_1[ResponsePacket]) -> ClientResult_1[bool]:
+    if parse_result.tag == 1:
+        return ClientResult_1(2, parse_result.fields[0])
+
+    elif parse_result.tag == 2:
+        return ClientResult_1(2, "Insufficient data")
+
+    else: 
+        response: ResponsePacket = parse_result.fields[0]
+        match_value: enum_type("Ocis.Server.ProtocolSpec.StatusCode", uint8, [("Success", 0.0), ("NotFound", 1.0), ("Error", 2.0)]) = response.StatusCode
+        if match_value == uint8(0):
+            return ClientResult_1(0, True)
+
+        elif match_value == uint8(1):
+            return ClientResult_1(0, False)
+
+        elif match_value == uint8(2):
+            return ClientResult_1(2, default_arg(response.ErrorMessage, "Unknown error"))
+
+        else: 
+            return ClientResult_1(2, "Invalid status code")
+
+
+
+
+__all__ = ["ClientResult_1_reflection", "parse_response", "to_client_result", "to_client_result_value", "to_client_result_exists"]
 
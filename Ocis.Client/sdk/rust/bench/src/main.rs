@@ -0,0 +1,217 @@
+//! `ocis-bench`: client-side load generator for Ocis servers.
+//!
+//! Results are printed as a markdown row in the shape of the README's
+//! server throughput table, so Fast/Strict/Balanced numbers can be
+//! reproduced from the client side and pasted next to the baseline.
+
+mod runner;
+mod stats;
+mod workload;
+
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::Parser;
+
+use crate::runner::{Frames, RunConfig};
+use crate::stats::RunSummary;
+use crate::workload::{Distribution, Workload};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "ocis-bench",
+    version,
+    about = "Load generator for Ocis servers.",
+    disable_help_flag = true
+)]
+struct Args {
+    /// Server host
+    #[arg(short = 'h', long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Server port
+    #[arg(short = 'p', long, default_value_t = 7379)]
+    port: u16,
+
+    /// Concurrent connections
+    #[arg(short = 'c', long, default_value_t = 32, value_parser = clap::value_parser!(u32).range(1..))]
+    concurrency: u32,
+
+    /// Requests in flight per connection
+    #[arg(short = 'P', long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pipeline: u32,
+
+    /// Measured run time in seconds
+    #[arg(short = 'd', long, default_value_t = 30)]
+    duration: u64,
+
+    /// Unmeasured warmup time in seconds
+    #[arg(long, default_value_t = 5)]
+    warmup: u64,
+
+    /// Number of distinct keys
+    #[arg(short = 'k', long, default_value_t = 100_000, value_parser = clap::value_parser!(u64).range(1..))]
+    keys: u64,
+
+    /// Key selection
+    #[arg(long, value_enum, default_value_t = Distribution::Uniform)]
+    distribution: Distribution,
+
+    /// Skew for the zipfian distribution, in (0, 1)
+    #[arg(long, default_value_t = 0.99)]
+    zipf_theta: f64,
+
+    /// Value size in bytes for SET
+    #[arg(short = 'v', long, default_value_t = 256)]
+    value_bytes: usize,
+
+    /// Fraction of operations that are GETs, from 0.0 (all SET) to 1.0 (all GET)
+    #[arg(short = 'r', long, default_value_t = 0.0)]
+    read_ratio: f64,
+
+    /// Do not write every key before a run that reads
+    #[arg(long)]
+    skip_preload: bool,
+
+    /// Row label, e.g. the server's durability mode (Fast, Strict, Balanced)
+    #[arg(long)]
+    label: Option<String>,
+
+    /// Random seed for key and operation selection
+    #[arg(long, default_value_t = 3000)]
+    seed: u64,
+
+    /// Print help
+    #[arg(long, action = clap::ArgAction::Help)]
+    help: Option<bool>,
+}
+
+impl Args {
+    fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.read_ratio) {
+            return Err("read ratio must be between 0.0 and 1.0".into());
+        }
+        if self.distribution == Distribution::Zipfian
+            && !(self.zipf_theta > 0.0 && self.zipf_theta < 1.0)
+        {
+            return Err("zipf theta must be between 0 and 1 (exclusive)".into());
+        }
+        Ok(())
+    }
+
+    fn label(&self) -> String {
+        self.label.clone().unwrap_or_else(|| match self.read_ratio {
+            0.0 => "SET".to_string(),
+            1.0 => "GET".to_string(),
+            r => format!("MIXED {:.0}/{:.0}", r * 100.0, (1.0 - r) * 100.0),
+        })
+    }
+}
+
+/// Format an integer with thousands separators, as in the README tables.
+fn thousands(value: u64) -> String {
+    let digits = value.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn print_report(args: &Args, summary: &RunSummary) {
+    println!(
+        "{}:{}  {} connections x pipeline {}  {}B values  {:?} keys ({})  {:.0}% reads  {}s (+{}s warmup)",
+        args.host,
+        args.port,
+        args.concurrency,
+        args.pipeline,
+        args.value_bytes,
+        args.distribution,
+        args.keys,
+        args.read_ratio * 100.0,
+        args.duration,
+        args.warmup,
+    );
+    println!();
+    println!("| Mode     | ops/s   | p50 (ms) | p99 (ms) | p999 (ms) |");
+    println!("|----------|--------:|---------:|---------:|----------:|");
+    println!(
+        "| {:<8} | {:>7} | {:>8.2} | {:>8.2} | {:>9.2} |",
+        args.label(),
+        thousands(summary.ops_per_sec().round() as u64),
+        summary.latency.p50_ms,
+        summary.latency.p99_ms,
+        summary.latency.p999_ms,
+    );
+    println!();
+    println!(
+        "ok={} failed={} error_rate={:.4} mean={:.3}ms max={:.3}ms",
+        summary.successes,
+        summary.failures,
+        summary.error_rate(),
+        summary.latency.mean_ms,
+        summary.latency.max_ms,
+    );
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    if let Err(message) = args.validate() {
+        eprintln!("error: {message}");
+        return ExitCode::from(2);
+    }
+
+    let keys = args.keys as usize;
+    let config = RunConfig {
+        host: args.host.clone(),
+        port: args.port,
+        concurrency: args.concurrency as usize,
+        pipeline: args.pipeline as usize,
+        duration: Duration::from_secs(args.duration),
+        warmup: Duration::from_secs(args.warmup),
+        seed: args.seed,
+    };
+    let frames = Arc::new(Frames::new(keys, args.value_bytes));
+    let workload = Arc::new(Workload::new(
+        keys,
+        args.distribution,
+        args.read_ratio,
+        args.zipf_theta,
+    ));
+
+    if args.read_ratio > 0.0 && !args.skip_preload {
+        eprintln!("preloading {keys} keys...");
+        if let Err(e) = runner::preload(&config, &frames) {
+            eprintln!("error: preload failed: {e}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    match runner::run(&config, workload, frames) {
+        Ok(summary) => {
+            print_report(&args, &summary);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_thousands() {
+        assert_eq!(thousands(0), "0");
+        assert_eq!(thousands(410), "410");
+        assert_eq!(thousands(35196), "35,196");
+        assert_eq!(thousands(1234567), "1,234,567");
+    }
+}
@@ -0,0 +1,235 @@
+//! Connection workers with request pipelining.
+//!
+//! Each connection has a writer thread and a reader thread. The writer may
+//! run up to `pipeline` requests ahead of the reader; responses arrive in
+//! order, so the reader pairs each one with the send time queued for it.
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ocis_client::protocol::{deserialize_response, frame_length, ParseResult};
+use ocis_client::request::{create_get_request, create_set_request};
+use ocis_client::spec::{StatusCode, HEADER_SIZE};
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+
+use crate::stats::{latency_stats, RunSummary};
+use crate::workload::{key_name, Op, Workload};
+
+pub struct RunConfig {
+    pub host: String,
+    pub port: u16,
+    pub concurrency: usize,
+    pub pipeline: usize,
+    pub duration: Duration,
+    pub warmup: Duration,
+    pub seed: u64,
+}
+
+/// Request frames encoded once up front so the client measures the server,
+/// not its own serialization.
+pub struct Frames {
+    get: Vec<Vec<u8>>,
+    set: Vec<Vec<u8>>,
+}
+
+impl Frames {
+    pub fn new(keys: usize, value_bytes: usize) -> Self {
+        // Same payload shape as Ocis.Perf: 'v' followed by 's' padding.
+        let mut payload = vec![b's'; value_bytes];
+        if let Some(first) = payload.first_mut() {
+            *first = b'v';
+        }
+        Frames {
+            get: (0..keys)
+                .map(|i| create_get_request(&key_name(i)))
+                .collect(),
+            set: (0..keys)
+                .map(|i| create_set_request(&key_name(i), &payload))
+                .collect(),
+        }
+    }
+
+    fn frame(&self, op: Op, key: usize) -> &[u8] {
+        match op {
+            Op::Get => &self.get[key],
+            Op::Set => &self.set[key],
+        }
+    }
+}
+
+struct Sent {
+    at: Instant,
+    op: Op,
+}
+
+#[derive(Default)]
+struct WorkerResult {
+    successes: u64,
+    failures: u64,
+    samples: Vec<u64>,
+}
+
+/// Write every key once so reads hit existing data.
+pub fn preload(config: &RunConfig, frames: &Frames) -> io::Result<()> {
+    let mut stream = connect(config)?;
+    let mut reader = stream.try_clone()?;
+    let keys = frames.set.len();
+    let window = config.pipeline.max(16);
+
+    let mut acked = 0;
+    for key in 0..keys {
+        stream.write_all(frames.frame(Op::Set, key))?;
+        if key + 1 - acked >= window {
+            expect_success(&mut reader, Op::Set)?;
+            acked += 1;
+        }
+    }
+    while acked < keys {
+        expect_success(&mut reader, Op::Set)?;
+        acked += 1;
+    }
+    Ok(())
+}
+
+/// Drive the server for warmup plus measurement time and summarize the
+/// measured window.
+pub fn run(
+    config: &RunConfig,
+    workload: Arc<Workload>,
+    frames: Arc<Frames>,
+) -> io::Result<RunSummary> {
+    let started = Instant::now();
+    let measure_from = started + config.warmup;
+    let stop_at = measure_from + config.duration;
+
+    let mut readers = Vec::with_capacity(config.concurrency);
+    for worker in 0..config.concurrency {
+        let stream = connect(config)?;
+        let reader = stream.try_clone()?;
+        let (sent_tx, sent_rx) = mpsc::channel();
+        let (credit_tx, credit_rx) = mpsc::sync_channel(config.pipeline);
+        for _ in 0..config.pipeline {
+            credit_tx.send(()).expect("credit channel has room");
+        }
+
+        let workload = Arc::clone(&workload);
+        let frames = Arc::clone(&frames);
+        let rng = SmallRng::seed_from_u64(config.seed.wrapping_add(worker as u64));
+        thread::spawn(move || {
+            write_loop(stream, &workload, &frames, rng, credit_rx, sent_tx, stop_at)
+        });
+        readers.push(thread::spawn(move || {
+            read_loop(reader, sent_rx, credit_tx, measure_from)
+        }));
+    }
+
+    let mut total = WorkerResult::default();
+    for reader in readers {
+        let result = reader
+            .join()
+            .map_err(|_| io::Error::other("reader thread panicked"))??;
+        total.successes += result.successes;
+        total.failures += result.failures;
+        total.samples.extend(result.samples);
+    }
+
+    Ok(RunSummary {
+        successes: total.successes,
+        failures: total.failures,
+        duration: Instant::now().saturating_duration_since(measure_from),
+        latency: latency_stats(total.samples),
+    })
+}
+
+fn connect(config: &RunConfig) -> io::Result<TcpStream> {
+    let stream = TcpStream::connect((config.host.as_str(), config.port))?;
+    stream.set_nodelay(true)?;
+    Ok(stream)
+}
+
+fn write_loop(
+    mut stream: TcpStream,
+    workload: &Workload,
+    frames: &Frames,
+    mut rng: SmallRng,
+    credits: Receiver<()>,
+    sent: Sender<Sent>,
+    stop_at: Instant,
+) {
+    // Each credit is one free pipeline slot, returned by the reader.
+    while credits.recv().is_ok() {
+        let at = Instant::now();
+        if at >= stop_at {
+            break;
+        }
+        let op = workload.next_op(&mut rng);
+        let key = workload.next_key(&mut rng);
+        if sent.send(Sent { at, op }).is_err() || stream.write_all(frames.frame(op, key)).is_err() {
+            break;
+        }
+    }
+    // Dropping `sent` lets the reader drain in-flight responses and finish.
+}
+
+fn read_loop(
+    mut stream: TcpStream,
+    sent: Receiver<Sent>,
+    credits: SyncSender<()>,
+    measure_from: Instant,
+) -> io::Result<WorkerResult> {
+    let mut result = WorkerResult::default();
+    for Sent { at, op } in sent {
+        let ok = read_status(&mut stream, op)?;
+        let latency = at.elapsed();
+        let _ = credits.try_send(());
+
+        if at < measure_from {
+            continue;
+        }
+        if ok {
+            result.successes += 1;
+        } else {
+            result.failures += 1;
+        }
+        result.samples.push(latency.as_nanos() as u64);
+    }
+    Ok(result)
+}
+
+fn expect_success(stream: &mut TcpStream, op: Op) -> io::Result<()> {
+    if read_status(stream, op)? {
+        Ok(())
+    } else {
+        Err(io::Error::other("server rejected preload write"))
+    }
+}
+
+/// Read one response and report whether it counts as a successful operation.
+/// As in `Ocis.Perf`, a GET answered with NotFound is still a success.
+fn read_status(stream: &mut TcpStream, op: Op) -> io::Result<bool> {
+    let mut header = [0u8; HEADER_SIZE];
+    stream.read_exact(&mut header)?;
+    let Some(total_length) = frame_length(&header) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid response packet size",
+        ));
+    };
+    let mut frame = vec![0u8; total_length];
+    frame[..HEADER_SIZE].copy_from_slice(&header);
+    stream.read_exact(&mut frame[HEADER_SIZE..])?;
+
+    Ok(match deserialize_response(&frame) {
+        ParseResult::ParseSuccess(response) => match response.status() {
+            Some(StatusCode::Success) => true,
+            Some(StatusCode::NotFound) => op == Op::Get,
+            _ => false,
+        },
+        _ => false,
+    })
+}
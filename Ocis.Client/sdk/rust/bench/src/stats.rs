@@ -0,0 +1,88 @@
+//! Latency percentiles and run summaries.
+
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LatencyStats {
+    pub p50_ms: f64,
+    pub p99_ms: f64,
+    pub p999_ms: f64,
+    pub max_ms: f64,
+    pub mean_ms: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunSummary {
+    pub successes: u64,
+    pub failures: u64,
+    pub duration: Duration,
+    pub latency: LatencyStats,
+}
+
+impl RunSummary {
+    /// Throughput counts successful operations only, like `Ocis.Perf`.
+    pub fn ops_per_sec(&self) -> f64 {
+        let secs = self.duration.as_secs_f64();
+        if secs <= 0.0 {
+            0.0
+        } else {
+            self.successes as f64 / secs
+        }
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        let total = self.successes + self.failures;
+        if total == 0 {
+            0.0
+        } else {
+            self.failures as f64 / total as f64
+        }
+    }
+}
+
+/// Nearest-rank percentile over sorted samples, matching `Ocis.Perf.Stats`.
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let index = (p * sorted.len() as f64).ceil() as usize;
+    sorted[index.clamp(1, sorted.len()) - 1]
+}
+
+/// Summarize latency samples given in nanoseconds.
+pub fn latency_stats(mut samples: Vec<u64>) -> LatencyStats {
+    if samples.is_empty() {
+        return LatencyStats::default();
+    }
+    samples.sort_unstable();
+    let ms = |nanos: u64| nanos as f64 / 1_000_000.0;
+    let total: u128 = samples.iter().map(|&n| n as u128).sum();
+    LatencyStats {
+        p50_ms: ms(percentile(&samples, 0.50)),
+        p99_ms: ms(percentile(&samples, 0.99)),
+        p999_ms: ms(percentile(&samples, 0.999)),
+        max_ms: ms(samples[samples.len() - 1]),
+        mean_ms: total as f64 / samples.len() as f64 / 1_000_000.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        let samples: Vec<u64> = (1..=1000).map(|i| i * 1_000_000).collect();
+        let stats = latency_stats(samples);
+        assert_eq!(stats.p50_ms, 500.0);
+        assert_eq!(stats.p99_ms, 990.0);
+        assert_eq!(stats.p999_ms, 999.0);
+        assert_eq!(stats.max_ms, 1000.0);
+        assert_eq!(stats.mean_ms, 500.5);
+    }
+
+    #[test]
+    fn empty_samples_are_zero() {
+        assert_eq!(latency_stats(Vec::new()), LatencyStats::default());
+    }
+}
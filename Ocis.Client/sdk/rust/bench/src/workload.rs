@@ -0,0 +1,149 @@
+//! Key selection and read/write mix.
+
+use clap::ValueEnum;
+use rand::rngs::SmallRng;
+use rand::Rng;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Distribution {
+    /// Every key equally likely
+    Uniform,
+    /// Skewed towards low key indices (YCSB-style, see `--zipf-theta`)
+    Zipfian,
+    /// Round-robin over the key space, shared by all workers
+    Sequential,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Get,
+    Set,
+}
+
+/// Zipfian generator after Gray et al., "Quickly Generating Billion-Record
+/// Synthetic Databases", as used by YCSB. Rank 0 is the hottest key.
+#[derive(Debug)]
+pub struct Zipfian {
+    items: usize,
+    theta: f64,
+    alpha: f64,
+    zetan: f64,
+    eta: f64,
+}
+
+impl Zipfian {
+    pub fn new(items: usize, theta: f64) -> Self {
+        assert!(items > 0, "zipfian needs at least one item");
+        assert!(
+            theta > 0.0 && theta < 1.0,
+            "zipfian theta must be in (0, 1)"
+        );
+        let zeta = |n: usize| (1..=n).map(|i| 1.0 / (i as f64).powf(theta)).sum::<f64>();
+        let zetan = zeta(items);
+        let zeta2 = zeta(2.min(items));
+        Zipfian {
+            items,
+            theta,
+            alpha: 1.0 / (1.0 - theta),
+            zetan,
+            eta: (1.0 - (2.0 / items as f64).powf(1.0 - theta)) / (1.0 - zeta2 / zetan),
+        }
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> usize {
+        let u: f64 = rng.gen();
+        let uz = u * self.zetan;
+        if uz < 1.0 {
+            return 0;
+        }
+        if uz < 1.0 + 0.5f64.powf(self.theta) {
+            return 1.min(self.items - 1);
+        }
+        let rank = (self.items as f64 * (self.eta * u - self.eta + 1.0).powf(self.alpha)) as usize;
+        rank.min(self.items - 1)
+    }
+}
+
+/// Shared, immutable description of what each worker should do.
+pub struct Workload {
+    pub keys: usize,
+    pub distribution: Distribution,
+    pub read_ratio: f64,
+    zipfian: Option<Zipfian>,
+    cursor: AtomicUsize,
+}
+
+impl Workload {
+    pub fn new(keys: usize, distribution: Distribution, read_ratio: f64, zipf_theta: f64) -> Self {
+        let zipfian =
+            (distribution == Distribution::Zipfian).then(|| Zipfian::new(keys, zipf_theta));
+        Workload {
+            keys,
+            distribution,
+            read_ratio,
+            zipfian,
+            cursor: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn next_key(&self, rng: &mut SmallRng) -> usize {
+        match (&self.zipfian, self.distribution) {
+            (Some(zipfian), _) => zipfian.sample(rng),
+            (None, Distribution::Sequential) => {
+                self.cursor.fetch_add(1, Ordering::Relaxed) % self.keys
+            }
+            (None, _) => rng.gen_range(0..self.keys),
+        }
+    }
+
+    pub fn next_op(&self, rng: &mut SmallRng) -> Op {
+        if rng.gen_bool(self.read_ratio) {
+            Op::Get
+        } else {
+            Op::Set
+        }
+    }
+}
+
+/// Key format shared with `Ocis.Perf` so both tools hit the same key space.
+pub fn key_name(index: usize) -> String {
+    format!("srv-key-{index:08}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn zipfian_stays_in_range_and_favours_low_ranks() {
+        let zipfian = Zipfian::new(1000, 0.99);
+        let mut rng = SmallRng::seed_from_u64(7);
+        let mut hot = 0;
+        for _ in 0..10_000 {
+            let rank = zipfian.sample(&mut rng);
+            assert!(rank < 1000);
+            if rank < 10 {
+                hot += 1;
+            }
+        }
+        // Ten of a thousand keys should take far more than 1% of traffic.
+        assert!(hot > 2_500, "hot keys got {hot} of 10000 samples");
+    }
+
+    #[test]
+    fn zipfian_handles_single_key() {
+        let zipfian = Zipfian::new(1, 0.99);
+        let mut rng = SmallRng::seed_from_u64(1);
+        assert!((0..100).all(|_| zipfian.sample(&mut rng) == 0));
+    }
+
+    #[test]
+    fn sequential_wraps_around() {
+        let workload = Workload::new(3, Distribution::Sequential, 0.0, 0.99);
+        let mut rng = SmallRng::seed_from_u64(1);
+        let keys: Vec<_> = (0..5).map(|_| workload.next_key(&mut rng)).collect();
+        assert_eq!(keys, [0, 1, 2, 0, 1]);
+    }
+}
@@ -0,0 +1,152 @@
+//! Command-line parsing for REPL and script input.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Get(String),
+    Set(String, String),
+    Del(String),
+    Exists(String),
+    Ping,
+    Info,
+    Help,
+    Quit,
+}
+
+pub const HELP: &str = "\
+GET <key>           Print the value stored at key
+SET <key> <value>   Store value at key (decoded with --input)
+DEL <key>           Delete key
+EXISTS <key>        Print 1 if key exists, else 0
+PING                Check that the server is answering
+INFO                Print server statistics
+HELP                Show this help
+QUIT                Exit";
+
+/// Parse one line. Returns `Ok(None)` for blank lines and `#` comments.
+pub fn parse(line: &str) -> Result<Option<Command>, String> {
+    from_args(&tokenize(line)?)
+}
+
+/// Build a command from already split words, e.g. trailing CLI arguments.
+pub fn from_args(args: &[String]) -> Result<Option<Command>, String> {
+    let Some((name, rest)) = args.split_first() else {
+        return Ok(None);
+    };
+
+    let command = match (name.to_ascii_uppercase().as_str(), rest) {
+        ("GET", [key]) => Command::Get(key.clone()),
+        ("SET", [key, value]) => Command::Set(key.clone(), value.clone()),
+        ("DEL" | "DELETE", [key]) => Command::Del(key.clone()),
+        ("EXISTS", [key]) => Command::Exists(key.clone()),
+        ("PING", []) => Command::Ping,
+        ("INFO", []) => Command::Info,
+        ("HELP", []) => Command::Help,
+        ("QUIT" | "EXIT", []) => Command::Quit,
+        (
+            "GET" | "SET" | "DEL" | "DELETE" | "EXISTS" | "PING" | "INFO" | "HELP" | "QUIT"
+            | "EXIT",
+            _,
+        ) => return Err(format!("wrong number of arguments for '{name}'")),
+        _ => return Err(format!("unknown command '{name}'")),
+    };
+    Ok(Some(command))
+}
+
+/// Split a line into words. Single and double quotes group words; inside
+/// double quotes `\"` and `\\` are unescaped. An unquoted `#` starts a comment.
+fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        match chars.peek() {
+            None | Some('#') => break,
+            Some(_) => {}
+        }
+
+        let mut word = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            chars.next();
+            match c {
+                '\'' => loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated quote".into()),
+                    }
+                },
+                '"' => loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated quote".into()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated quote".into()),
+                    }
+                },
+                c => word.push(c),
+            }
+        }
+        args.push(word);
+    }
+
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands_case_insensitively() {
+        assert_eq!(parse("get k").unwrap(), Some(Command::Get("k".into())));
+        assert_eq!(
+            parse("SET k 'a b'").unwrap(),
+            Some(Command::Set("k".into(), "a b".into()))
+        );
+        assert_eq!(parse("Del k").unwrap(), Some(Command::Del("k".into())));
+        assert_eq!(
+            parse("exists k").unwrap(),
+            Some(Command::Exists("k".into()))
+        );
+        assert_eq!(parse("ping").unwrap(), Some(Command::Ping));
+        assert_eq!(parse("INFO").unwrap(), Some(Command::Info));
+    }
+
+    #[test]
+    fn skips_blank_lines_and_comments() {
+        assert_eq!(parse("   ").unwrap(), None);
+        assert_eq!(parse("# note").unwrap(), None);
+        assert_eq!(
+            parse("GET k # trailing").unwrap(),
+            Some(Command::Get("k".into()))
+        );
+    }
+
+    #[test]
+    fn unescapes_double_quotes() {
+        assert_eq!(
+            parse(r#"SET k "say \"hi\"""#).unwrap(),
+            Some(Command::Set("k".into(), "say \"hi\"".into()))
+        );
+    }
+
+    #[test]
+    fn reports_arity_and_unknown_commands() {
+        assert!(parse("GET").is_err());
+        assert!(parse("SET k").is_err());
+        assert!(parse("PING now").is_err());
+        assert!(parse("FLUSHALL").is_err());
+        assert!(parse("GET 'k").is_err());
+    }
+}
@@ -0,0 +1,64 @@
+//! Value encodings for command input and output.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Encoding {
+    /// UTF-8 text; invalid sequences are shown escaped on output
+    #[default]
+    Utf8,
+    /// Hexadecimal, e.g. `68656c6c6f`
+    Hex,
+    /// Standard base64 with padding
+    Base64,
+}
+
+impl Encoding {
+    pub fn decode(self, text: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Encoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            Encoding::Hex => hex::decode(text).map_err(|e| format!("invalid hex value: {e}")),
+            Encoding::Base64 => BASE64
+                .decode(text)
+                .map_err(|e| format!("invalid base64 value: {e}")),
+        }
+    }
+
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Utf8 => match std::str::from_utf8(bytes) {
+                Ok(text) => format!("{text:?}"),
+                Err(_) => format!("\"{}\"", bytes.escape_ascii()),
+            },
+            Encoding::Hex => hex::encode(bytes),
+            Encoding::Base64 => BASE64.encode(bytes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_binary_values() {
+        let bytes = [0u8, 1, 0xfe, 0xff];
+        for encoding in [Encoding::Hex, Encoding::Base64] {
+            assert_eq!(encoding.decode(&encoding.encode(&bytes)).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn utf8_output_escapes_invalid_bytes() {
+        assert_eq!(Encoding::Utf8.encode(b"hi"), "\"hi\"");
+        assert_eq!(Encoding::Utf8.encode(&[0x68, 0xff]), "\"h\\xff\"");
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(Encoding::Hex.decode("zz").is_err());
+        assert!(Encoding::Base64.decode("!!").is_err());
+    }
+}
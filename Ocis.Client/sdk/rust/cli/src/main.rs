@@ -0,0 +1,328 @@
+//! `ocis-cli`: interactive and scriptable client for Ocis servers.
+//!
+//! Exit codes: `0` success, `1` the last command hit a missing key,
+//! `2` any error (usage, connection, protocol or server error).
+
+mod command;
+mod encoding;
+
+use std::io::{self, BufRead, IsTerminal};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::{ArgAction, Parser};
+use ocis_client::blocking::Client;
+use ocis_client::info::ServerInfo;
+use ocis_client::Error;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+use crate::command::{Command, HELP};
+use crate::encoding::Encoding;
+
+const HISTORY_FILE: &str = ".ocis_cli_history";
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "ocis-cli",
+    version,
+    about = "Command-line client for Ocis servers.",
+    disable_help_flag = true
+)]
+struct Args {
+    /// Server host
+    #[arg(short = 'h', long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Server port
+    #[arg(short = 'p', long, default_value_t = 7379)]
+    port: u16,
+
+    /// How SET values are decoded
+    #[arg(short = 'i', long, value_enum, default_value_t)]
+    input: Encoding,
+
+    /// How GET values are printed
+    #[arg(short = 'o', long, value_enum, default_value_t)]
+    output: Encoding,
+
+    /// Read commands from stdin, one per line, stopping at the first error
+    #[arg(long)]
+    eval: bool,
+
+    /// Token to AUTH with on every connection
+    #[arg(short = 'a', long)]
+    auth: Option<String>,
+
+    /// Socket read/write timeout in seconds
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+
+    /// Print help
+    #[arg(long, action = ArgAction::Help)]
+    help: Option<bool>,
+
+    /// Run a single command and exit, e.g. `ocis-cli GET my-key`
+    #[arg(trailing_var_arg = true)]
+    command: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Status {
+    Success,
+    NotFound,
+    Failure,
+}
+
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Success => ExitCode::SUCCESS,
+            Status::NotFound => ExitCode::from(1),
+            Status::Failure => ExitCode::from(2),
+        }
+    }
+}
+
+/// Result of one command as seen by the user.
+#[derive(Debug, PartialEq, Eq)]
+enum Reply {
+    Ok,
+    Value(Vec<u8>),
+    NotFound,
+    Exists(bool),
+    Pong,
+    Info(ServerInfo),
+}
+
+/// Lazily (re)connecting session shared by all modes.
+struct Session {
+    args: Args,
+    connection: Option<Client>,
+}
+
+impl Session {
+    fn execute(&mut self, command: &Command) -> Status {
+        match command {
+            Command::Help => {
+                println!("{HELP}");
+                return Status::Success;
+            }
+            Command::Quit => return Status::Success,
+            _ => {}
+        }
+
+        match self.dispatch(command) {
+            Ok(Reply::Ok) => {
+                println!("OK");
+                Status::Success
+            }
+            Ok(Reply::Value(value)) => {
+                println!("{}", self.args.output.encode(&value));
+                Status::Success
+            }
+            Ok(Reply::Exists(exists)) => {
+                println!("(integer) {}", u8::from(exists));
+                if exists {
+                    Status::Success
+                } else {
+                    Status::NotFound
+                }
+            }
+            Ok(Reply::Pong) => {
+                println!("PONG");
+                Status::Success
+            }
+            Ok(Reply::Info(info)) => {
+                print_info(&info);
+                Status::Success
+            }
+            Ok(Reply::NotFound) => {
+                println!("(nil)");
+                Status::NotFound
+            }
+            Err(message) => {
+                eprintln!("(error) {message}");
+                Status::Failure
+            }
+        }
+    }
+
+    fn dispatch(&mut self, command: &Command) -> Result<Reply, String> {
+        let value = match command {
+            Command::Set(_, value) => Some(self.args.input.decode(value)?),
+            _ => None,
+        };
+
+        let connection = match &mut self.connection {
+            Some(connection) => connection,
+            slot => slot.insert(connect(&self.args).map_err(|e| {
+                format!(
+                    "could not connect to {}:{}: {e}",
+                    self.args.host, self.args.port
+                )
+            })?),
+        };
+
+        let reply = match command {
+            Command::Get(key) => connection
+                .get(key)
+                .map(|value| value.map_or(Reply::NotFound, Reply::Value)),
+            Command::Set(key, _) => connection
+                .set(key, &value.unwrap_or_default())
+                .map(|()| Reply::Ok),
+            Command::Del(key) => connection.delete(key).map(|()| Reply::Ok),
+            Command::Exists(key) => connection.exists(key).map(Reply::Exists),
+            Command::Ping => connection.ping().map(|()| Reply::Pong),
+            Command::Info => connection.info().map(Reply::Info),
+            Command::Help | Command::Quit => unreachable!("handled locally"),
+        };
+        reply.map_err(|e| {
+            if e.is_fatal() {
+                // Drop the connection so the next command reconnects.
+                self.connection = None;
+            }
+            e.to_string()
+        })
+    }
+}
+
+/// Print `info` as `field:value` lines.
+fn print_info(info: &ServerInfo) {
+    let durability = info
+        .durability
+        .map_or_else(|| "unknown".to_string(), |d| format!("{d:?}"));
+    println!("format:{}", info.format);
+    println!("durability:{durability}");
+    println!("memtable_bytes:{}", info.memtable_bytes);
+    println!("wal_bytes:{}", info.wal_bytes);
+    println!("queue_depth:{}", info.queue_depth);
+    for (level, count) in info.sstables_per_level.iter().enumerate() {
+        println!("sstables_l{level}:{count}");
+    }
+    if let Some(limits) = &info.limits {
+        println!("max_packet_size:{}", limits.max_packet_size);
+        println!("max_key_length:{}", limits.max_key_length);
+        println!("max_value_length:{}", limits.max_value_length);
+    }
+}
+
+fn connect(args: &Args) -> io::Result<Client> {
+    let addr = (args.host.as_str(), args.port);
+    let client = match &args.auth {
+        Some(token) => Client::connect_with_token(addr, token.as_bytes()),
+        None => Client::connect(addr),
+    }
+    .map_err(into_io)?;
+    client
+        .set_timeout(Some(Duration::from_secs(args.timeout)))
+        .map_err(into_io)?;
+    Ok(client)
+}
+
+fn into_io(e: Error) -> io::Error {
+    match e {
+        Error::Io(e) => e,
+        other => io::Error::other(other.to_string()),
+    }
+}
+
+/// Run newline-separated commands; the exit status is that of the last
+/// command, and the script stops at the first failure.
+fn run_script(session: &mut Session, input: impl BufRead) -> Status {
+    let mut status = Status::Success;
+    for (number, line) in input.lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("(error) reading stdin: {e}");
+                return Status::Failure;
+            }
+        };
+        status = match command::parse(&line) {
+            Ok(None) => continue,
+            Ok(Some(Command::Quit)) => break,
+            Ok(Some(command)) => session.execute(&command),
+            Err(message) => {
+                eprintln!("(error) line {}: {message}", number + 1);
+                Status::Failure
+            }
+        };
+        if status == Status::Failure {
+            break;
+        }
+    }
+    status
+}
+
+fn run_repl(session: &mut Session) -> Status {
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("(error) cannot start line editor: {e}");
+            return Status::Failure;
+        }
+    };
+    let history = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+    if let Some(path) = &history {
+        let _ = editor.load_history(path);
+    }
+
+    let prompt = format!("{}:{}> ", session.args.host, session.args.port);
+    let mut status = Status::Success;
+    loop {
+        let line = match editor.readline(&prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("(error) {e}");
+                status = Status::Failure;
+                break;
+            }
+        };
+        if !line.trim().is_empty() {
+            let _ = editor.add_history_entry(line.as_str());
+        }
+        status = match command::parse(&line) {
+            Ok(None) => continue,
+            Ok(Some(Command::Quit)) => break,
+            Ok(Some(command)) => session.execute(&command),
+            Err(message) => {
+                eprintln!("(error) {message}");
+                Status::Failure
+            }
+        };
+    }
+
+    if let Some(path) = &history {
+        let _ = editor.save_history(path);
+    }
+    status
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let one_shot = match command::from_args(&args.command) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("(error) {message}");
+            return Status::Failure.into();
+        }
+    };
+    let eval = args.eval;
+    let mut session = Session {
+        args,
+        connection: None,
+    };
+
+    let status = if let Some(command) = one_shot {
+        session.execute(&command)
+    } else if eval || !io::stdin().is_terminal() {
+        run_script(&mut session, io::stdin().lock())
+    } else {
+        run_repl(&mut session)
+    };
+    status.into()
+}
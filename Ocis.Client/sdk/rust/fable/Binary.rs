@@ -8,6 +8,7 @@ pub mod Ocis {
                 use super::*;
                 use fable_library_rust::Encoding_::get_UTF8;
                 use fable_library_rust::NativeArray_::Array;
+                use fable_library_rust::NativeArray_::get_Count;
                 use fable_library_rust::String_::string;
                 fn getUint32(b0: u8, b1: u8, b2: u8, b3: u8) -> u32 {
                     (((b0 as u32) | ((b1 as u32) << 8_i32)) |
@@ -40,6 +41,36 @@ pub mod Ocis {
                 pub fn readByte(buffer: Array<u8>, offset: i32) -> u8 {
                     buffer[offset].clone()
                 }
+                fn isInBounds(buffer: Array<u8>, offset: i32, size: i32)
+                 -> bool {
+                    if (offset) >= 0_i32 {
+                        (offset) <= ((get_Count(buffer)) - (size))
+                    } else { false }
+                }
+                pub fn tryReadUInt32LittleEndian(buffer: Array<u8>,
+                                                 offset: i32) -> Option<u32> {
+                    if Ocis::Client::SDK::Binary::isInBounds(buffer.clone(),
+                                                             offset, 4_i32) {
+                        Some(Ocis::Client::SDK::Binary::readUInt32LittleEndian(buffer,
+                                                                               offset))
+                    } else { None::<u32> }
+                }
+                pub fn tryReadInt32LittleEndian(buffer: Array<u8>,
+                                                offset: i32) -> Option<i32> {
+                    if Ocis::Client::SDK::Binary::isInBounds(buffer.clone(),
+                                                             offset, 4_i32) {
+                        Some(Ocis::Client::SDK::Binary::readInt32LittleEndian(buffer,
+                                                                              offset))
+                    } else { None::<i32> }
+                }
+                pub fn tryReadByte(buffer: Array<u8>, offset: i32)
+                 -> Option<u8> {
+                    if Ocis::Client::SDK::Binary::isInBounds(buffer.clone(),
+                                                             offset, 1_i32) {
+                        Some(Ocis::Client::SDK::Binary::readByte(buffer,
+                                                                 offset))
+                    } else { None::<u8> }
+                }
                 pub fn writeUInt32LittleEndian(value: u32, buffer: Array<u8>,
                                                offset: i32) {
                     buffer.get_mut()[offset as usize] = value as u8;
@@ -33,6 +33,17 @@ pub mod Ocis {
                 use crate::Ocis::Server::ProtocolSpec::RequestPacket;
                 use crate::Ocis::Server::ProtocolSpec::ResponsePacket;
                 use fable_library_rust::System::Exception;
+                pub fn HasConsistentLengths(totalPacketLength: i32,
+                                            firstLength: i32,
+                                            secondLength: i32) -> bool {
+                    if if (firstLength) >= 0_i32 {
+                           (secondLength) >= 0_i32
+                       } else { false } {
+                        (totalPacketLength as i64) ==
+                            (((18_i32 as i64) + (firstLength as i64)) +
+                                 (secondLength as i64))
+                    } else { false }
+                }
                 pub fn TryParseRequestHeader(buffer: Array<u8>)
                  -> Option<LrcPtr<RequestPacket>> {
                     if (get_Count(buffer.clone())) < 18_i32 {
@@ -88,8 +99,13 @@ pub mod Ocis {
                         Some(matchValue_0_0) => {
                             let header: LrcPtr<RequestPacket> =
                                 matchValue_0_0.clone();
-                            if (get_Count(buffer.clone())) >=
-                                   (header.TotalPacketLength) {
+                            if !Ocis::Client::SDK::Protocol::HasConsistentLengths(header.TotalPacketLength,
+                                                                                  header.KeyLength,
+                                                                                  header.ValueLength)
+                               {
+                                None::<LrcPtr<RequestPacket>>
+                            } else if (get_Count(buffer.clone())) >=
+                                          (header.TotalPacketLength) {
                                 try_catch(||
                                               Some(LrcPtr::new(RequestPacket{MagicNumber:
                                                                                  header.MagicNumber,
@@ -404,13 +420,9 @@ pub mod Ocis {
                                                                      } {
                                                                       LrcPtr::new(Ocis::Client::SDK::Protocol::ParseResult_1::ParseError::<LrcPtr<ResponsePacket>>(string("Invalid length field")))
                                                                   } else {
-                                                                      if (totalPacketLength)
-                                                                             !=
-                                                                             ((18_i32
-                                                                                   +
-                                                                                   (valueLength))
-                                                                                  +
-                                                                                  (errorMessageLength))
+                                                                      if !Ocis::Client::SDK::Protocol::HasConsistentLengths(totalPacketLength,
+                                                                                                                            valueLength,
+                                                                                                                            errorMessageLength)
                                                                          {
                                                                           LrcPtr::new(Ocis::Client::SDK::Protocol::ParseResult_1::ParseError::<LrcPtr<ResponsePacket>>(string("Packet length mismatch")))
                                                                       } else {
@@ -53,6 +53,11 @@ pub mod Ocis {
                                                                               key,
                                                                               None::<Array<u8>>))
                 }
+                pub fn createExistsRequest(key: string) -> Array<u8> {
+                    SerializeRequest(Ocis::Client::SDK::Request::createPacket(5_i32,
+                                                                              key,
+                                                                              None::<Array<u8>>))
+                }
             }
         }
     }
@@ -111,6 +111,33 @@ mod module_8ab04c88 {
                             }
                         }
                     }
+                    /// Map an EXISTS response: NotFound is a successful `false`.
+                    pub fn toClientResultExists(parseResult:
+                                                    LrcPtr<ParseResult_1<LrcPtr<ResponsePacket>>>)
+                     -> LrcPtr<Ocis::Client::SDK::Response::ClientResult_1<bool>> {
+                        match parseResult.as_ref() {
+                            ParseResult_1::ParseError(parseResult_1_0) =>
+                            LrcPtr::new(Ocis::Client::SDK::Response::ClientResult_1::Error::<bool>(parseResult_1_0.clone())),
+                            ParseResult_1::InsufficientData =>
+                            LrcPtr::new(Ocis::Client::SDK::Response::ClientResult_1::Error::<bool>(string("Insufficient data"))),
+                            ParseResult_1::ParseSuccess(parseResult_0_0) => {
+                                let response: LrcPtr<ResponsePacket> =
+                                    parseResult_0_0.clone();
+                                let matchValue: u8 = response.StatusCode;
+                                match &matchValue {
+                                    0_u8 =>
+                                    LrcPtr::new(Ocis::Client::SDK::Response::ClientResult_1::Success::<bool>(true)),
+                                    1_u8 =>
+                                    LrcPtr::new(Ocis::Client::SDK::Response::ClientResult_1::Success::<bool>(false)),
+                                    2_u8 =>
+                                    LrcPtr::new(Ocis::Client::SDK::Response::ClientResult_1::Error::<bool>(defaultValue(string("Unknown error"),
+                                                                                                                        response.ErrorMessage.clone()))),
+                                    _ =>
+                                    LrcPtr::new(Ocis::Client::SDK::Response::ClientResult_1::Error::<bool>(string("Invalid status code"))),
+                                }
+                            }
+                        }
+                    }
                 }
             }
         }
@@ -0,0 +1,27 @@
+#![no_main]
+
+use fable_library_rust::NativeArray_::array_from;
+use libfuzzer_sys::fuzz_target;
+use ocis_client_fable::Ocis::Client::SDK::Binary::{
+    tryReadByte, tryReadInt32LittleEndian, tryReadUInt32LittleEndian,
+};
+
+fuzz_target!(|input: (i32, Vec<u8>)| {
+    let (offset, data) = input;
+    let buffer = array_from(data.clone());
+    let at = |size: usize| {
+        usize::try_from(offset)
+            .ok()
+            .and_then(|start| data.get(start..start.checked_add(size)?))
+    };
+
+    assert_eq!(tryReadByte(buffer.clone(), offset), at(1).map(|b| b[0]));
+    assert_eq!(
+        tryReadInt32LittleEndian(buffer.clone(), offset),
+        at(4).map(|b| i32::from_le_bytes(b.try_into().unwrap()))
+    );
+    assert_eq!(
+        tryReadUInt32LittleEndian(buffer, offset),
+        at(4).map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+    );
+});
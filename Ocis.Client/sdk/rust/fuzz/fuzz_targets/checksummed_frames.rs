@@ -0,0 +1,57 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ocis_client::protocol::{
+    add_checksum, deserialize_response, encode_frame_with_flags, has_checksum, request_id,
+    serialize_response, success_response, try_parse_request_packet, verify_checksum, ParseResult,
+};
+use ocis_client::request::create_set_request;
+use ocis_client::spec::LATEST_VERSION;
+
+fuzz_target!(|input: (String, Vec<u8>, u64, u8, usize)| {
+    let (key, value, id, flags, bit) = input;
+
+    let request = add_checksum(encode_frame_with_flags(
+        create_set_request(&key, &value),
+        LATEST_VERSION,
+        id,
+        flags,
+    ));
+    assert!(has_checksum(&request) && verify_checksum(&request));
+    assert_eq!(request_id(&request), Some(id));
+    let packet = try_parse_request_packet(&request).expect("checksummed request must parse");
+    assert_eq!(packet.key, key.as_bytes());
+    assert_eq!(packet.value.unwrap_or_default(), value);
+
+    let response = add_checksum(encode_frame_with_flags(
+        serialize_response(&success_response(Some(&value))),
+        LATEST_VERSION,
+        id,
+        flags,
+    ));
+    let ParseResult::ParseSuccess(packet) = deserialize_response(&response) else {
+        panic!("checksummed response must parse");
+    };
+    assert_eq!(packet.value.unwrap_or_default(), value);
+
+    // CRC32C catches every single-bit error, wherever it lands: in the
+    // header, the flags byte, the body or the trailer itself.
+    let mut request = request;
+    let bit = bit % (request.len() * 8);
+    request[bit / 8] ^= 1 << (bit % 8);
+    assert!(
+        try_parse_request_packet(&request).is_none(),
+        "bit {bit} flipped unnoticed"
+    );
+
+    let mut response = response;
+    let bit = bit % (response.len() * 8);
+    response[bit / 8] ^= 1 << (bit % 8);
+    assert!(
+        !matches!(
+            deserialize_response(&response),
+            ParseResult::ParseSuccess(_)
+        ),
+        "bit {bit} flipped unnoticed"
+    );
+});
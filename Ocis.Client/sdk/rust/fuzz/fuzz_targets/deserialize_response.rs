@@ -0,0 +1,43 @@
+#![no_main]
+
+use fable_library_rust::NativeArray_::array_from;
+use libfuzzer_sys::fuzz_target;
+use ocis_client_fable::Ocis::Client::SDK::Protocol::{DeserializeResponse, ParseResult_1};
+use ocis_client_fuzz::{
+    declared_length, largest_allocation, read_i32, ALLOCATION_SLACK, HEADER_SIZE,
+    MAX_UTF8_EXPANSION,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let buffer = array_from(data.to_vec());
+    let (result, peak) = largest_allocation(|| DeserializeResponse(buffer));
+
+    let limit = declared_length(data).min(data.len());
+    assert!(
+        peak <= limit * MAX_UTF8_EXPANSION + ALLOCATION_SLACK,
+        "allocated {peak} bytes for a frame declaring {limit}"
+    );
+
+    match result.as_ref() {
+        ParseResult_1::ParseSuccess(packet) => {
+            // Everything reported must come straight from the header and fit
+            // inside the declared frame.
+            assert_eq!(packet.TotalPacketLength, read_i32(data, 6));
+            assert_eq!(packet.ValueLength, read_i32(data, 10));
+            assert_eq!(packet.ErrorMessageLength, read_i32(data, 14));
+            let total = packet.TotalPacketLength as usize;
+            assert!(total <= data.len());
+            assert_eq!(
+                total,
+                HEADER_SIZE + packet.ValueLength as usize + packet.ErrorMessageLength as usize
+            );
+            let value_len = packet.Value.as_ref().map_or(0, |v| v.len());
+            assert_eq!(value_len, packet.ValueLength as usize);
+            assert_eq!(packet.ErrorMessage.is_some(), packet.ErrorMessageLength > 0);
+        }
+        ParseResult_1::InsufficientData => {
+            assert!(data.len() < HEADER_SIZE || data.len() < declared_length(data));
+        }
+        ParseResult_1::ParseError(_) => {}
+    }
+});
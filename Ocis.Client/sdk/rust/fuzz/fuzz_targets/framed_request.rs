@@ -0,0 +1,40 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ocis_client::protocol::{
+    frame_version, has_checksum, request_id, try_parse_request_header, try_parse_request_packet,
+    verify_checksum,
+};
+use ocis_client::spec::{PROTOCOL_VERSION, SUPPORTED_VERSIONS};
+use ocis_client_fuzz::read_i32;
+
+fuzz_target!(|data: &[u8]| {
+    // Any version, with or without a checksum, goes through the mapping
+    // onto the v1 layout before the generated codec sees it.
+    let header = try_parse_request_header(data);
+    let Some(packet) = try_parse_request_packet(data) else {
+        return;
+    };
+
+    assert_eq!(frame_version(data), Some(packet.version));
+    assert!(SUPPORTED_VERSIONS.contains(&packet.version));
+    assert_eq!(packet.total_packet_length, read_i32(data, 6));
+    assert_eq!(packet.key.len() as i32, packet.key_length);
+    assert_eq!(
+        packet.value.as_ref().map_or(0, |value| value.len() as i32),
+        packet.value_length
+    );
+    assert_eq!(
+        request_id(data).is_some(),
+        packet.version > PROTOCOL_VERSION
+    );
+    if has_checksum(data) {
+        assert!(verify_checksum(data), "parsed a frame failing its checksum");
+    }
+
+    let header = header.expect("full parse succeeded but header parse failed");
+    assert_eq!(header.version, packet.version);
+    assert_eq!(header.command_type, packet.command_type);
+    assert_eq!(header.key_length, packet.key_length);
+    assert_eq!(header.value_length, packet.value_length);
+});
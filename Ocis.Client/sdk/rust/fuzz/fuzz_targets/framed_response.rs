@@ -0,0 +1,30 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ocis_client::protocol::{
+    deserialize_response, frame_version, has_checksum, request_id, verify_checksum, ParseResult,
+};
+use ocis_client::spec::{PROTOCOL_VERSION, SUPPORTED_VERSIONS};
+use ocis_client_fuzz::read_i32;
+
+fuzz_target!(|data: &[u8]| {
+    let ParseResult::ParseSuccess(packet) = deserialize_response(data) else {
+        return;
+    };
+
+    assert_eq!(frame_version(data), Some(packet.version));
+    assert!(SUPPORTED_VERSIONS.contains(&packet.version));
+    assert_eq!(packet.total_packet_length, read_i32(data, 6));
+    assert!(packet.total_packet_length as usize <= data.len());
+    assert_eq!(
+        packet.value.as_ref().map_or(0, |value| value.len() as i32),
+        packet.value_length
+    );
+    assert_eq!(
+        request_id(data).is_some(),
+        packet.version > PROTOCOL_VERSION
+    );
+    if has_checksum(data) {
+        assert!(verify_checksum(data), "parsed a frame failing its checksum");
+    }
+});
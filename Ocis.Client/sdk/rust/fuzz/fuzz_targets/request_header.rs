@@ -0,0 +1,27 @@
+#![no_main]
+
+use fable_library_rust::NativeArray_::array_from;
+use libfuzzer_sys::fuzz_target;
+use ocis_client_fable::Ocis::Client::SDK::Protocol::TryParseRequestHeader;
+use ocis_client_fuzz::{largest_allocation, read_i32, ALLOCATION_SLACK, HEADER_SIZE};
+
+fuzz_target!(|data: &[u8]| {
+    let buffer = array_from(data.to_vec());
+    let (header, peak) = largest_allocation(|| TryParseRequestHeader(buffer));
+
+    // The header parse never looks past the fixed header.
+    assert!(
+        peak <= ALLOCATION_SLACK,
+        "allocated {peak} bytes for a header"
+    );
+
+    if let Some(header) = header {
+        assert!(data.len() >= HEADER_SIZE);
+        assert_eq!(header.CommandType, data[5] as i32);
+        assert_eq!(header.TotalPacketLength, read_i32(data, 6));
+        assert_eq!(header.KeyLength, read_i32(data, 10));
+        assert_eq!(header.ValueLength, read_i32(data, 14));
+        assert!(header.Key.is_empty());
+        assert!(header.Value.is_none());
+    }
+});
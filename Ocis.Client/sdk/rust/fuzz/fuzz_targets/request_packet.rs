@@ -0,0 +1,44 @@
+#![no_main]
+
+use fable_library_rust::NativeArray_::array_from;
+use libfuzzer_sys::fuzz_target;
+use ocis_client_fable::Ocis::Client::SDK::Protocol::{
+    TryParseRequestHeader, TryParseRequestPacket,
+};
+use ocis_client_fuzz::{declared_length, largest_allocation, ALLOCATION_SLACK, HEADER_SIZE};
+
+fuzz_target!(|data: &[u8]| {
+    let buffer = array_from(data.to_vec());
+    let (packet, peak) = largest_allocation(|| TryParseRequestPacket(buffer.clone()));
+
+    let limit = declared_length(data).min(data.len());
+    assert!(
+        peak <= limit + ALLOCATION_SLACK,
+        "allocated {peak} bytes for a frame declaring {limit}"
+    );
+
+    let header = TryParseRequestHeader(buffer);
+    let Some(packet) = packet else {
+        return;
+    };
+
+    // A full parse implies a header parse that agrees with it.
+    let header = header.expect("full parse succeeded but header parse failed");
+    assert_eq!(header.MagicNumber, packet.MagicNumber);
+    assert_eq!(header.Version, packet.Version);
+    assert_eq!(header.CommandType, packet.CommandType);
+    assert_eq!(header.TotalPacketLength, packet.TotalPacketLength);
+    assert_eq!(header.KeyLength, packet.KeyLength);
+    assert_eq!(header.ValueLength, packet.ValueLength);
+
+    let total = packet.TotalPacketLength as usize;
+    assert!(total <= data.len());
+    assert_eq!(packet.Key.len(), packet.KeyLength as usize);
+    let value_len = packet.Value.as_ref().map_or(0, |v| v.len());
+    assert_eq!(value_len, packet.ValueLength as usize);
+    assert_eq!(total, HEADER_SIZE + packet.Key.len() + value_len);
+    assert_eq!(
+        packet.Key.to_vec(),
+        data[HEADER_SIZE..HEADER_SIZE + packet.Key.len()]
+    );
+});
@@ -0,0 +1,65 @@
+//! Shared helpers for the fuzz targets.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+pub const HEADER_SIZE: usize = 18;
+
+/// Allocations that are not proportional to the input, such as the packet
+/// records themselves and fixed parse error messages.
+pub const ALLOCATION_SLACK: usize = 256;
+
+/// Lossy UTF-8 decoding of an error message turns each invalid byte into
+/// a three-byte U+FFFD, so a decoded message can outgrow its frame.
+pub const MAX_UTF8_EXPANSION: usize = 3;
+
+/// Records the largest single allocation made while armed, so targets can
+/// check that the decoders never size a buffer from an unchecked length.
+pub struct PeakAllocator;
+
+static ARMED: AtomicBool = AtomicBool::new(false);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if ARMED.load(Ordering::Relaxed) {
+            PEAK.fetch_max(layout.size(), Ordering::Relaxed);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if ARMED.load(Ordering::Relaxed) {
+            PEAK.fetch_max(new_size, Ordering::Relaxed);
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAllocator = PeakAllocator;
+
+/// Run `f` and return its result with the largest allocation it made.
+pub fn largest_allocation<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    PEAK.store(0, Ordering::Relaxed);
+    ARMED.store(true, Ordering::Relaxed);
+    let result = f();
+    ARMED.store(false, Ordering::Relaxed);
+    (result, PEAK.load(Ordering::Relaxed))
+}
+
+/// The `TotalPacketLength` a frame declares, or 0 if it is too short to
+/// declare one or the field is negative.
+pub fn declared_length(data: &[u8]) -> usize {
+    data.get(6..10)
+        .map(|b| i32::from_le_bytes(b.try_into().unwrap()))
+        .map_or(0, |n| n.max(0) as usize)
+}
+
+pub fn read_i32(data: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
@@ -0,0 +1,520 @@
+//! Append-only log storage.
+//!
+//! Every write is appended to a single file as
+//! `[op: u8][key_len: u32 LE][value_len: u32 LE][key][value]` and the full
+//! key space is indexed in memory. Expiring writes prefix the value with
+//! the deadline in milliseconds since the Unix epoch (`u64` LE).
+//!
+//! On open the log is replayed; a torn record at the tail (crash
+//! mid-append) is truncated away. If most of the log turns out to be
+//! superseded records, it is then rewritten with only the live entries.
+//! Compaction runs only at open; a long-running server's log keeps growing
+//! until the next restart.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use ocis_client::batch::{BatchOp, WriteBatch};
+use ocis_client::durability::Durability;
+use ocis_client::expiry::{from_unix_millis, unix_millis};
+use ocis_client::info::{ServerInfo, INFO_FORMAT};
+use ocis_client::scan::{Scan, ScanPage};
+
+use crate::storage::{index_bytes, live_entry, scan_index, Entry, Index, Storage};
+
+const OP_SET: u8 = 1;
+const OP_DELETE: u8 = 3;
+const OP_SET_EXPIRING: u8 = 4;
+/// A group of SET and DELETE records, nested in the value, that replay
+/// applies all together or, if torn, not at all.
+const OP_BATCH: u8 = 5;
+const RECORD_HEADER_SIZE: usize = 9;
+
+/// Compact on open once the log holds this many more records than live keys.
+const COMPACTION_SLACK: u64 = 1024;
+
+struct Inner {
+    file: File,
+    index: Index,
+    records: u64,
+}
+
+/// Durable storage backed by an append-only log file.
+pub struct AppendLogStorage {
+    path: PathBuf,
+    sync: bool,
+    inner: Mutex<Inner>,
+}
+
+impl AppendLogStorage {
+    /// Open or create the log at `path`. With `sync` set, every write is
+    /// flushed to disk with `fdatasync` before it is acknowledged.
+    pub fn open(path: impl AsRef<Path>, sync: bool) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .append(true)
+            .open(&path)?;
+
+        let (index, records, valid_len) = replay(&mut file)?;
+        if valid_len < file.metadata()?.len() {
+            log::warn!(
+                "truncating torn tail of {} at offset {valid_len}",
+                path.display()
+            );
+            file.set_len(valid_len)?;
+        }
+
+        let storage = AppendLogStorage {
+            path,
+            sync,
+            inner: Mutex::new(Inner {
+                file,
+                index,
+                records,
+            }),
+        };
+
+        {
+            let mut inner = storage.inner.lock().unwrap();
+            if inner.records > inner.index.len() as u64 * 2 + COMPACTION_SLACK {
+                storage.compact(&mut inner)?;
+            }
+        }
+
+        Ok(storage)
+    }
+
+    /// Rewrite the log with one record per live key and swap it in.
+    fn compact(&self, inner: &mut Inner) -> io::Result<()> {
+        let tmp_path = self.path.with_extension("compact");
+        {
+            let mut tmp = File::create(&tmp_path)?;
+            for (key, entry) in &inner.index {
+                tmp.write_all(&encode_entry(key, entry))?;
+            }
+            tmp.sync_all()?;
+        }
+        fs::rename(&tmp_path, &self.path)?;
+
+        inner.file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(&self.path)?;
+        inner.records = inner.index.len() as u64;
+        log::info!(
+            "compacted {} to {} records",
+            self.path.display(),
+            inner.records
+        );
+        Ok(())
+    }
+
+    /// Append the record for `entry` and index it.
+    fn put(&self, inner: &mut Inner, key: &[u8], entry: Entry) -> io::Result<()> {
+        self.append(inner, &encode_entry(key, &entry))?;
+        inner.index.insert(key.to_vec(), entry);
+        Ok(())
+    }
+
+    fn append(&self, inner: &mut Inner, record: &[u8]) -> io::Result<()> {
+        inner.file.write_all(record)?;
+        if self.sync {
+            inner.file.sync_data()?;
+        }
+        inner.records += 1;
+        Ok(())
+    }
+}
+
+impl Storage for AppendLogStorage {
+    fn get(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(live_entry(&inner.index, key).map(|entry| entry.value.clone()))
+    }
+
+    fn set(&self, key: &[u8], value: &[u8]) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        self.put(&mut inner, key, Entry::new(value, None))
+    }
+
+    fn delete(&self, key: &[u8]) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.index.contains_key(key) {
+            return Ok(());
+        }
+        self.append(&mut inner, &encode(OP_DELETE, key, &[]))?;
+        inner.index.remove(key);
+        Ok(())
+    }
+
+    fn exists(&self, key: &[u8]) -> io::Result<bool> {
+        Ok(live_entry(&self.inner.lock().unwrap().index, key).is_some())
+    }
+
+    fn compare_and_swap(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> io::Result<bool> {
+        let mut inner = self.inner.lock().unwrap();
+        let current = live_entry(&inner.index, key);
+        if current.map(|entry| entry.value.as_slice()) != expected {
+            return Ok(false);
+        }
+        let expires_at = current.and_then(|entry| entry.expires_at);
+        match new {
+            Some(value) => self.put(&mut inner, key, Entry::new(value, expires_at))?,
+            None if expected.is_some() => {
+                self.append(&mut inner, &encode(OP_DELETE, key, &[]))?;
+                inner.index.remove(key);
+            }
+            None => {}
+        }
+        Ok(true)
+    }
+
+    fn scan(&self, scan: &Scan) -> io::Result<ScanPage> {
+        Ok(scan_index(&self.inner.lock().unwrap().index, scan))
+    }
+
+    fn set_with_expiry(&self, key: &[u8], value: &[u8], expires_at: SystemTime) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        self.put(&mut inner, key, Entry::new(value, Some(expires_at)))
+    }
+
+    fn expiry(&self, key: &[u8]) -> io::Result<Option<Option<SystemTime>>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(live_entry(&inner.index, key).map(|entry| entry.expires_at))
+    }
+
+    fn persist(&self, key: &[u8]) -> io::Result<bool> {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(entry) = live_entry(&inner.index, key) else {
+            return Ok(false);
+        };
+        if entry.expires_at.is_some() {
+            let entry = Entry::new(&entry.value, None);
+            self.put(&mut inner, key, entry)?;
+        }
+        Ok(true)
+    }
+
+    /// The whole batch is appended as one record, so it is written with one
+    /// write and one flush and replays atomically.
+    fn write_batch(&self, batch: &WriteBatch) -> io::Result<()> {
+        let mut nested = Vec::new();
+        for op in batch.ops() {
+            match op {
+                BatchOp::Set { key, value } => nested.extend(encode(OP_SET, key, value)),
+                BatchOp::Delete { key } => nested.extend(encode(OP_DELETE, key, &[])),
+            }
+        }
+        let mut inner = self.inner.lock().unwrap();
+        self.append(&mut inner, &encode(OP_BATCH, &[], &nested))?;
+        apply_batch(&mut inner.index, &nested);
+        Ok(())
+    }
+
+    /// Expired keys are dropped from the index only: their records carry
+    /// the deadline, so replay drops them too.
+    fn purge_expired(&self) -> io::Result<usize> {
+        let mut inner = self.inner.lock().unwrap();
+        let before = inner.index.len();
+        let now = SystemTime::now();
+        inner.index.retain(|_, entry| entry.is_live(now));
+        Ok(before - inner.index.len())
+    }
+
+    fn durability(&self) -> Durability {
+        if self.sync {
+            Durability::Strict
+        } else {
+            Durability::Fast
+        }
+    }
+
+    fn flush(&self) -> io::Result<Durability> {
+        self.inner.lock().unwrap().file.sync_data()?;
+        Ok(Durability::Strict)
+    }
+
+    /// The in-memory index stands in for the memtable and the log for the
+    /// WAL; there are no SSTables.
+    fn info(&self) -> io::Result<ServerInfo> {
+        let inner = self.inner.lock().unwrap();
+        Ok(ServerInfo {
+            format: INFO_FORMAT,
+            durability: Some(self.durability()),
+            memtable_bytes: index_bytes(&inner.index),
+            wal_bytes: inner.file.metadata()?.len(),
+            ..ServerInfo::default()
+        })
+    }
+}
+
+/// Encode the record that stores `entry` under `key`.
+fn encode_entry(key: &[u8], entry: &Entry) -> Vec<u8> {
+    match entry.expires_at {
+        None => encode(OP_SET, key, &entry.value),
+        Some(deadline) => {
+            let mut value = unix_millis(deadline).to_le_bytes().to_vec();
+            value.extend_from_slice(&entry.value);
+            encode(OP_SET_EXPIRING, key, &value)
+        }
+    }
+}
+
+fn encode(op: u8, key: &[u8], value: &[u8]) -> Vec<u8> {
+    let mut record = Vec::with_capacity(RECORD_HEADER_SIZE + key.len() + value.len());
+    record.push(op);
+    record.extend_from_slice(&(key.len() as u32).to_le_bytes());
+    record.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    record.extend_from_slice(key);
+    record.extend_from_slice(value);
+    record
+}
+
+/// Replay the log, returning the index without expired keys, the number of
+/// records applied and the byte length of the intact prefix.
+fn replay(file: &mut File) -> io::Result<(Index, u64, u64)> {
+    let file_len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(0))?;
+    let mut reader = BufReader::new(&*file);
+    let mut index = Index::new();
+    let mut records = 0;
+    let mut valid_len = 0u64;
+
+    loop {
+        let mut header = [0u8; RECORD_HEADER_SIZE];
+        if !read_full(&mut reader, &mut header)? {
+            break;
+        }
+        let key_len = u32::from_le_bytes(header[1..5].try_into().unwrap()) as usize;
+        let value_len = u32::from_le_bytes(header[5..9].try_into().unwrap()) as usize;
+        let record_len = (RECORD_HEADER_SIZE + key_len + value_len) as u64;
+        if valid_len + record_len > file_len {
+            break;
+        }
+
+        let mut key = vec![0u8; key_len];
+        let mut value = vec![0u8; value_len];
+        if !read_full(&mut reader, &mut key)? || !read_full(&mut reader, &mut value)? {
+            break;
+        }
+
+        let applied = match header[0] {
+            OP_BATCH => apply_batch(&mut index, &value),
+            op => apply(&mut index, op, key, &value),
+        };
+        if !applied {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "invalid log record of type {} at offset {valid_len}",
+                    header[0]
+                ),
+            ));
+        }
+        records += 1;
+        valid_len += record_len;
+    }
+
+    let now = SystemTime::now();
+    index.retain(|_, entry| entry.is_live(now));
+    Ok((index, records, valid_len))
+}
+
+/// Apply one SET, expiring SET or DELETE record to `index`. Returns
+/// `false` for anything else.
+fn apply(index: &mut Index, op: u8, key: Vec<u8>, value: &[u8]) -> bool {
+    match op {
+        OP_SET => {
+            index.insert(key, Entry::new(value, None));
+        }
+        OP_SET_EXPIRING if value.len() >= 8 => {
+            let deadline = u64::from_le_bytes(value[..8].try_into().unwrap());
+            let entry = Entry::new(&value[8..], Some(from_unix_millis(deadline)));
+            index.insert(key, entry);
+        }
+        OP_DELETE => {
+            index.remove(&key);
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Apply the records nested in a batch record. They are checked before any
+/// is applied, so a corrupt batch leaves `index` untouched.
+fn apply_batch(index: &mut Index, mut nested: &[u8]) -> bool {
+    let mut records = Vec::new();
+    while !nested.is_empty() {
+        if nested.len() < RECORD_HEADER_SIZE {
+            return false;
+        }
+        let key_len = u32::from_le_bytes(nested[1..5].try_into().unwrap()) as usize;
+        let value_len = u32::from_le_bytes(nested[5..9].try_into().unwrap()) as usize;
+        let Some(record) = nested.get(..RECORD_HEADER_SIZE + key_len + value_len) else {
+            return false;
+        };
+        let (key, value) = record[RECORD_HEADER_SIZE..].split_at(key_len);
+        if !matches!(record[0], OP_SET | OP_DELETE) {
+            return false;
+        }
+        records.push((record[0], key, value));
+        nested = &nested[record.len()..];
+    }
+    for (op, key, value) in records {
+        apply(index, op, key.to_vec(), value);
+    }
+    true
+}
+
+/// Fill `buf` completely. Returns `false` if the reader hit end of file first.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<bool> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn replays_writes_and_deletes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ocis.log");
+        {
+            let storage = AppendLogStorage::open(&path, false).unwrap();
+            storage.set(b"a", b"1").unwrap();
+            storage.set(b"b", b"2").unwrap();
+            storage.set(b"a", b"3").unwrap();
+            storage.delete(b"b").unwrap();
+        }
+
+        let storage = AppendLogStorage::open(&path, false).unwrap();
+        assert_eq!(storage.get(b"a").unwrap(), Some(b"3".to_vec()));
+        assert_eq!(storage.get(b"b").unwrap(), None);
+    }
+
+    #[test]
+    fn replays_conditional_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ocis.log");
+        {
+            let storage = AppendLogStorage::open(&path, false).unwrap();
+            assert!(storage.compare_and_swap(b"a", None, Some(b"1")).unwrap());
+            assert!(!storage.compare_and_swap(b"a", None, Some(b"2")).unwrap());
+            assert!(storage
+                .compare_and_swap(b"a", Some(b"1"), Some(b"3"))
+                .unwrap());
+            storage.set(b"b", b"4").unwrap();
+            assert!(storage.compare_and_swap(b"b", Some(b"4"), None).unwrap());
+        }
+
+        let storage = AppendLogStorage::open(&path, false).unwrap();
+        assert_eq!(storage.get(b"a").unwrap(), Some(b"3".to_vec()));
+        assert_eq!(storage.get(b"b").unwrap(), None);
+    }
+
+    #[test]
+    fn replays_batches_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ocis.log");
+        {
+            let storage = AppendLogStorage::open(&path, false).unwrap();
+            storage.set(b"a", b"1").unwrap();
+            let batch = WriteBatch::new().set("b", "2").delete("a");
+            storage.write_batch(&batch).unwrap();
+        }
+        let intact = fs::metadata(&path).unwrap().len();
+        {
+            let mut nested = encode(OP_SET, b"c", b"3");
+            nested.extend(encode(OP_SET, b"d", b"4"));
+            let record = encode(OP_BATCH, &[], &nested);
+            let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+            file.write_all(&record[..record.len() - 3]).unwrap();
+        }
+
+        let storage = AppendLogStorage::open(&path, false).unwrap();
+        assert_eq!(storage.get(b"a").unwrap(), None);
+        assert_eq!(storage.get(b"b").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(storage.get(b"c").unwrap(), None);
+        assert_eq!(fs::metadata(&path).unwrap().len(), intact);
+    }
+
+    #[test]
+    fn replays_expiries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ocis.log");
+        let future = from_unix_millis(unix_millis(SystemTime::now()) + 3_600_000);
+        {
+            let storage = AppendLogStorage::open(&path, false).unwrap();
+            storage.set_with_expiry(b"a", b"1", future).unwrap();
+            storage.set_with_expiry(b"b", b"2", future).unwrap();
+            storage.set_with_expiry(b"gone", b"3", UNIX_EPOCH).unwrap();
+            assert!(storage.persist(b"b").unwrap());
+        }
+
+        let storage = AppendLogStorage::open(&path, false).unwrap();
+        assert_eq!(storage.expiry(b"a").unwrap(), Some(Some(future)));
+        assert_eq!(storage.expiry(b"b").unwrap(), Some(None));
+        assert_eq!(storage.expiry(b"gone").unwrap(), None);
+        assert_eq!(storage.inner.lock().unwrap().index.len(), 2);
+    }
+
+    #[test]
+    fn truncates_torn_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ocis.log");
+        {
+            let storage = AppendLogStorage::open(&path, false).unwrap();
+            storage.set(b"a", b"1").unwrap();
+        }
+        let intact = fs::metadata(&path).unwrap().len();
+        {
+            let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+            file.write_all(&encode(OP_SET, b"b", b"22")[..11]).unwrap();
+        }
+
+        let storage = AppendLogStorage::open(&path, false).unwrap();
+        assert_eq!(storage.get(b"a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(storage.get(b"b").unwrap(), None);
+        assert_eq!(fs::metadata(&path).unwrap().len(), intact);
+
+        storage.set(b"c", b"3").unwrap();
+        drop(storage);
+        let storage = AppendLogStorage::open(&path, false).unwrap();
+        assert_eq!(storage.get(b"c").unwrap(), Some(b"3".to_vec()));
+    }
+
+    #[test]
+    fn compacts_superseded_records_on_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ocis.log");
+        {
+            let storage = AppendLogStorage::open(&path, false).unwrap();
+            for i in 0..(COMPACTION_SLACK as u32 + 10) {
+                storage.set(b"hot", &i.to_le_bytes()).unwrap();
+            }
+        }
+
+        let storage = AppendLogStorage::open(&path, false).unwrap();
+        assert_eq!(storage.inner.lock().unwrap().records, 1);
+        assert_eq!(
+            storage.get(b"hot").unwrap(),
+            Some((COMPACTION_SLACK as u32 + 9).to_le_bytes().to_vec())
+        );
+    }
+}
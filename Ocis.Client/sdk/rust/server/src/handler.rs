@@ -0,0 +1,727 @@
+//! Request validation and dispatch to storage.
+//!
+//! Validation limits and error messages mirror `Ocis.Server.Handler` so
+//! clients see identical behaviour from either server.
+
+use ocis_client::auth::{auth_response, requires_auth};
+use ocis_client::batch::{parse_batch_request, BatchOp, WriteBatch};
+use ocis_client::counter::{self, encode_counter, CounterError, COUNTER_SIZE};
+use ocis_client::durability::Durability;
+use ocis_client::expiry::{encode_ttl, split_set_with_expiry_value, EXPIRY_SIZE};
+use ocis_client::handshake::hello_response;
+use ocis_client::info::encode_info;
+use ocis_client::limits::{self, Limits};
+use ocis_client::protocol::{
+    add_checksum, condition_failed_response, encode_frame_with_flags, error_response, frame_flags,
+    frame_version, has_checksum, is_valid_packet_size, not_found_response, overflow_response,
+    request_id, serialize_response, success_response, try_parse_request_packet,
+    unauthorized_response, verify_checksum,
+};
+use ocis_client::request::split_compare_and_set_value;
+use ocis_client::scan::{encode_scan_page, parse_scan_request};
+use ocis_client::spec::{
+    CommandType, RequestPacket, ResponsePacket, StatusCode, PROTOCOL_VERSION, SUPPORTED_VERSIONS,
+};
+
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::storage::Storage;
+
+/// Maximum key length accepted by the server (1 MiB).
+const MAX_KEY_LENGTH: i32 = limits::MAX_KEY_LENGTH as i32;
+
+/// Maximum value length accepted for SET and conditional writes (8 MiB).
+const MAX_VALUE_LENGTH: i32 = limits::MAX_VALUE_LENGTH as i32;
+
+/// Requests received but not yet answered, across all connections; the
+/// queue depth INFO reports.
+pub static QUEUE_DEPTH: AtomicU64 = AtomicU64::new(0);
+
+/// Per-connection authentication state.
+#[derive(Default)]
+pub struct Session {
+    /// Token the connection must AUTH with; `None` needs no AUTH.
+    token: Option<Arc<[u8]>>,
+    authenticated: AtomicBool,
+}
+
+impl Session {
+    pub fn new(token: Option<Arc<[u8]>>) -> Self {
+        Session {
+            token,
+            authenticated: AtomicBool::new(false),
+        }
+    }
+
+    fn is_authenticated(&self) -> bool {
+        self.token.is_none() || self.authenticated.load(Ordering::Acquire)
+    }
+
+    fn authenticate(&self, offered: &[u8]) -> ResponsePacket {
+        let response = auth_response(self.token.as_deref(), offered);
+        let accepted = response.status() == Some(StatusCode::Success);
+        if !accepted {
+            log::warn!("AUTH with a wrong token");
+        }
+        self.authenticated.store(accepted, Ordering::Release);
+        response
+    }
+}
+
+/// Decode one complete frame, run it against `storage` and return the
+/// serialized response frame, tagged with the request's protocol version
+/// and echoing its request id. Successful writes on v3 also report the
+/// durability they reached, and checksummed requests get checksummed
+/// responses. Until `session` authenticates, commands that need it are
+/// refused.
+pub fn handle_frame(storage: &dyn Storage, session: &Session, frame: Vec<u8>) -> Vec<u8> {
+    let mut honored = None;
+    let (version, response) = match try_parse_request_packet(&frame) {
+        None if !verify_checksum(&frame) => {
+            log::warn!("Request failed its checksum");
+            let version = frame_version(&frame).unwrap_or(PROTOCOL_VERSION);
+            (version, error_response("Frame checksum mismatch"))
+        }
+        None => (PROTOCOL_VERSION, error_response("Malformed request")),
+        // HELLO carries no key, so it bypasses the key checks.
+        Some(request) if request.command() == Some(CommandType::Hello) => {
+            let offered = request.value.unwrap_or_default();
+            (
+                request.version,
+                hello_response(&offered, SUPPORTED_VERSIONS),
+            )
+        }
+        Some(request) if request.command() == Some(CommandType::Auth) => {
+            let offered = request.value.unwrap_or_default();
+            (request.version, session.authenticate(&offered))
+        }
+        Some(request) if requires_auth(request.command()) && !session.is_authenticated() => {
+            (request.version, unauthorized_response())
+        }
+        Some(request) => {
+            let response = match validate(&request) {
+                Ok(()) => handle_request(storage, &request),
+                Err(message) => {
+                    log::warn!("Invalid request: {message}");
+                    error_response(&message)
+                }
+            };
+            let write = request.command().is_some_and(CommandType::is_write);
+            if write && response.status() == Some(StatusCode::Success) {
+                let requested = frame_flags(&frame).and_then(Durability::from_flags);
+                honored = Some(reach_durability(storage, requested));
+            }
+            (request.version, response)
+        }
+    };
+    let id = request_id(&frame).unwrap_or_default();
+    let flags = honored.map_or(0, Durability::flags);
+    let reply = encode_frame_with_flags(serialize_response(&response), version, id, flags);
+    if has_checksum(&frame) {
+        add_checksum(reply)
+    } else {
+        reply
+    }
+}
+
+/// Raise an acknowledged write to `requested` if the backend's default
+/// falls short, returning the level it reached.
+fn reach_durability(storage: &dyn Storage, requested: Option<Durability>) -> Durability {
+    let default = storage.durability();
+    match requested {
+        Some(requested) if requested > default => storage.flush().unwrap_or_else(|e| {
+            log::error!("flush failed: {e}");
+            default
+        }),
+        _ => default,
+    }
+}
+
+/// Validate protocol-level boundaries before touching storage.
+fn validate(request: &RequestPacket) -> Result<(), String> {
+    let command = request.command();
+    if !is_valid_packet_size(request.total_packet_length) {
+        Err("Invalid packet size".into())
+    } else if request.key_length > MAX_KEY_LENGTH
+        // SCAN starts from the empty key to cover the whole key space,
+        // BATCH carries its keys in the value and the admin commands have
+        // none.
+        || (request.key_length <= 0
+            && !matches!(
+                command,
+                Some(
+                    CommandType::Scan | CommandType::Batch | CommandType::Ping | CommandType::Info
+                )
+            ))
+    {
+        Err("Invalid key length".into())
+    } else if command == Some(CommandType::Set)
+        && (request.value_length <= 0 || request.value_length > MAX_VALUE_LENGTH)
+    {
+        Err("Invalid value length for SET command".into())
+    } else if command == Some(CommandType::SetWithExpiry)
+        && (request.value_length <= EXPIRY_SIZE as i32
+            || request.value_length > MAX_VALUE_LENGTH + EXPIRY_SIZE as i32)
+    {
+        Err("Invalid value length for SETEX command".into())
+    } else if is_conditional(command)
+        && (request.value_length <= 0 || request.value_length > MAX_VALUE_LENGTH)
+    {
+        Err("Invalid value length for conditional write".into())
+    } else if matches!(command, Some(CommandType::IncrBy | CommandType::DecrBy))
+        && request.value_length != COUNTER_SIZE as i32
+    {
+        Err("Invalid counter delta".into())
+    } else if matches!(
+        command,
+        Some(
+            CommandType::Get
+                | CommandType::Delete
+                | CommandType::Exists
+                | CommandType::Ttl
+                | CommandType::Persist
+                | CommandType::Ping
+                | CommandType::Info
+        )
+    ) && request.value_length > 0
+    {
+        let name = match command {
+            Some(CommandType::Get) => "Get",
+            Some(CommandType::Delete) => "Delete",
+            Some(CommandType::Exists) => "Exists",
+            Some(CommandType::Ttl) => "Ttl",
+            Some(CommandType::Persist) => "Persist",
+            Some(CommandType::Ping) => "Ping",
+            _ => "Info",
+        };
+        Err(format!("{name} command should not have a value"))
+    } else if request.key.len() as i32 != request.key_length {
+        Err("Key length mismatch".into())
+    } else if matches!(command, Some(CommandType::Set | CommandType::SetWithExpiry))
+        || is_conditional(command)
+    {
+        match &request.value {
+            Some(value) if value.len() as i32 != request.value_length => {
+                Err("Value length mismatch".into())
+            }
+            None => Err("Missing value for SET command".into()),
+            _ => Ok(()),
+        }
+    } else {
+        Ok(())
+    }
+}
+
+/// Apply the SET and DELETE limits to every operation of a batch, so that
+/// an invalid one rejects the whole batch before anything is written.
+fn validate_batch(batch: &WriteBatch) -> Result<(), String> {
+    for op in batch.ops() {
+        let key_length = op.key().len();
+        if key_length == 0 || key_length > MAX_KEY_LENGTH as usize {
+            return Err("Invalid key length".into());
+        }
+        if let BatchOp::Set { value, .. } = op {
+            if value.is_empty() || value.len() > MAX_VALUE_LENGTH as usize {
+                return Err("Invalid value length for SET command".into());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_conditional(command: Option<CommandType>) -> bool {
+    matches!(
+        command,
+        Some(CommandType::SetIfAbsent | CommandType::CompareAndSet | CommandType::CompareAndDelete)
+    )
+}
+
+fn handle_request(storage: &dyn Storage, request: &RequestPacket) -> ResponsePacket {
+    let key = &request.key;
+    let result = match request.command() {
+        Some(CommandType::Set) => match &request.value {
+            Some(value) => storage.set(key, value).map(|()| success_response(None)),
+            None => Ok(error_response("SET command requires a value")),
+        },
+        Some(CommandType::Get) => storage.get(key).map(|value| match value {
+            Some(value) => success_response(Some(&value)),
+            None => not_found_response(),
+        }),
+        Some(CommandType::Delete) => storage.delete(key).map(|()| success_response(None)),
+        Some(CommandType::Exists) => storage.exists(key).map(|exists| {
+            if exists {
+                success_response(None)
+            } else {
+                not_found_response()
+            }
+        }),
+        Some(CommandType::SetIfAbsent) => {
+            let value = request.value.as_deref();
+            storage
+                .compare_and_swap(key, None, value)
+                .map(condition_response)
+        }
+        Some(CommandType::CompareAndSet) => {
+            match request
+                .value
+                .as_deref()
+                .and_then(split_compare_and_set_value)
+            {
+                Some((expected, new)) if !new.is_empty() => storage
+                    .compare_and_swap(key, Some(expected), Some(new))
+                    .map(condition_response),
+                _ => Ok(error_response("Malformed compare-and-set value")),
+            }
+        }
+        Some(CommandType::CompareAndDelete) => {
+            let expected = request.value.as_deref();
+            storage
+                .compare_and_swap(key, expected, None)
+                .map(condition_response)
+        }
+        Some(CommandType::SetWithExpiry) => {
+            match request
+                .value
+                .as_deref()
+                .and_then(split_set_with_expiry_value)
+            {
+                Some((expiry, value)) => {
+                    let expires_at = expiry.deadline(SystemTime::now());
+                    storage
+                        .set_with_expiry(key, value, expires_at)
+                        .map(|()| success_response(None))
+                }
+                None => Ok(error_response("Malformed SETEX value")),
+            }
+        }
+        Some(CommandType::Ttl) => storage.expiry(key).map(|expiry| match expiry {
+            Some(expires_at) => {
+                success_response(encode_ttl(expires_at, SystemTime::now()).as_deref())
+            }
+            None => not_found_response(),
+        }),
+        Some(CommandType::Persist) => storage.persist(key).map(|persisted| {
+            if persisted {
+                success_response(None)
+            } else {
+                not_found_response()
+            }
+        }),
+        Some(CommandType::IncrBy | CommandType::DecrBy) => update_counter(storage, request),
+        Some(CommandType::Batch) => match parse_batch_request(request) {
+            Some(batch) => match validate_batch(&batch) {
+                Ok(()) => storage.write_batch(&batch).map(|()| success_response(None)),
+                Err(message) => Ok(error_response(&message)),
+            },
+            None => Ok(error_response("Malformed BATCH")),
+        },
+        Some(CommandType::Scan) => match parse_scan_request(request) {
+            Some(scan) => storage
+                .scan(&scan)
+                .map(|page| success_response(Some(&encode_scan_page(&page)))),
+            None => Ok(error_response("Malformed SCAN options")),
+        },
+        Some(CommandType::Ping) => Ok(success_response(None)),
+        Some(CommandType::Info) => storage.info().map(|mut info| {
+            info.queue_depth = QUEUE_DEPTH.load(Ordering::Relaxed);
+            info.limits = Some(Limits::default());
+            success_response(Some(&encode_info(&info)))
+        }),
+        Some(CommandType::Hello | CommandType::Auth) | None => {
+            let message = format!("Unknown command type: {}", request.command_type);
+            log::error!("{message}");
+            Ok(error_response(&message))
+        }
+    };
+
+    result.unwrap_or_else(|e| {
+        log::error!("storage error: {e}");
+        error_response(&e.to_string())
+    })
+}
+
+/// Apply a counter update with a compare-and-swap loop, so that it stays
+/// atomic on any storage backend.
+fn update_counter(storage: &dyn Storage, request: &RequestPacket) -> io::Result<ResponsePacket> {
+    loop {
+        let current = storage.get(&request.key)?;
+        let value = match counter::apply(request, current.as_deref()) {
+            Some(Ok(value)) => encode_counter(value),
+            Some(Err(CounterError::Overflow)) => return Ok(overflow_response()),
+            Some(Err(CounterError::NotACounter)) => {
+                return Ok(error_response("Value is not a counter"))
+            }
+            None => return Ok(error_response("Malformed counter delta")),
+        };
+        if storage.compare_and_swap(&request.key, current.as_deref(), Some(&value))? {
+            return Ok(success_response(Some(&value)));
+        }
+    }
+}
+
+fn condition_response(written: bool) -> ResponsePacket {
+    if written {
+        success_response(None)
+    } else {
+        condition_failed_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::append_log::AppendLogStorage;
+    use crate::storage::MemoryStorage;
+    use ocis_client::auth::create_auth_request;
+    use ocis_client::batch::create_batch_request;
+    use ocis_client::counter::{create_decr_by_request, create_incr_by_request};
+    use ocis_client::durability::honored_durability;
+    use ocis_client::expiry::{
+        create_persist_request, create_set_with_expiry_request, create_ttl_request, decode_ttl,
+        Expiry, Ttl,
+    };
+    use ocis_client::handshake::{accepted_version, create_hello_request};
+    use ocis_client::info::{create_info_request, create_ping_request, decode_info};
+    use ocis_client::protocol::{deserialize_response, encode_frame, ParseResult, CHECKSUM_SIZE};
+    use ocis_client::request::{
+        create_compare_and_delete_request, create_compare_and_set_request, create_delete_request,
+        create_exists_request, create_get_request, create_set_if_absent_request,
+        create_set_request,
+    };
+    use ocis_client::response::{
+        parse_response, to_client_result, to_client_result_exists, to_client_result_value,
+        ClientResult,
+    };
+    use ocis_client::scan::{create_scan_request, decode_scan_page, Scan};
+    use ocis_client::spec::StatusCode;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn handle_frame(storage: &dyn Storage, frame: Vec<u8>) -> Vec<u8> {
+        super::handle_frame(storage, &Session::default(), frame)
+    }
+
+    fn run(storage: &MemoryStorage, frame: Vec<u8>) -> ClientResult<Vec<u8>> {
+        let response = handle_frame(storage, frame);
+        to_client_result_value(&parse_response(&response))
+    }
+
+    #[test]
+    fn set_then_get() {
+        let storage = MemoryStorage::new();
+        handle_frame(&storage, create_set_request("k", b"v"));
+        let get = create_get_request("k");
+        assert_eq!(run(&storage, get), ClientResult::Success(b"v".to_vec()));
+    }
+
+    #[test]
+    fn answers_hello_and_v2_frames() {
+        let storage = MemoryStorage::new();
+        let hello = handle_frame(&storage, create_hello_request(&[1, 2]));
+        assert_eq!(accepted_version(&hello, &[1, 2]).unwrap(), 2);
+
+        handle_frame(&storage, encode_frame(create_set_request("k", b"v"), 2, 1));
+        let response = handle_frame(&storage, encode_frame(create_get_request("k"), 2, 42));
+        assert_eq!(response[4], 2);
+        assert_eq!(request_id(&response), Some(42));
+        assert_eq!(
+            to_client_result_value(&parse_response(&response)),
+            ClientResult::Success(b"v".to_vec())
+        );
+    }
+
+    #[test]
+    fn exists_reports_presence_without_value() {
+        let storage = MemoryStorage::new();
+        handle_frame(&storage, create_set_request("k", b"v"));
+
+        let present = parse_response(&handle_frame(&storage, create_exists_request("k")));
+        assert_eq!(
+            to_client_result_exists(&present),
+            ClientResult::Success(true)
+        );
+        let ParseResult::ParseSuccess(packet) = present else {
+            panic!("expected a parsed response");
+        };
+        assert_eq!(packet.value, None);
+
+        let absent = parse_response(&handle_frame(&storage, create_exists_request("x")));
+        assert_eq!(
+            to_client_result_exists(&absent),
+            ClientResult::Success(false)
+        );
+    }
+
+    #[test]
+    fn scan_returns_pages_from_the_empty_key() {
+        let storage = MemoryStorage::new();
+        for key in ["b", "a", "c"] {
+            handle_frame(&storage, create_set_request(key, b"v"));
+        }
+
+        let scan = Scan::all().limit(2).keys_only();
+        let response = handle_frame(&storage, create_scan_request(&scan, None));
+        let ClientResult::Success(page) = to_client_result_value(&parse_response(&response)) else {
+            panic!("expected a page");
+        };
+        let page = decode_scan_page(&page).unwrap();
+        let keys: Vec<_> = page.entries.iter().map(|e| e.key.as_slice()).collect();
+        assert_eq!(keys, [b"a", b"b"]);
+        assert_eq!(page.cursor.as_deref(), Some(&b"c"[..]));
+    }
+
+    #[test]
+    fn conditional_writes_report_condition_failed() {
+        let storage = MemoryStorage::new();
+        let status = |frame| match parse_response(&handle_frame(&storage, frame)) {
+            ParseResult::ParseSuccess(packet) => packet.status(),
+            _ => panic!("expected a parsed response"),
+        };
+
+        assert_eq!(
+            status(create_set_if_absent_request("k", b"1")),
+            Some(StatusCode::Success)
+        );
+        assert_eq!(
+            status(create_set_if_absent_request("k", b"2")),
+            Some(StatusCode::ConditionFailed)
+        );
+        assert_eq!(
+            status(create_compare_and_set_request("k", b"2", b"3")),
+            Some(StatusCode::ConditionFailed)
+        );
+        assert_eq!(
+            status(create_compare_and_set_request("k", b"1", b"3")),
+            Some(StatusCode::Success)
+        );
+        assert_eq!(
+            status(create_compare_and_delete_request("k", b"3")),
+            Some(StatusCode::Success)
+        );
+        assert_eq!(status(create_get_request("k")), Some(StatusCode::NotFound));
+        assert_eq!(
+            status(create_compare_and_set_request("k", b"", b"")),
+            Some(StatusCode::Error)
+        );
+    }
+
+    #[test]
+    fn counters_update_atomically_and_report_overflow() {
+        let storage = MemoryStorage::new();
+        let counter = |frame| match parse_response(&handle_frame(&storage, frame)) {
+            ParseResult::ParseSuccess(packet) => (packet.status(), packet.value),
+            _ => panic!("expected a parsed response"),
+        };
+
+        assert_eq!(
+            counter(create_incr_by_request("n", 3)),
+            (Some(StatusCode::Success), Some(3i64.to_le_bytes().to_vec()))
+        );
+        assert_eq!(
+            counter(create_decr_by_request("n", i64::MIN)),
+            (Some(StatusCode::Overflow), None)
+        );
+        assert_eq!(
+            counter(create_decr_by_request("n", 4)),
+            (
+                Some(StatusCode::Success),
+                Some((-1i64).to_le_bytes().to_vec())
+            )
+        );
+
+        let mut short = create_incr_by_request("n", 1);
+        short.truncate(short.len() - 1);
+        let total = short.len() as i32;
+        short[6..10].copy_from_slice(&total.to_le_bytes());
+        short[14..18].copy_from_slice(&7i32.to_le_bytes());
+        assert_eq!(counter(short).0, Some(StatusCode::Error));
+    }
+
+    #[test]
+    fn expiring_keys_answer_ttl_and_persist() {
+        let storage = MemoryStorage::new();
+        let hour = Duration::from_secs(3600);
+        let reply = |frame| match parse_response(&handle_frame(&storage, frame)) {
+            ParseResult::ParseSuccess(packet) => (packet.status(), packet.value),
+            _ => panic!("expected a parsed response"),
+        };
+
+        let set = create_set_with_expiry_request("k", b"v", Expiry::After(hour));
+        assert_eq!(reply(set).0, Some(StatusCode::Success));
+        let (status, ttl) = reply(create_ttl_request("k"));
+        assert_eq!(status, Some(StatusCode::Success));
+        assert!(matches!(
+            decode_ttl(ttl.as_deref()),
+            Some(Ttl::Remaining(remaining)) if remaining <= hour
+        ));
+
+        assert_eq!(
+            reply(create_persist_request("k")),
+            (Some(StatusCode::Success), None)
+        );
+        assert_eq!(
+            reply(create_ttl_request("k")),
+            (Some(StatusCode::Success), None)
+        );
+
+        let expired = create_set_with_expiry_request("old", b"v", Expiry::At(UNIX_EPOCH));
+        reply(expired);
+        assert_eq!(
+            reply(create_get_request("old")).0,
+            Some(StatusCode::NotFound)
+        );
+        assert_eq!(
+            reply(create_persist_request("old")).0,
+            Some(StatusCode::NotFound)
+        );
+    }
+
+    #[test]
+    fn batches_apply_all_or_nothing() {
+        let storage = MemoryStorage::new();
+        handle_frame(&storage, create_set_request("old", b"x"));
+
+        let invalid = WriteBatch::new().set("a", "1").set("b", "");
+        assert_eq!(
+            run(&storage, create_batch_request(&invalid)),
+            ClientResult::Error("Invalid value length for SET command".into())
+        );
+        assert_eq!(
+            run(&storage, create_get_request("a")),
+            ClientResult::NotFound
+        );
+
+        let batch = WriteBatch::new().set("a", "1").delete("old");
+        let response = handle_frame(&storage, create_batch_request(&batch));
+        assert_eq!(
+            to_client_result(&parse_response(&response)),
+            ClientResult::Success(())
+        );
+        assert_eq!(
+            run(&storage, create_get_request("a")),
+            ClientResult::Success(b"1".to_vec())
+        );
+        assert_eq!(
+            run(&storage, create_get_request("old")),
+            ClientResult::NotFound
+        );
+    }
+
+    #[test]
+    fn reports_honored_durability_on_v3() {
+        let strict = Durability::Strict.flags();
+        let memory = MemoryStorage::new();
+        let set = encode_frame_with_flags(create_set_request("k", b"v"), 3, 1, strict);
+        let response = handle_frame(&memory, set);
+        assert_eq!(honored_durability(&response), Some(Durability::Fast));
+        let get = encode_frame_with_flags(create_get_request("k"), 3, 2, strict);
+        assert_eq!(honored_durability(&handle_frame(&memory, get)), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let log = AppendLogStorage::open(dir.path().join("ocis.log"), false).unwrap();
+        let set = encode_frame_with_flags(create_set_request("k", b"v"), 3, 3, 0);
+        let response = handle_frame(&log, set);
+        assert_eq!(honored_durability(&response), Some(Durability::Fast));
+        let delete = encode_frame_with_flags(create_delete_request("k"), 3, 4, strict);
+        let response = handle_frame(&log, delete);
+        assert_eq!(honored_durability(&response), Some(Durability::Strict));
+    }
+
+    #[test]
+    fn checks_and_adds_checksums() {
+        let storage = MemoryStorage::new();
+        let set = encode_frame_with_flags(create_set_request("k", b"v"), 3, 1, 0);
+        let response = handle_frame(&storage, add_checksum(set));
+        assert!(has_checksum(&response) && verify_checksum(&response));
+        assert_eq!(
+            to_client_result(&parse_response(&response)),
+            ClientResult::Success(())
+        );
+
+        let mut corrupt = add_checksum(encode_frame_with_flags(
+            create_set_request("k", b"w"),
+            3,
+            2,
+            0,
+        ));
+        let last = corrupt.len() - CHECKSUM_SIZE - 1;
+        corrupt[last] ^= 1;
+        let response = handle_frame(&storage, corrupt);
+        assert_eq!(request_id(&response), Some(2));
+        assert_eq!(
+            to_client_result(&parse_response(&response)),
+            ClientResult::Error("Frame checksum mismatch".into())
+        );
+        let get = handle_frame(&storage, create_get_request("k"));
+        assert!(!has_checksum(&get));
+        assert_eq!(
+            to_client_result_value(&parse_response(&get)),
+            ClientResult::Success(b"v".to_vec())
+        );
+    }
+
+    #[test]
+    fn answers_ping_and_info() {
+        let storage = MemoryStorage::new();
+        handle_frame(&storage, create_set_request("key", b"value"));
+        let ping = handle_frame(&storage, create_ping_request());
+        assert_eq!(
+            to_client_result(&parse_response(&ping)),
+            ClientResult::Success(())
+        );
+
+        let ClientResult::Success(info) = run(&storage, create_info_request()) else {
+            panic!("INFO failed");
+        };
+        let info = decode_info(&info).unwrap();
+        assert_eq!(info.durability, Some(Durability::Fast));
+        assert_eq!(info.memtable_bytes, 8);
+        assert!(info.sstables_per_level.is_empty());
+        assert_eq!(info.limits, Some(Limits::default()));
+    }
+
+    #[test]
+    fn refuses_commands_until_authenticated() {
+        let storage = MemoryStorage::new();
+        let session = Session::new(Some(Arc::from(&b"secret"[..])));
+        let status = |frame| {
+            let response = super::handle_frame(&storage, &session, frame);
+            match deserialize_response(&response) {
+                ParseResult::ParseSuccess(packet) => packet.status(),
+                _ => None,
+            }
+        };
+
+        assert_eq!(status(create_ping_request()), Some(StatusCode::Success));
+        let set = || create_set_request("k", b"v");
+        assert_eq!(status(set()), Some(StatusCode::Unauthorized));
+        let wrong = create_auth_request(b"guess");
+        assert_eq!(status(wrong), Some(StatusCode::Unauthorized));
+        let right = create_auth_request(b"secret");
+        assert_eq!(status(right), Some(StatusCode::Success));
+        assert_eq!(status(set()), Some(StatusCode::Success));
+    }
+
+    #[test]
+    fn rejects_empty_key_like_dotnet_server() {
+        let storage = MemoryStorage::new();
+        let get = create_get_request("");
+        assert_eq!(
+            run(&storage, get),
+            ClientResult::Error("Invalid key length".into())
+        );
+    }
+
+    #[test]
+    fn rejects_get_with_value() {
+        let storage = MemoryStorage::new();
+        let mut frame = create_set_request("k", b"v");
+        frame[5] = CommandType::Get as u8;
+        assert_eq!(
+            run(&storage, frame),
+            ClientResult::Error("Get command should not have a value".into())
+        );
+    }
+}
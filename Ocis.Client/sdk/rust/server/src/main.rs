@@ -0,0 +1,175 @@
+//! Standalone server speaking the Ocis v1 wire protocol.
+//!
+//! Intended for edge nodes that cannot run .NET but must serve the same
+//! clients as the central Ocis server.
+
+mod append_log;
+mod handler;
+mod server;
+mod storage;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::{Parser, ValueEnum};
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+
+use crate::append_log::AppendLogStorage;
+use crate::server::{serve, ServerConfig};
+use crate::storage::{MemoryStorage, Storage};
+
+const LOG_FILE_NAME: &str = "ocis.log";
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum StorageKind {
+    /// Volatile in-memory map
+    Memory,
+    /// Append-only log file in the working directory
+    AppendLog,
+}
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "ocis-server",
+    version,
+    about = "Ocis protocol server (v1-v3 and the extension commands) with pluggable storage."
+)]
+struct Args {
+    /// The working directory (required for append-log storage)
+    working_dir: Option<PathBuf>,
+
+    /// Storage backend
+    #[arg(long, value_enum, default_value_t = StorageKind::AppendLog)]
+    storage: StorageKind,
+
+    /// Fsync every append-log write before acknowledging it
+    #[arg(long)]
+    sync: bool,
+
+    /// Server host address
+    #[arg(long, default_value = "0.0.0.0")]
+    host: String,
+
+    /// Server port
+    #[arg(long, default_value_t = 7379, value_parser = clap::value_parser!(u16).range(1..))]
+    port: u16,
+
+    /// Maximum concurrent connections
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u32).range(1..))]
+    max_connections: u32,
+
+    /// File holding the token clients must AUTH with; without it any client
+    /// is accepted
+    #[arg(long)]
+    auth_token_file: Option<PathBuf>,
+
+    /// Log level: Debug, Info, Warn, Error
+    #[arg(long, default_value = "Info")]
+    log_level: log::LevelFilter,
+}
+
+fn open_storage(args: &Args) -> Result<Arc<dyn Storage>, String> {
+    match args.storage {
+        StorageKind::Memory => Ok(Arc::new(MemoryStorage::new())),
+        StorageKind::AppendLog => {
+            let dir = args
+                .working_dir
+                .as_ref()
+                .ok_or("append-log storage requires a working directory")?;
+            if !dir.is_dir() {
+                return Err("Directory does not exist".into());
+            }
+            let storage = AppendLogStorage::open(dir.join(LOG_FILE_NAME), args.sync)
+                .map_err(|e| format!("failed to open {}: {e}", dir.display()))?;
+            Ok(Arc::new(storage))
+        }
+    }
+}
+
+/// Read the AUTH token, ignoring a trailing newline.
+fn read_auth_token(path: &Path) -> Result<Arc<[u8]>, String> {
+    let mut token =
+        fs::read(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    while token.last().is_some_and(|b| matches!(b, b'\n' | b'\r')) {
+        token.pop();
+    }
+    if token.is_empty() {
+        return Err(format!("{} holds an empty token", path.display()));
+    }
+    Ok(token.into())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+    env_logger::Builder::new()
+        .filter_level(args.log_level)
+        .init();
+
+    let storage = match open_storage(&args) {
+        Ok(storage) => storage,
+        Err(message) => {
+            log::error!("{message}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let auth_token = match args.auth_token_file.as_deref().map(read_auth_token) {
+        None => None,
+        Some(Ok(token)) => Some(token),
+        Some(Err(message)) => {
+            log::error!("{message}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let listener = match TcpListener::bind((args.host.as_str(), args.port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::error!("failed to bind {}:{}: {e}", args.host, args.port);
+            return ExitCode::FAILURE;
+        }
+    };
+    log::info!(
+        "listening on {}:{} with {:?} storage",
+        args.host,
+        args.port,
+        args.storage
+    );
+
+    let (stop, stopped) = watch::channel(false);
+    tokio::spawn(async move {
+        match tokio::signal::ctrl_c().await {
+            Ok(()) => {
+                log::info!("shutting down");
+                let _ = stop.send(true);
+            }
+            Err(e) => {
+                log::error!("cannot listen for Ctrl-C, running until killed: {e}");
+                // Dropping `stop` would wake every `changed()` as if to
+                // shut down, so keep it for as long as the server runs.
+                std::future::pending::<()>().await;
+                drop(stop);
+            }
+        }
+    });
+
+    let config = ServerConfig {
+        max_connections: args.max_connections as usize,
+        receive_timeout: Duration::from_secs(30),
+        send_timeout: Duration::from_secs(30),
+        purge_interval: Duration::from_secs(1),
+        auth_token,
+    };
+    match serve(listener, storage, config, stopped).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            log::error!("server failed: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! TCP accept loop and per-connection framing.
+
+use std::io;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ocis_client::protocol::{frame_length, request_id, try_parse_request_header};
+use ocis_client::spec::{CommandType, HEADER_SIZE};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, watch, Semaphore};
+use tokio::time::timeout;
+
+use crate::handler::{handle_frame, Session, QUEUE_DEPTH};
+use crate::storage::Storage;
+
+pub struct ServerConfig {
+    pub max_connections: usize,
+    pub receive_timeout: Duration,
+    pub send_timeout: Duration,
+    /// How often expired keys are dropped from storage.
+    pub purge_interval: Duration,
+    /// Token every connection must AUTH with first; `None` allows all.
+    pub auth_token: Option<Arc<[u8]>>,
+}
+
+/// Requests carrying an id that one connection may have in flight at once.
+const MAX_IN_FLIGHT: usize = 256;
+
+/// Accept connections until `shutdown` flips to `true`. v1 requests on one
+/// connection are answered strictly in order; requests that carry an id
+/// (v2 and later) run concurrently and are answered as they complete.
+/// Connections run concurrently.
+pub async fn serve(
+    listener: TcpListener,
+    storage: Arc<dyn Storage>,
+    config: ServerConfig,
+    mut shutdown: watch::Receiver<bool>,
+) -> io::Result<()> {
+    let config = Arc::new(config);
+    let permits = Arc::new(Semaphore::new(config.max_connections));
+    let purger = tokio::spawn(purge_expired(Arc::clone(&storage), config.purge_interval));
+
+    loop {
+        // Like the .NET server, stop accepting while at capacity instead of
+        // accepting and immediately dropping connections.
+        let permit = tokio::select! {
+            permit = Arc::clone(&permits).acquire_owned() => permit.map_err(io::Error::other)?,
+            _ = shutdown.changed() => break,
+        };
+        let (socket, peer) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    log::error!("accept failed: {e}");
+                    continue;
+                }
+            },
+            _ = shutdown.changed() => break,
+        };
+
+        let storage = Arc::clone(&storage);
+        let config = Arc::clone(&config);
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            log::debug!("connection {peer} started");
+            if let Err(e) = handle_connection(socket, storage, &config, shutdown).await {
+                log::debug!("connection {peer} error: {e}");
+            }
+            log::debug!("connection {peer} ended");
+            drop(permit);
+        });
+    }
+
+    purger.abort();
+    Ok(())
+}
+
+/// Drop expired keys every `interval`. Reads already treat them as absent;
+/// this only reclaims their memory.
+async fn purge_expired(storage: Arc<dyn Storage>, interval: Duration) {
+    let mut ticks = tokio::time::interval(interval);
+    loop {
+        ticks.tick().await;
+        let storage = Arc::clone(&storage);
+        match tokio::task::spawn_blocking(move || storage.purge_expired()).await {
+            Ok(Ok(0)) => {}
+            Ok(Ok(purged)) => log::debug!("purged {purged} expired keys"),
+            Ok(Err(e)) => log::error!("purging expired keys failed: {e}"),
+            Err(e) => log::error!("purge task failed: {e}"),
+        }
+    }
+}
+
+async fn handle_connection(
+    socket: TcpStream,
+    storage: Arc<dyn Storage>,
+    config: &ServerConfig,
+    mut shutdown: watch::Receiver<bool>,
+) -> io::Result<()> {
+    socket.set_nodelay(true)?;
+    let (mut reader, writer) = socket.into_split();
+    let (responses, outgoing) = mpsc::unbounded_channel();
+    let mut writer = tokio::spawn(write_responses(writer, outgoing, config.send_timeout));
+    let in_flight = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
+    let session = Arc::new(Session::new(config.auth_token.clone()));
+
+    loop {
+        let frame = tokio::select! {
+            frame = timeout(config.receive_timeout, read_frame(&mut reader)) => match frame {
+                Ok(frame) => frame?,
+                Err(_) => break,
+            },
+            written = &mut writer => return written.map_err(io::Error::other)?,
+            _ = shutdown.changed() => break,
+        };
+        let Some(frame) = frame else {
+            break;
+        };
+
+        // AUTH changes how every later request is treated, so it runs in
+        // order even on pipelined connections.
+        let pipelined = request_id(&frame).is_some() && frame[5] != CommandType::Auth as u8;
+        QUEUE_DEPTH.fetch_add(1, Ordering::Relaxed);
+        let permit = Arc::clone(&in_flight)
+            .acquire_owned()
+            .await
+            .map_err(io::Error::other)?;
+        let storage = Arc::clone(&storage);
+        let session = Arc::clone(&session);
+        let responses = responses.clone();
+        let task = tokio::task::spawn_blocking(move || {
+            let _ = responses.send(handle_frame(&*storage, &session, frame));
+            QUEUE_DEPTH.fetch_sub(1, Ordering::Relaxed);
+            drop(permit);
+        });
+        if !pipelined {
+            task.await.map_err(io::Error::other)?;
+        }
+    }
+
+    // Let requests still in flight finish and flush their responses.
+    drop(responses);
+    writer.await.map_err(io::Error::other)?
+}
+
+/// Write responses in the order they are produced.
+async fn write_responses(
+    mut writer: OwnedWriteHalf,
+    mut outgoing: mpsc::UnboundedReceiver<Vec<u8>>,
+    send_timeout: Duration,
+) -> io::Result<()> {
+    while let Some(response) = outgoing.recv().await {
+        timeout(send_timeout, writer.write_all(&response))
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "send timed out"))??;
+    }
+    Ok(())
+}
+
+/// Read one framed request: fixed-size header first, then the payload.
+/// Returns `None` on clean disconnect or a framing error, which closes the
+/// connection just like the .NET server does.
+async fn read_frame(socket: &mut (impl AsyncReadExt + Unpin)) -> io::Result<Option<Vec<u8>>> {
+    let mut header = [0u8; HEADER_SIZE];
+    match socket.read_exact(&mut header).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let Some(total_length) = request_length(&header) else {
+        log::debug!("received invalid header");
+        return Ok(None);
+    };
+
+    let mut frame = vec![0u8; total_length];
+    frame[..HEADER_SIZE].copy_from_slice(&header);
+    socket.read_exact(&mut frame[HEADER_SIZE..]).await?;
+    Ok(Some(frame))
+}
+
+/// Validate a request header and return its declared total length.
+fn request_length(header: &[u8; HEADER_SIZE]) -> Option<usize> {
+    let packet = try_parse_request_header(header)?;
+    let length = frame_length(header);
+    if length.is_none() {
+        log::warn!(
+            "received packet with invalid size: {}",
+            packet.total_packet_length
+        );
+    }
+    length
+}
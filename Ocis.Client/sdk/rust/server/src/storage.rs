@@ -0,0 +1,285 @@
+//! Pluggable storage backends behind the protocol handler.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use ocis_client::batch::{BatchOp, WriteBatch};
+use ocis_client::durability::Durability;
+use ocis_client::info::{ServerInfo, INFO_FORMAT};
+use ocis_client::scan::{collect_page, Scan, ScanPage};
+
+/// Key-value storage used by the server. Implementations must be safe to
+/// call from many connections at once; each call is a single atomic step.
+///
+/// Keys may carry an expiry. Once it has passed, every method treats the
+/// key as absent; [`Storage::purge_expired`] reclaims the space.
+pub trait Storage: Send + Sync + 'static {
+    fn get(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>>;
+    /// Store `value` under `key`, clearing any expiry.
+    fn set(&self, key: &[u8], value: &[u8]) -> io::Result<()>;
+    fn delete(&self, key: &[u8]) -> io::Result<()>;
+
+    /// Whether `key` is present. Backends that can answer without reading
+    /// the value should override this.
+    fn exists(&self, key: &[u8]) -> io::Result<bool> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// One page of `scan` in ascending key order; see
+    /// [`ocis_client::scan::collect_page`].
+    fn scan(&self, scan: &Scan) -> io::Result<ScanPage>;
+
+    /// Atomically replace the value of `key` with `new` (deleting it for
+    /// `None`) if its current value is `expected` (absent for `None`).
+    /// Returns whether the write happened. A replaced value keeps the
+    /// key's expiry.
+    fn compare_and_swap(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> io::Result<bool>;
+
+    /// Store `value` under `key` until `expires_at`.
+    fn set_with_expiry(&self, key: &[u8], value: &[u8], expires_at: SystemTime) -> io::Result<()>;
+
+    /// When `key` expires: `None` if there is no such key, `Some(None)` if
+    /// it does not expire.
+    fn expiry(&self, key: &[u8]) -> io::Result<Option<Option<SystemTime>>>;
+
+    /// Clear the expiry of `key`; `false` if there is no such key.
+    fn persist(&self, key: &[u8]) -> io::Result<bool>;
+
+    /// Apply every operation in `batch`, in order, or none of them.
+    fn write_batch(&self, batch: &WriteBatch) -> io::Result<()>;
+
+    /// Drop expired keys, returning how many were dropped.
+    fn purge_expired(&self) -> io::Result<usize>;
+
+    /// Durability every acknowledged write already has.
+    fn durability(&self) -> Durability;
+
+    /// Make every acknowledged write as durable as this backend can,
+    /// returning the level reached.
+    fn flush(&self) -> io::Result<Durability>;
+
+    /// Statistics for INFO. The handler fills in the queue depth.
+    fn info(&self) -> io::Result<ServerInfo>;
+}
+
+/// A stored value and when it expires.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub value: Vec<u8>,
+    pub expires_at: Option<SystemTime>,
+}
+
+impl Entry {
+    pub fn new(value: &[u8], expires_at: Option<SystemTime>) -> Self {
+        Entry {
+            value: value.to_vec(),
+            expires_at,
+        }
+    }
+
+    pub fn is_live(&self, now: SystemTime) -> bool {
+        self.expires_at.is_none_or(|deadline| deadline > now)
+    }
+}
+
+/// Ordered in-memory index shared by the backends.
+pub type Index = BTreeMap<Vec<u8>, Entry>;
+
+/// The entry stored under `key`, unless it has expired.
+pub fn live_entry<'a>(index: &'a Index, key: &[u8]) -> Option<&'a Entry> {
+    let now = SystemTime::now();
+    index.get(key).filter(|entry| entry.is_live(now))
+}
+
+/// Bytes of keys and values held in `index`.
+pub fn index_bytes(index: &Index) -> u64 {
+    index
+        .iter()
+        .map(|(key, entry)| (key.len() + entry.value.len()) as u64)
+        .sum()
+}
+
+/// One page of `scan` over the live entries of `index`.
+pub fn scan_index(index: &Index, scan: &Scan) -> ScanPage {
+    let now = SystemTime::now();
+    let entries = index
+        .range(scan.start().to_vec()..)
+        .filter(|(_, entry)| entry.is_live(now))
+        .map(|(key, entry)| (key.as_slice(), entry.value.as_slice()));
+    collect_page(scan, entries)
+}
+
+/// Volatile storage backed by an ordered map. Data is lost on restart.
+#[derive(Default)]
+pub struct MemoryStorage {
+    entries: RwLock<Index>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn get(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        let entries = self.entries.read().unwrap();
+        Ok(live_entry(&entries, key).map(|entry| entry.value.clone()))
+    }
+
+    fn set(&self, key: &[u8], value: &[u8]) -> io::Result<()> {
+        self.entries
+            .write()
+            .unwrap()
+            .insert(key.to_vec(), Entry::new(value, None));
+        Ok(())
+    }
+
+    fn delete(&self, key: &[u8]) -> io::Result<()> {
+        self.entries.write().unwrap().remove(key);
+        Ok(())
+    }
+
+    fn exists(&self, key: &[u8]) -> io::Result<bool> {
+        Ok(live_entry(&self.entries.read().unwrap(), key).is_some())
+    }
+
+    fn compare_and_swap(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> io::Result<bool> {
+        let mut entries = self.entries.write().unwrap();
+        let current = live_entry(&entries, key);
+        if current.map(|entry| entry.value.as_slice()) != expected {
+            return Ok(false);
+        }
+        let expires_at = current.and_then(|entry| entry.expires_at);
+        match new {
+            Some(value) => {
+                entries.insert(key.to_vec(), Entry::new(value, expires_at));
+            }
+            None => {
+                entries.remove(key);
+            }
+        }
+        Ok(true)
+    }
+
+    fn scan(&self, scan: &Scan) -> io::Result<ScanPage> {
+        Ok(scan_index(&self.entries.read().unwrap(), scan))
+    }
+
+    fn set_with_expiry(&self, key: &[u8], value: &[u8], expires_at: SystemTime) -> io::Result<()> {
+        self.entries
+            .write()
+            .unwrap()
+            .insert(key.to_vec(), Entry::new(value, Some(expires_at)));
+        Ok(())
+    }
+
+    fn expiry(&self, key: &[u8]) -> io::Result<Option<Option<SystemTime>>> {
+        let entries = self.entries.read().unwrap();
+        Ok(live_entry(&entries, key).map(|entry| entry.expires_at))
+    }
+
+    fn persist(&self, key: &[u8]) -> io::Result<bool> {
+        let mut entries = self.entries.write().unwrap();
+        let now = SystemTime::now();
+        match entries.get_mut(key).filter(|entry| entry.is_live(now)) {
+            Some(entry) => {
+                entry.expires_at = None;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn write_batch(&self, batch: &WriteBatch) -> io::Result<()> {
+        let mut entries = self.entries.write().unwrap();
+        for op in batch.ops() {
+            match op {
+                BatchOp::Set { key, value } => {
+                    entries.insert(key.clone(), Entry::new(value, None));
+                }
+                BatchOp::Delete { key } => {
+                    entries.remove(key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn purge_expired(&self) -> io::Result<usize> {
+        let mut entries = self.entries.write().unwrap();
+        let before = entries.len();
+        let now = SystemTime::now();
+        entries.retain(|_, entry| entry.is_live(now));
+        Ok(before - entries.len())
+    }
+
+    fn durability(&self) -> Durability {
+        Durability::Fast
+    }
+
+    /// Nothing reaches disk, so a flush cannot raise the level.
+    fn flush(&self) -> io::Result<Durability> {
+        Ok(Durability::Fast)
+    }
+
+    fn info(&self) -> io::Result<ServerInfo> {
+        Ok(ServerInfo {
+            format: INFO_FORMAT,
+            durability: Some(self.durability()),
+            memtable_bytes: index_bytes(&self.entries.read().unwrap()),
+            ..ServerInfo::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn expired_keys_read_as_absent() {
+        let storage = MemoryStorage::new();
+        let past = SystemTime::now() - Duration::from_secs(1);
+        let future = SystemTime::now() + Duration::from_secs(3600);
+
+        storage.set_with_expiry(b"old", b"1", past).unwrap();
+        storage.set_with_expiry(b"new", b"2", future).unwrap();
+        assert_eq!(storage.get(b"old").unwrap(), None);
+        assert_eq!(storage.expiry(b"old").unwrap(), None);
+        assert_eq!(storage.expiry(b"new").unwrap(), Some(Some(future)));
+        assert!(storage.compare_and_swap(b"old", None, Some(b"3")).unwrap());
+        assert_eq!(storage.expiry(b"old").unwrap(), Some(None));
+
+        assert!(storage
+            .compare_and_swap(b"new", Some(b"2"), Some(b"4"))
+            .unwrap());
+        assert_eq!(storage.expiry(b"new").unwrap(), Some(Some(future)));
+        assert!(storage.persist(b"new").unwrap());
+        assert_eq!(storage.expiry(b"new").unwrap(), Some(None));
+
+        storage.set_with_expiry(b"gone", b"5", past).unwrap();
+        assert_eq!(storage.purge_expired().unwrap(), 1);
+        let keys: Vec<_> = storage
+            .scan(&Scan::all())
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|entry| entry.key)
+            .collect();
+        assert_eq!(keys, [b"new", b"old"]);
+    }
+}
@@ -0,0 +1,1035 @@
+//! Asynchronous client over tokio.
+//!
+//! One connection carries any number of concurrent requests. On v2
+//! connections every request carries an id and responses are dispatched to
+//! the waiting call by id, so a slow request does not hold up the others.
+//! Servers that only speak v1 answer in order, and responses are matched to
+//! calls first in, first out.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+
+use futures_core::Stream;
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, ReadBuf};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{lookup_host, TcpStream, ToSocketAddrs};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::auth::create_auth_request;
+use crate::batch::{create_batch_request, WriteBatch};
+use crate::chunking::{chunk_prefix, is_orphan, Manifest, DEFAULT_CHUNK_SIZE};
+use crate::compression::Compression;
+use crate::counter::{create_decr_by_request, create_incr_by_request};
+use crate::durability::{honored_durability, Durability, WriteOptions};
+#[cfg(feature = "encryption")]
+use crate::encryption::Keyring;
+use crate::error::{Error, Result};
+use crate::expiry::{
+    create_persist_request, create_set_with_expiry_request, create_ttl_request, Expiry, Ttl,
+};
+#[cfg(feature = "serde")]
+use crate::format::Format;
+use crate::handshake::{accepted_version, create_hello_request};
+use crate::info::{create_info_request, create_ping_request, ServerInfo};
+use crate::keyspace::Keyspace;
+use crate::limits::Limits;
+use crate::protocol::{
+    add_checksum, encode_frame_with_flags, has_checksum, request_id, verify_checksum,
+};
+use crate::request::{
+    create_compare_and_delete_request, create_compare_and_set_request, create_delete_request,
+    create_exists_request, create_get_request, create_set_if_absent_request, create_set_request,
+};
+use crate::scan::{create_scan_request, Entry, Scan, ScanPage};
+use crate::spec::{HEADER_SIZE, PROTOCOL_VERSION, SUPPORTED_VERSIONS};
+use crate::transport::{
+    check_integrity, condition_reply, counter_reply, exists_reply, frame_buffer, info_reply,
+    scan_reply, stored_value_reply, ttl_reply, unit_reply, ValueCodec,
+};
+
+type Reply = oneshot::Sender<Result<Vec<u8>>>;
+
+/// A connection to an Ocis server, the async counterpart of
+/// [`crate::blocking::Client`]. Clones share the connection and may issue
+/// requests concurrently. Wrap calls in `tokio::time::timeout` to bound
+/// them; abandoning a call does not disturb the others.
+///
+/// After an error for which [`Error::is_fatal`] is true the connection is
+/// closed and every later call fails; after any other error it stays
+/// usable. [`Client::reconnect`] replaces a closed connection.
+#[derive(Clone)]
+pub struct Client {
+    shared: Arc<Shared>,
+    values: ValueCodec,
+    checksums: bool,
+    chunk_size: usize,
+    keyspace: Option<Keyspace>,
+    #[cfg(feature = "serde")]
+    format: Option<Format>,
+}
+
+struct Shared {
+    /// Where the connection was opened, for [`Client::reconnect`].
+    addrs: Vec<SocketAddr>,
+    /// Token replayed on every new connection, once one was accepted.
+    token: Mutex<Option<Vec<u8>>>,
+    version: u8,
+    next_id: AtomicU64,
+    /// Limits of the server, shared by every handle.
+    limits: Mutex<Limits>,
+    outgoing: mpsc::UnboundedSender<(u64, Vec<u8>, Reply)>,
+    pending: Arc<Mutex<Pending>>,
+}
+
+impl Shared {
+    /// Open a connection, negotiate its version and start its reader and
+    /// writer tasks.
+    async fn open(addrs: Vec<SocketAddr>, limits: Limits) -> Result<Self> {
+        let mut stream = TcpStream::connect(&addrs[..]).await?;
+        stream.set_nodelay(true)?;
+        stream
+            .write_all(&create_hello_request(SUPPORTED_VERSIONS))
+            .await?;
+        let reply = read_frame(&mut stream).await?;
+        let version = accepted_version(&reply, SUPPORTED_VERSIONS)?;
+
+        let (reader, writer) = stream.into_split();
+        let pending = Arc::new(Mutex::new(Pending {
+            in_order: version == PROTOCOL_VERSION,
+            ..Pending::default()
+        }));
+        let (outgoing, requests) = mpsc::unbounded_channel();
+        tokio::spawn(write_requests(writer, requests, Arc::clone(&pending)));
+        tokio::spawn(read_responses(reader, Arc::clone(&pending)));
+
+        Ok(Shared {
+            addrs,
+            token: Mutex::new(None),
+            version,
+            next_id: AtomicU64::new(1),
+            limits: Mutex::new(limits),
+            outgoing,
+            pending,
+        })
+    }
+}
+
+/// Calls waiting for a response.
+#[derive(Default)]
+struct Pending {
+    waiters: HashMap<u64, Reply>,
+    /// Whether responses carry no id and arrive in request order (v1).
+    in_order: bool,
+    /// Ids in the order their requests were written, kept when `in_order`.
+    order: VecDeque<u64>,
+    /// Why the connection closed, once it has.
+    closed: Option<Arc<Error>>,
+}
+
+impl Client {
+    /// Connect and negotiate the protocol version with a HELLO request,
+    /// falling back to v1 against servers that predate it.
+    ///
+    /// Must be called within a tokio runtime, which runs the connection's
+    /// reader and writer tasks.
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Self> {
+        let addrs = lookup_host(addr).await?.collect();
+        Ok(Client {
+            shared: Arc::new(Shared::open(addrs, Limits::default()).await?),
+            values: ValueCodec::default(),
+            checksums: false,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            keyspace: None,
+            #[cfg(feature = "serde")]
+            format: None,
+        })
+    }
+
+    /// Connect, then authenticate with `token`. The token is kept and
+    /// replayed by [`Client::reconnect`].
+    pub async fn connect_with_token(addr: impl ToSocketAddrs, token: &[u8]) -> Result<Self> {
+        let client = Client::connect(addr).await?;
+        client.authenticate(token).await?;
+        Ok(client)
+    }
+
+    /// Authenticate this connection with `token`. Fails with
+    /// [`Error::Unauthorized`] if the server rejects it. Requests sent
+    /// concurrently with it may be refused. Once accepted the token is kept
+    /// and replayed by [`Client::reconnect`].
+    pub async fn authenticate(&self, token: &[u8]) -> Result<()> {
+        unit_reply(&self.request(create_auth_request(token)).await?)?;
+        *self.shared.token.lock().unwrap() = Some(token.to_vec());
+        Ok(())
+    }
+
+    /// Move this handle to a new connection to the same address, after the
+    /// old one closed for instance; other clones stay on the old one. The
+    /// new connection negotiates its version afresh, keeps the limits, and
+    /// authenticates with the kept token, if any.
+    pub async fn reconnect(&mut self) -> Result<()> {
+        let limits = *self.shared.limits.lock().unwrap();
+        let token = self.shared.token.lock().unwrap().clone();
+        self.shared = Arc::new(Shared::open(self.shared.addrs.clone(), limits).await?);
+        if let Some(token) = token {
+            self.authenticate(&token).await?;
+        }
+        Ok(())
+    }
+
+    /// Compress values this handle writes; see [`crate::compression`].
+    /// Other clones keep their own setting, and reads decompress values
+    /// whatever it is. Counters are not compressed; read them with
+    /// [`Client::incr_by`], see [`crate::counter`].
+    pub fn with_compression(mut self, compression: Option<Compression>) -> Self {
+        self.values.compression = compression;
+        self
+    }
+
+    /// Encrypt values this handle writes with `keyring`, and decrypt values
+    /// it reads; see [`crate::encryption`]. Other clones keep their own
+    /// keyring. Counters stay in the clear, and [`Client::get`] fails on
+    /// them with [`Error::Tampered`] while a keyring is set; read them with
+    /// [`Client::incr_by`], see [`crate::counter`].
+    #[cfg(feature = "encryption")]
+    pub fn with_keyring(mut self, keyring: Option<Keyring>) -> Self {
+        self.values.keyring = keyring.map(Arc::new);
+        self
+    }
+
+    /// Confine every key this handle uses to `keyspace`; see
+    /// [`crate::keyspace`]. Keys are given and returned relative to it.
+    /// Other clones keep their own keyspace.
+    pub fn with_keyspace(mut self, keyspace: Option<Keyspace>) -> Self {
+        self.keyspace = keyspace;
+        self
+    }
+
+    /// Format for [`Client::get_as`] and [`Client::set_as`] on this
+    /// handle.
+    #[cfg(feature = "serde")]
+    pub fn with_format(mut self, format: Option<Format>) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Add a CRC32C trailer to requests this handle sends and require one
+    /// on their responses, failing with [`Error::Integrity`] if it is
+    /// missing. Needs a v3 connection; earlier versions have no room for it
+    /// and go unchecked. A response whose checksum is wrong closes the
+    /// connection, whichever handle sent the request; calls then fail with
+    /// an I/O error whose source is [`Error::Integrity`].
+    pub fn with_checksums(mut self, enabled: bool) -> Self {
+        self.checksums = enabled;
+        self
+    }
+
+    /// Split objects this handle writes into chunks of `bytes`; see
+    /// [`crate::chunking`]. Readers refuse objects whose chunks are larger
+    /// than their value limit.
+    pub fn with_chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_size = bytes;
+        self
+    }
+
+    /// Refuse requests larger than `limits` from now on, on every handle
+    /// to this connection; see [`crate::limits`].
+    pub fn set_limits(&self, limits: Limits) {
+        *self.shared.limits.lock().unwrap() = limits;
+    }
+
+    /// Adopt the limits the server reports in INFO, if it reports any, and
+    /// return the limits now in force.
+    pub async fn fetch_limits(&self) -> Result<Limits> {
+        let advertised = self.info().await?.limits;
+        let mut limits = self.shared.limits.lock().unwrap();
+        if let Some(advertised) = advertised {
+            *limits = advertised;
+        }
+        Ok(*limits)
+    }
+
+    /// Protocol version negotiated for this connection.
+    pub fn version(&self) -> u8 {
+        self.shared.version
+    }
+
+    /// Fetch a value; `Ok(None)` if the key does not exist.
+    pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let key = &self.key(key);
+        let response = self.request(create_get_request(key)).await?;
+        stored_value_reply(&response, key, &self.values)
+    }
+
+    pub async fn set(&self, key: &str, value: &[u8]) -> Result<()> {
+        let key = &self.key(key);
+        let value = self.values.encode(key.as_bytes(), value);
+        let response = self.request(create_set_request(key, &value)).await?;
+        unit_reply(&response)
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        let key = &self.key(key);
+        let response = self.request(create_delete_request(key)).await?;
+        unit_reply(&response)
+    }
+
+    /// SET with per-request `options`. Returns the durability the server
+    /// reports for the write; `None` on connections older than v3, which
+    /// cannot carry options.
+    pub async fn set_with(
+        &self,
+        key: &str,
+        value: &[u8],
+        options: &WriteOptions,
+    ) -> Result<Option<Durability>> {
+        let key = &self.key(key);
+        let frame = create_set_request(key, &self.values.encode(key.as_bytes(), value));
+        let response = self.request_with_flags(frame, options.flags()).await?;
+        unit_reply(&response)?;
+        Ok(honored_durability(&response))
+    }
+
+    /// DELETE with per-request `options`; see [`Client::set_with`].
+    pub async fn delete_with(
+        &self,
+        key: &str,
+        options: &WriteOptions,
+    ) -> Result<Option<Durability>> {
+        let key = &self.key(key);
+        let frame = create_delete_request(key);
+        let response = self.request_with_flags(frame, options.flags()).await?;
+        unit_reply(&response)?;
+        Ok(honored_durability(&response))
+    }
+
+    /// Fetch and deserialize a value in the handle's format; see
+    /// [`crate::format`]. Fails with [`Error::Codec`] if no format is set.
+    #[cfg(feature = "serde")]
+    pub async fn get_as<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        self.get_as_with(key, self.format()?).await
+    }
+
+    /// Serialize and store a value in the handle's format; see
+    /// [`Client::get_as`].
+    #[cfg(feature = "serde")]
+    pub async fn set_as<T: Serialize + ?Sized>(&self, key: &str, value: &T) -> Result<()> {
+        self.set_as_with(key, value, self.format()?).await
+    }
+
+    /// [`Client::get_as`] in `format`, whatever the handle's format is.
+    #[cfg(feature = "serde")]
+    pub async fn get_as_with<T: DeserializeOwned>(
+        &self,
+        key: &str,
+        format: Format,
+    ) -> Result<Option<T>> {
+        self.get(key)
+            .await?
+            .map(|value| format.decode(&value))
+            .transpose()
+    }
+
+    /// [`Client::set_as`] in `format`, whatever the handle's format is.
+    #[cfg(feature = "serde")]
+    pub async fn set_as_with<T: Serialize + ?Sized>(
+        &self,
+        key: &str,
+        value: &T,
+        format: Format,
+    ) -> Result<()> {
+        let value = format.encode(value)?;
+        self.set(key, &value).await
+    }
+
+    #[cfg(feature = "serde")]
+    fn format(&self) -> Result<Format> {
+        self.format
+            .ok_or_else(|| Error::Codec("no value format set".into()))
+    }
+
+    /// Check that the server is alive and answering.
+    pub async fn ping(&self) -> Result<()> {
+        unit_reply(&self.request(create_ping_request()).await?)
+    }
+
+    /// Server statistics.
+    pub async fn info(&self) -> Result<ServerInfo> {
+        info_reply(&self.request(create_info_request()).await?)
+    }
+
+    /// Whether `key` exists, without transferring its value.
+    pub async fn exists(&self, key: &str) -> Result<bool> {
+        let key = &self.key(key);
+        let response = self.request(create_exists_request(key)).await?;
+        exists_reply(&response)
+    }
+
+    /// Store `value` only if `key` does not exist; `Ok(false)` if it does.
+    pub async fn set_if_absent(&self, key: &str, value: &[u8]) -> Result<bool> {
+        let key = &self.key(key);
+        let value = self.values.encode(key.as_bytes(), value);
+        let response = self
+            .request(create_set_if_absent_request(key, &value))
+            .await?;
+        condition_reply(&response)
+    }
+
+    /// Replace the value of `key` only if it is currently `expected`;
+    /// `Ok(false)` if it is not, or if the key does not exist. Fails with
+    /// [`Error::Codec`] while compression or a keyring is set, since the
+    /// server compares the stored bytes.
+    pub async fn compare_and_set(&self, key: &str, expected: &[u8], value: &[u8]) -> Result<bool> {
+        let key = &self.key(key);
+        let expected = self.values.encode_comparable(expected)?;
+        let value = self.values.encode_comparable(value)?;
+        let response = self
+            .request(create_compare_and_set_request(key, &expected, &value))
+            .await?;
+        condition_reply(&response)
+    }
+
+    /// Delete `key` only if its value is currently `expected`; `Ok(false)`
+    /// if it is not, or if the key does not exist. Fails like
+    /// [`Client::compare_and_set`] while compression or a keyring is set.
+    pub async fn compare_and_delete(&self, key: &str, expected: &[u8]) -> Result<bool> {
+        let key = &self.key(key);
+        let expected = self.values.encode_comparable(expected)?;
+        let response = self
+            .request(create_compare_and_delete_request(key, &expected))
+            .await?;
+        condition_reply(&response)
+    }
+
+    /// Store `value` under `key` until `expiry`, after which the key reads
+    /// as absent.
+    pub async fn set_with_expiry(&self, key: &str, value: &[u8], expiry: Expiry) -> Result<()> {
+        let key = &self.key(key);
+        let value = self.values.encode(key.as_bytes(), value);
+        let response = self
+            .request(create_set_with_expiry_request(key, &value, expiry))
+            .await?;
+        unit_reply(&response)
+    }
+
+    /// Remaining time to live of `key`; `Ok(None)` if it does not exist.
+    pub async fn ttl(&self, key: &str) -> Result<Option<Ttl>> {
+        let key = &self.key(key);
+        let response = self.request(create_ttl_request(key)).await?;
+        ttl_reply(&response)
+    }
+
+    /// Remove the expiry of `key`; `Ok(false)` if it does not exist.
+    pub async fn persist(&self, key: &str) -> Result<bool> {
+        let key = &self.key(key);
+        let response = self.request(create_persist_request(key)).await?;
+        exists_reply(&response)
+    }
+
+    /// Apply every write in `batch`, or none of them if any fails.
+    pub async fn write_batch(&self, batch: &WriteBatch) -> Result<()> {
+        let batch = match &self.keyspace {
+            Some(keyspace) => Cow::Owned(keyspace.batch(batch)),
+            None => Cow::Borrowed(batch),
+        };
+        let batch = self.values.encode_batch(&batch);
+        self.shared.limits.lock().unwrap().check_batch(&batch)?;
+        let response = self.request(create_batch_request(&batch)).await?;
+        unit_reply(&response)
+    }
+
+    /// Add `delta` to the counter at `key`, which starts at zero if absent,
+    /// and return the new value. Fails with [`Error::Overflow`] if the
+    /// result would not fit an `i64`.
+    pub async fn incr_by(&self, key: &str, delta: i64) -> Result<i64> {
+        let key = &self.key(key);
+        let response = self.request(create_incr_by_request(key, delta)).await?;
+        counter_reply(&response)
+    }
+
+    /// Subtract `delta` from the counter at `key`; see [`Client::incr_by`].
+    pub async fn decr_by(&self, key: &str, delta: i64) -> Result<i64> {
+        let key = &self.key(key);
+        let response = self.request(create_decr_by_request(key, delta)).await?;
+        counter_reply(&response)
+    }
+
+    /// Fetch one page of `scan`, starting at `cursor` or at the scan's start.
+    pub async fn scan_page(&self, scan: &Scan, cursor: Option<&[u8]>) -> Result<ScanPage> {
+        let response = self.request(self.scan_request(scan, cursor)).await?;
+        let page = scan_reply(&response, &self.values)?;
+        Ok(match &self.keyspace {
+            Some(keyspace) => keyspace.page(page),
+            None => page,
+        })
+    }
+
+    /// Stream every entry of `scan`, fetching pages as needed.
+    pub fn scan(&self, scan: Scan) -> ScanStream {
+        ScanStream {
+            client: self.clone(),
+            scan,
+            cursor: None,
+            entries: VecDeque::new(),
+            fetch: None,
+            done: false,
+        }
+    }
+
+    /// Store everything `reader` yields at `key` as a chunked object; see
+    /// [`crate::chunking`]. Replaces any value or object at `key`, and
+    /// deletes the chunks of an object it replaces.
+    pub async fn put_object(
+        &self,
+        key: &str,
+        mut reader: impl AsyncRead + Unpin,
+    ) -> Result<Manifest> {
+        let previous = self.manifest(key).await?;
+        let mut manifest = Manifest::new(self.chunk_size);
+        let mut written = self.write_chunks(key, &mut reader, &mut manifest).await;
+        if written.is_ok() {
+            written = self.set(key, &manifest.encode()).await;
+        }
+        if let Err(e) = written {
+            let _ = self.delete_chunks(key, &manifest).await;
+            return Err(e);
+        }
+        if let Some(previous) = previous {
+            self.delete_chunks(key, &previous).await?;
+        }
+        Ok(manifest)
+    }
+
+    /// Read the object at `key` as it is fetched, one chunk at a time;
+    /// `Ok(None)` if the key does not exist. Fails with [`Error::Codec`] if
+    /// the key holds a plain value.
+    pub async fn open_object(&self, key: &str) -> Result<Option<ObjectReader>> {
+        let Some(manifest) = self.object_manifest(key).await? else {
+            return Ok(None);
+        };
+        Ok(Some(ObjectReader {
+            client: self.clone(),
+            key: key.to_string(),
+            manifest: Arc::new(manifest),
+            next: 0,
+            chunk: Vec::new(),
+            position: 0,
+            fetch: None,
+        }))
+    }
+
+    /// Fetch the whole object at `key`; `Ok(None)` if the key does not
+    /// exist.
+    pub async fn get_object(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let Some(manifest) = self.object_manifest(key).await? else {
+            return Ok(None);
+        };
+        let mut object = Vec::new();
+        for index in 0..manifest.chunk_count() {
+            let chunk = self.get(&manifest.chunk_key(key, index)).await?;
+            object.extend_from_slice(&manifest.check(key, index, chunk)?);
+        }
+        Ok(Some(object))
+    }
+
+    /// Delete the object at `key` and its chunks, or a plain value;
+    /// `Ok(false)` if the key does not exist.
+    pub async fn delete_object(&self, key: &str) -> Result<bool> {
+        let Some(stored) = self.get(key).await? else {
+            return Ok(false);
+        };
+        self.delete(key).await?;
+        if let Some(manifest) = Manifest::decode(&stored) {
+            self.delete_chunks(key, &manifest).await?;
+        }
+        Ok(true)
+    }
+
+    /// Delete chunks of the object at `key` that its manifest does not
+    /// use, and return how many there were. A write to `key` in progress
+    /// loses its chunks, so only sweep keys nothing is writing.
+    pub async fn sweep_object(&self, key: &str) -> Result<usize> {
+        let current = self.manifest(key).await?;
+        let scan = Scan::prefix(chunk_prefix(key)).keys_only();
+        let mut orphans = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = self.scan_page(&scan, cursor.as_deref()).await?;
+            orphans.extend(
+                page.entries
+                    .into_iter()
+                    .filter(|entry| is_orphan(key, &entry.key, current.as_ref()))
+                    .map(|entry| String::from_utf8_lossy(&entry.key).into_owned()),
+            );
+            cursor = page.cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        for orphan in &orphans {
+            self.delete(orphan).await?;
+        }
+        Ok(orphans.len())
+    }
+
+    /// The manifest at `key`, if it holds one.
+    async fn manifest(&self, key: &str) -> Result<Option<Manifest>> {
+        Ok(self.get(key).await?.as_deref().and_then(Manifest::decode))
+    }
+
+    /// The manifest at `key`, failing if the key holds a plain value or a
+    /// manifest whose chunks are over the value limit.
+    async fn object_manifest(&self, key: &str) -> Result<Option<Manifest>> {
+        let limits = *self.shared.limits.lock().unwrap();
+        self.get(key)
+            .await?
+            .map(|stored| Manifest::decode_within(key, &stored, &limits))
+            .transpose()
+    }
+
+    async fn write_chunks(
+        &self,
+        key: &str,
+        reader: &mut (impl AsyncRead + Unpin),
+        manifest: &mut Manifest,
+    ) -> Result<()> {
+        loop {
+            let mut chunk = Vec::new();
+            reader
+                .take(manifest.chunk_size() as u64)
+                .read_to_end(&mut chunk)
+                .await?;
+            if chunk.is_empty() {
+                return Ok(());
+            }
+            self.set(&manifest.push(key, &chunk), &chunk).await?;
+            if chunk.len() < manifest.chunk_size() {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn delete_chunks(&self, key: &str, manifest: &Manifest) -> Result<()> {
+        for chunk_key in manifest.chunk_keys(key) {
+            self.delete(&chunk_key).await?;
+        }
+        Ok(())
+    }
+
+    /// `key` as stored on the server.
+    fn key<'k>(&self, key: &'k str) -> Cow<'k, str> {
+        match &self.keyspace {
+            Some(keyspace) => Cow::Owned(keyspace.key(key)),
+            None => Cow::Borrowed(key),
+        }
+    }
+
+    /// A SCAN request for the page of `scan` at `cursor`, inside the
+    /// keyspace.
+    fn scan_request(&self, scan: &Scan, cursor: Option<&[u8]>) -> Vec<u8> {
+        match &self.keyspace {
+            Some(keyspace) => {
+                let cursor = cursor.map(|cursor| keyspace.server_key(cursor));
+                create_scan_request(&keyspace.scan(scan), cursor.as_deref())
+            }
+            None => create_scan_request(scan, cursor),
+        }
+    }
+
+    /// Hand a v1 request frame to the writer task and wait for the
+    /// matching response frame.
+    async fn request(&self, frame: Vec<u8>) -> Result<Vec<u8>> {
+        self.request_with_flags(frame, 0).await
+    }
+
+    async fn request_with_flags(&self, frame: Vec<u8>, flags: u8) -> Result<Vec<u8>> {
+        let shared = &self.shared;
+        let id = shared.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut frame = encode_frame_with_flags(frame, shared.version, id, flags);
+        if self.checksums {
+            frame = add_checksum(frame);
+        }
+        shared.limits.lock().unwrap().check(&frame)?;
+        let checksummed = has_checksum(&frame);
+        let (reply, response) = oneshot::channel();
+        if shared.outgoing.send((id, frame, reply)).is_err() {
+            return Err(shared.pending.lock().unwrap().closed_error());
+        }
+        match response.await {
+            Ok(response) => {
+                let response = response?;
+                check_integrity(&response, checksummed)?;
+                Ok(response)
+            }
+            Err(_) => Err(shared.pending.lock().unwrap().closed_error()),
+        }
+    }
+}
+
+type PageFuture = Pin<Box<dyn Future<Output = Result<ScanPage>> + Send>>;
+type ChunkFuture = Pin<Box<dyn Future<Output = Result<Vec<u8>>> + Send>>;
+
+/// Reader over a chunked object, returned by [`Client::open_object`].
+/// Chunks are fetched as they are needed and checked against their hashes;
+/// errors other than [`Error::Io`] surface as [`io::ErrorKind::InvalidData`]
+/// wrapping the [`Error`].
+pub struct ObjectReader {
+    client: Client,
+    key: String,
+    manifest: Arc<Manifest>,
+    next: u32,
+    chunk: Vec<u8>,
+    position: usize,
+    fetch: Option<ChunkFuture>,
+}
+
+impl ObjectReader {
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+}
+
+impl AsyncRead for ObjectReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        while this.position == this.chunk.len() {
+            if this.next == this.manifest.chunk_count() {
+                return Poll::Ready(Ok(()));
+            }
+            let fetch = this.fetch.get_or_insert_with(|| {
+                let client = this.client.clone();
+                let key = this.key.clone();
+                let manifest = Arc::clone(&this.manifest);
+                let index = this.next;
+                Box::pin(async move {
+                    let chunk = client.get(&manifest.chunk_key(&key, index)).await?;
+                    manifest.check(&key, index, chunk)
+                })
+            });
+            let chunk = ready!(fetch.as_mut().poll(cx));
+            this.fetch = None;
+            this.chunk = chunk?;
+            this.next += 1;
+            this.position = 0;
+        }
+        let n = buf.remaining().min(this.chunk.len() - this.position);
+        buf.put_slice(&this.chunk[this.position..this.position + n]);
+        this.position += n;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Stream over a scan, returned by [`Client::scan`]. Ends after the first
+/// error.
+pub struct ScanStream {
+    client: Client,
+    scan: Scan,
+    cursor: Option<Vec<u8>>,
+    entries: VecDeque<Entry>,
+    fetch: Option<PageFuture>,
+    done: bool,
+}
+
+impl Stream for ScanStream {
+    type Item = Result<Entry>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(entry) = this.entries.pop_front() {
+                return Poll::Ready(Some(Ok(entry)));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            let fetch = this.fetch.get_or_insert_with(|| {
+                let client = this.client.clone();
+                let scan = this.scan.clone();
+                let cursor = this.cursor.take();
+                Box::pin(async move { client.scan_page(&scan, cursor.as_deref()).await })
+            });
+            let page = ready!(fetch.as_mut().poll(cx));
+            this.fetch = None;
+            match page {
+                Ok(page) => {
+                    this.done = page.cursor.is_none();
+                    this.cursor = page.cursor;
+                    this.entries = page.entries.into();
+                }
+                Err(e) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+    }
+}
+
+impl Pending {
+    /// Register a call before its request is written, or fail it at once if
+    /// the connection has closed.
+    fn register(&mut self, id: u64, reply: Reply) {
+        if self.closed.is_some() {
+            let _ = reply.send(Err(self.closed_error()));
+            return;
+        }
+        self.waiters.insert(id, reply);
+        if self.in_order {
+            self.order.push_back(id);
+        }
+    }
+
+    /// Hand a response to its call. v1 responses carry no id and go to the
+    /// oldest call still waiting.
+    fn complete(&mut self, frame: Vec<u8>) -> Result<()> {
+        let reply = match request_id(&frame) {
+            Some(id) => self.waiters.remove(&id),
+            None => loop {
+                let Some(id) = self.order.pop_front() else {
+                    break None;
+                };
+                if let Some(reply) = self.waiters.remove(&id) {
+                    break Some(reply);
+                }
+            },
+        };
+        let reply = reply.ok_or_else(|| Error::Protocol("unexpected response".into()))?;
+        // The call may have been abandoned; its response is simply dropped.
+        let _ = reply.send(Ok(frame));
+        Ok(())
+    }
+
+    /// Fail every waiting call and all later ones with `error`.
+    fn close(&mut self, error: Error) {
+        if self.closed.is_none() {
+            self.closed = Some(Arc::new(error));
+        }
+        for reply in std::mem::take(&mut self.waiters).into_values() {
+            let _ = reply.send(Err(self.closed_error()));
+        }
+        self.order.clear();
+    }
+
+    /// The error calls fail with once the connection has closed: an I/O
+    /// error of the kind that closed it, `ConnectionAborted` if it was not
+    /// an I/O error, with the reason as its source.
+    fn closed_error(&self) -> Error {
+        let Some(reason) = &self.closed else {
+            return Error::Io(io::ErrorKind::NotConnected.into());
+        };
+        let kind = match &**reason {
+            Error::Io(e) => e.kind(),
+            _ => io::ErrorKind::ConnectionAborted,
+        };
+        Error::Io(io::Error::new(kind, Arc::clone(reason)))
+    }
+}
+
+/// Write requests in the order calls were made. Calls are registered just
+/// before their request is written so that v1 responses line up with them.
+/// Ends when every [`Client`] clone is gone, which closes the write side.
+async fn write_requests(
+    mut writer: OwnedWriteHalf,
+    mut requests: mpsc::UnboundedReceiver<(u64, Vec<u8>, Reply)>,
+    pending: Arc<Mutex<Pending>>,
+) {
+    while let Some((id, frame, reply)) = requests.recv().await {
+        pending.lock().unwrap().register(id, reply);
+        if let Err(e) = writer.write_all(&frame).await {
+            pending.lock().unwrap().close(e.into());
+            return;
+        }
+    }
+}
+
+/// Dispatch responses until the connection fails or the server closes it.
+async fn read_responses(mut reader: OwnedReadHalf, pending: Arc<Mutex<Pending>>) {
+    let error = loop {
+        let completed = match read_frame(&mut reader).await {
+            Ok(frame) => pending.lock().unwrap().complete(frame),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = completed {
+            break e;
+        }
+    };
+    pending.lock().unwrap().close(error);
+}
+
+async fn read_frame(reader: &mut (impl AsyncRead + Unpin)) -> Result<Vec<u8>> {
+    let mut header = [0u8; HEADER_SIZE];
+    reader.read_exact(&mut header).await?;
+    let mut frame = frame_buffer(&header)?;
+    reader.read_exact(&mut frame[HEADER_SIZE..]).await?;
+    if !verify_checksum(&frame) {
+        return Err(Error::Integrity);
+    }
+    Ok(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Fault, MockServer};
+    use crate::spec::LATEST_VERSION;
+    use std::time::{Duration, Instant};
+
+    #[tokio::test]
+    async fn set_get_delete() {
+        let server = MockServer::start().unwrap();
+        let client = Client::connect(server.addr()).await.unwrap();
+        assert_eq!(client.version(), LATEST_VERSION);
+
+        client.set("k", b"v").await.unwrap();
+        assert_eq!(client.get("k").await.unwrap(), Some(b"v".to_vec()));
+        assert!(client.exists("k").await.unwrap());
+        client.delete("k").await.unwrap();
+        assert_eq!(client.get("k").await.unwrap(), None);
+        assert!(!client.exists("k").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn scan_streams_every_page() {
+        let server = MockServer::start().unwrap();
+        for i in 0..5 {
+            server.insert(format!("k{i}"), "v");
+        }
+        let client = Client::connect(server.addr()).await.unwrap();
+
+        let mut stream = client.scan(Scan::all().limit(2));
+        let mut keys = Vec::new();
+        while let Some(entry) = std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await
+        {
+            keys.push(String::from_utf8(entry.unwrap().key).unwrap());
+        }
+        assert_eq!(keys, ["k0", "k1", "k2", "k3", "k4"]);
+        assert_eq!(server.request_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn slow_requests_do_not_block_others() {
+        let server = MockServer::start().unwrap();
+        server.insert("slow", "1");
+        server.insert("fast", "2");
+        server.push_fault(Fault::Delay(Duration::from_millis(300)));
+        let client = Client::connect(server.addr()).await.unwrap();
+
+        let start = Instant::now();
+        let timed = |key| {
+            let client = client.clone();
+            async move { (client.get(key).await.unwrap(), start.elapsed()) }
+        };
+        let ((slow, slow_at), (fast, fast_at)) = tokio::join!(timed("slow"), timed("fast"));
+        assert_eq!(slow, Some(b"1".to_vec()));
+        assert_eq!(fast, Some(b"2".to_vec()));
+        assert!(fast_at < slow_at);
+    }
+
+    #[tokio::test]
+    async fn streams_large_objects() {
+        let server = MockServer::start().unwrap();
+        let client = Client::connect(server.addr())
+            .await
+            .unwrap()
+            .with_chunk_size(3);
+        let first = client.put_object("model", &b"0123456"[..]).await.unwrap();
+        assert_eq!(first.chunk_count(), 3);
+
+        let mut object = client.open_object("model").await.unwrap().unwrap();
+        let mut read = Vec::new();
+        object.read_to_end(&mut read).await.unwrap();
+        assert_eq!(read, b"0123456");
+
+        let second = client.put_object("model", &b"abc"[..]).await.unwrap();
+        assert_eq!(
+            client.get_object("model").await.unwrap(),
+            Some(b"abc".to_vec())
+        );
+        assert!(first
+            .chunk_keys("model")
+            .all(|key| server.get(key.as_bytes()).is_none()));
+
+        server.insert(first.chunk_key("model", 1), "orphan");
+        assert_eq!(client.sweep_object("model").await.unwrap(), 1);
+        client.delete(&second.chunk_key("model", 0)).await.unwrap();
+        let mut object = client.open_object("model").await.unwrap().unwrap();
+        let err = object.read_to_end(&mut Vec::new()).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(client.delete_object("model").await.unwrap());
+        assert_eq!(client.get_object("model").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn v1_responses_match_in_order() {
+        let server = MockServer::start_with_versions(&[PROTOCOL_VERSION]).unwrap();
+        server.insert("a", "1");
+        server.insert("b", "2");
+        let client = Client::connect(server.addr()).await.unwrap();
+        assert_eq!(client.version(), PROTOCOL_VERSION);
+
+        let (a, b) = tokio::join!(client.get("a"), client.get("b"));
+        assert_eq!(a.unwrap(), Some(b"1".to_vec()));
+        assert_eq!(b.unwrap(), Some(b"2".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn dropped_connection_fails_pending_and_later_calls() {
+        let server = MockServer::start().unwrap();
+        server.push_fault(Fault::Drop);
+        let client = Client::connect(server.addr()).await.unwrap();
+
+        assert!(client.get("k").await.unwrap_err().is_fatal());
+        assert!(matches!(client.get("k").await, Err(Error::Io(_))));
+    }
+
+    #[tokio::test]
+    async fn reconnect_replays_the_token() {
+        let server = MockServer::start_with_token(b"secret").unwrap();
+        let mut client = Client::connect_with_token(server.addr(), b"secret")
+            .await
+            .unwrap();
+        client.set("k", b"v").await.unwrap();
+        server.push_fault(Fault::Drop);
+        assert!(client.get("k").await.unwrap_err().is_fatal());
+
+        client.reconnect().await.unwrap();
+        assert_eq!(client.get("k").await.unwrap(), Some(b"v".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn corrupted_response_closes_the_connection_with_its_reason() {
+        let server = MockServer::start().unwrap();
+        server.insert("k", b"value".to_vec());
+        let client = Client::connect(server.addr())
+            .await
+            .unwrap()
+            .with_checksums(true);
+
+        server.push_fault(Fault::Corrupt(HEADER_SIZE + 9 + 4));
+        for _ in 0..2 {
+            let Err(Error::Io(error)) = client.get("k").await else {
+                panic!("expected the connection to be closed");
+            };
+            assert_eq!(error.kind(), io::ErrorKind::ConnectionAborted);
+            let reason = error
+                .get_ref()
+                .and_then(|reason| reason.downcast_ref::<Arc<Error>>());
+            assert!(matches!(reason.map(|r| &**r), Some(Error::Integrity)));
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! AUTH: shared-token authentication.
+//!
+//! A server configured with a token answers every command other than
+//! HELLO, PING and AUTH with [`StatusCode::Unauthorized`] until the
+//! connection sends AUTH with that token. The request has an empty key and
+//! the token as its value. A wrong token answers Unauthorized and leaves
+//! the connection unauthenticated. Servers without a token accept any AUTH,
+//! so clients can send one unconditionally.
+//!
+//! The token travels in the clear; put the connection behind TLS or a
+//! trusted network.
+
+use crate::protocol::{success_response, unauthorized_response};
+use crate::request::create_request;
+use crate::spec::{CommandType, ResponsePacket};
+
+#[cfg(doc)]
+use crate::spec::StatusCode;
+
+/// Build an AUTH request offering `token`.
+pub fn create_auth_request(token: &[u8]) -> Vec<u8> {
+    create_request(CommandType::Auth, &[], Some(token.to_vec()))
+}
+
+/// Whether `command` is refused on a connection that has not
+/// authenticated yet. Unknown commands are.
+pub fn requires_auth(command: Option<CommandType>) -> bool {
+    !matches!(
+        command,
+        Some(CommandType::Hello | CommandType::Ping | CommandType::Auth)
+    )
+}
+
+/// Compare tokens in time independent of where they differ.
+pub fn token_matches(expected: &[u8], offered: &[u8]) -> bool {
+    expected.len() == offered.len()
+        && expected
+            .iter()
+            .zip(offered)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Server side: answer an AUTH offering `offered` to a server expecting
+/// `expected`.
+pub fn auth_response(expected: Option<&[u8]>, offered: &[u8]) -> ResponsePacket {
+    match expected {
+        Some(expected) if !token_matches(expected, offered) => unauthorized_response(),
+        _ => success_response(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::try_parse_request_packet;
+    use crate::spec::StatusCode;
+
+    #[test]
+    fn auth_checks_the_token() {
+        let request = try_parse_request_packet(&create_auth_request(b"secret")).unwrap();
+        assert_eq!(request.command(), Some(CommandType::Auth));
+        let offered = request.value.unwrap();
+
+        let accepted = auth_response(Some(b"secret"), &offered);
+        assert_eq!(accepted.status(), Some(StatusCode::Success));
+        let rejected = auth_response(Some(b"secreT"), &offered);
+        assert_eq!(rejected.status(), Some(StatusCode::Unauthorized));
+        assert!(!token_matches(b"secret", b"secret2"));
+        let open = auth_response(None, b"anything");
+        assert_eq!(open.status(), Some(StatusCode::Success));
+
+        assert!(requires_auth(Some(CommandType::Get)));
+        assert!(requires_auth(None));
+        assert!(!requires_auth(Some(CommandType::Ping)));
+    }
+}
@@ -0,0 +1,158 @@
+//! BATCH: several SETs and DELETEs applied atomically.
+//!
+//! A BATCH request has an empty key; its value is the encoded operations,
+//! little-endian:
+//!
+//! ```text
+//! count u32 | count × (op u8 (1 = set, 3 = delete) | key length i32 | key
+//! | [value length i32 | value])
+//! ```
+//!
+//! The server applies every operation or none of them, in order, and
+//! answers with a plain success or error. SETs in a batch clear the key's
+//! expiry like a plain SET.
+
+use crate::request::create_request;
+use crate::spec::{CommandType, RequestPacket};
+
+const OP_SET: u8 = CommandType::Set as u8;
+const OP_DELETE: u8 = CommandType::Delete as u8;
+
+/// One operation in a [`WriteBatch`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BatchOp {
+    Set { key: Vec<u8>, value: Vec<u8> },
+    Delete { key: Vec<u8> },
+}
+
+impl BatchOp {
+    pub fn key(&self) -> &[u8] {
+        match self {
+            BatchOp::Set { key, .. } | BatchOp::Delete { key } => key,
+        }
+    }
+}
+
+/// Writes to apply atomically, in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WriteBatch {
+    ops: Vec<BatchOp>,
+}
+
+impl WriteBatch {
+    pub fn new() -> Self {
+        WriteBatch::default()
+    }
+
+    pub fn set(mut self, key: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) -> Self {
+        self.ops.push(BatchOp::Set {
+            key: key.into(),
+            value: value.into(),
+        });
+        self
+    }
+
+    pub fn delete(mut self, key: impl Into<Vec<u8>>) -> Self {
+        self.ops.push(BatchOp::Delete { key: key.into() });
+        self
+    }
+
+    pub fn ops(&self) -> &[BatchOp] {
+        &self.ops
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+/// Build a BATCH request applying `batch`.
+pub fn create_batch_request(batch: &WriteBatch) -> Vec<u8> {
+    create_request(CommandType::Batch, &[], Some(encode_batch(batch)))
+}
+
+pub fn encode_batch(batch: &WriteBatch) -> Vec<u8> {
+    let mut buffer = (batch.ops.len() as u32).to_le_bytes().to_vec();
+    for op in &batch.ops {
+        match op {
+            BatchOp::Set { key, value } => {
+                buffer.push(OP_SET);
+                put_bytes(&mut buffer, key);
+                put_bytes(&mut buffer, value);
+            }
+            BatchOp::Delete { key } => {
+                buffer.push(OP_DELETE);
+                put_bytes(&mut buffer, key);
+            }
+        }
+    }
+    buffer
+}
+
+/// Server side: the batch a BATCH request carries; `None` if it is
+/// malformed.
+pub fn parse_batch_request(request: &RequestPacket) -> Option<WriteBatch> {
+    decode_batch(request.value.as_deref()?)
+}
+
+/// Decode a batch; `None` if it is malformed.
+pub fn decode_batch(mut buffer: &[u8]) -> Option<WriteBatch> {
+    let count = u32::from_le_bytes(take(&mut buffer, 4)?.try_into().unwrap());
+    let mut ops = Vec::new();
+    for _ in 0..count {
+        let op = take(&mut buffer, 1)?[0];
+        let key = take_bytes(&mut buffer)?.to_vec();
+        ops.push(match op {
+            OP_SET => BatchOp::Set {
+                key,
+                value: take_bytes(&mut buffer)?.to_vec(),
+            },
+            OP_DELETE => BatchOp::Delete { key },
+            _ => return None,
+        });
+    }
+    buffer.is_empty().then_some(WriteBatch { ops })
+}
+
+fn put_bytes(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    buffer.extend_from_slice(&(bytes.len() as i32).to_le_bytes());
+    buffer.extend_from_slice(bytes);
+}
+
+fn take<'a>(buffer: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if buffer.len() < n {
+        return None;
+    }
+    let (head, tail) = buffer.split_at(n);
+    *buffer = tail;
+    Some(head)
+}
+
+fn take_bytes<'a>(buffer: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let length = i32::from_le_bytes(take(buffer, 4)?.try_into().unwrap());
+    take(buffer, usize::try_from(length).ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::try_parse_request_packet;
+
+    #[test]
+    fn batch_round_trips() {
+        let batch = WriteBatch::new().set("a", "1").delete("b").set("c", "3");
+        let request = try_parse_request_packet(&create_batch_request(&batch)).unwrap();
+        assert_eq!(request.command(), Some(CommandType::Batch));
+        assert!(request.key.is_empty());
+        assert_eq!(parse_batch_request(&request), Some(batch.clone()));
+
+        let encoded = encode_batch(&batch);
+        assert_eq!(decode_batch(&encoded[..encoded.len() - 1]), None);
+        assert_eq!(decode_batch(&[1, 0, 0, 0, 9, 0, 0, 0, 0]), None);
+        assert_eq!(decode_batch(&[0, 0, 0, 0]), Some(WriteBatch::new()));
+    }
+}
@@ -0,0 +1,1193 @@
+//! Synchronous client over one TCP connection.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+#[cfg(feature = "encryption")]
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::auth::create_auth_request;
+use crate::batch::{create_batch_request, WriteBatch};
+use crate::chunking::{chunk_prefix, is_orphan, Manifest, DEFAULT_CHUNK_SIZE};
+use crate::compression::Compression;
+use crate::counter::{create_decr_by_request, create_incr_by_request};
+use crate::durability::{honored_durability, Durability, WriteOptions};
+#[cfg(feature = "encryption")]
+use crate::encryption::Keyring;
+use crate::error::{Error, Result};
+use crate::expiry::{
+    create_persist_request, create_set_with_expiry_request, create_ttl_request, Expiry, Ttl,
+};
+#[cfg(feature = "serde")]
+use crate::format::Format;
+use crate::handshake::{accepted_version, create_hello_request};
+use crate::info::{create_info_request, create_ping_request, ServerInfo};
+use crate::keyspace::Keyspace;
+use crate::limits::Limits;
+use crate::protocol::{add_checksum, encode_frame_with_flags, has_checksum, request_id};
+use crate::request::{
+    create_compare_and_delete_request, create_compare_and_set_request, create_delete_request,
+    create_exists_request, create_get_request, create_set_if_absent_request, create_set_request,
+};
+use crate::scan::{create_scan_request, Entry, Scan, ScanPage};
+use crate::spec::{HEADER_SIZE, PROTOCOL_VERSION, SUPPORTED_VERSIONS};
+use crate::transport::{
+    check_integrity, condition_reply, counter_reply, exists_reply, frame_buffer, info_reply,
+    scan_reply, stored_value_reply, ttl_reply, unit_reply, ValueCodec,
+};
+
+/// A connection to an Ocis server. Requests are answered strictly in order.
+///
+/// After an error for which [`Error::is_fatal`] is true the connection may
+/// be out of sync with the server and should be dropped; after any other
+/// error, such as [`Error::PayloadTooLarge`], it stays usable;
+/// [`Client::reconnect`] replaces a broken connection.
+pub struct Client {
+    stream: TcpStream,
+    /// Where the connection was opened, for [`Client::reconnect`].
+    addrs: Vec<SocketAddr>,
+    /// Token replayed on every new connection, once one was accepted.
+    token: Option<Vec<u8>>,
+    version: u8,
+    last_id: u64,
+    values: ValueCodec,
+    checksums: bool,
+    limits: Limits,
+    chunk_size: usize,
+    keyspace: Option<Keyspace>,
+    #[cfg(feature = "serde")]
+    format: Option<Format>,
+}
+
+impl Client {
+    /// Connect and negotiate the protocol version with a HELLO request,
+    /// falling back to v1 against servers that predate it.
+    pub fn connect(addr: impl ToSocketAddrs) -> Result<Self> {
+        let addrs: Vec<SocketAddr> = addr.to_socket_addrs()?.collect();
+        let stream = TcpStream::connect(&addrs[..])?;
+        stream.set_nodelay(true)?;
+        let mut client = Client {
+            stream,
+            addrs,
+            token: None,
+            version: PROTOCOL_VERSION,
+            last_id: 0,
+            values: ValueCodec::default(),
+            checksums: false,
+            limits: Limits::default(),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            keyspace: None,
+            #[cfg(feature = "serde")]
+            format: None,
+        };
+        let reply = client.round_trip(&create_hello_request(SUPPORTED_VERSIONS))?;
+        client.version = accepted_version(&reply, SUPPORTED_VERSIONS)?;
+        Ok(client)
+    }
+
+    /// Connect, then authenticate with `token`. The token is kept and
+    /// replayed by [`Client::reconnect`].
+    pub fn connect_with_token(addr: impl ToSocketAddrs, token: &[u8]) -> Result<Self> {
+        let mut client = Client::connect(addr)?;
+        client.authenticate(token)?;
+        Ok(client)
+    }
+
+    /// Authenticate this connection with `token`. Fails with
+    /// [`Error::Unauthorized`] if the server rejects it. Once accepted the
+    /// token is kept and replayed by [`Client::reconnect`].
+    pub fn authenticate(&mut self, token: &[u8]) -> Result<()> {
+        let response = self.request(create_auth_request(token))?;
+        unit_reply(&response)?;
+        self.token = Some(token.to_vec());
+        Ok(())
+    }
+
+    /// Replace the connection with a new one to the same address, after a
+    /// fatal error for instance. The new connection negotiates its version
+    /// afresh, keeps the timeout and every other setting, and authenticates
+    /// with the kept token, if any.
+    pub fn reconnect(&mut self) -> Result<()> {
+        let stream = TcpStream::connect(&self.addrs[..])?;
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(self.stream.read_timeout()?)?;
+        stream.set_write_timeout(self.stream.write_timeout()?)?;
+        self.stream = stream;
+        self.version = PROTOCOL_VERSION;
+        self.last_id = 0;
+        let reply = self.round_trip(&create_hello_request(SUPPORTED_VERSIONS))?;
+        self.version = accepted_version(&reply, SUPPORTED_VERSIONS)?;
+        if let Some(token) = self.token.clone() {
+            self.authenticate(&token)?;
+        }
+        Ok(())
+    }
+
+    /// Apply `timeout` to every socket read and write; `None` waits forever.
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        self.stream.set_read_timeout(timeout)?;
+        self.stream.set_write_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Compress values written from now on; see [`crate::compression`].
+    /// Reads decompress values whatever this is set to. Counters are not
+    /// compressed; read them with [`Client::incr_by`], see
+    /// [`crate::counter`].
+    pub fn set_compression(&mut self, compression: Option<Compression>) {
+        self.values.compression = compression;
+    }
+
+    /// Encrypt values written from now on with `keyring`, and decrypt
+    /// values read with it; see [`crate::encryption`]. Counters stay in the
+    /// clear, and [`Client::get`] fails on them with [`Error::Tampered`]
+    /// while a keyring is set; read them with [`Client::incr_by`], see
+    /// [`crate::counter`].
+    #[cfg(feature = "encryption")]
+    pub fn set_keyring(&mut self, keyring: Option<Keyring>) {
+        self.values.keyring = keyring.map(Arc::new);
+    }
+
+    /// Confine every key from now on to `keyspace`; see
+    /// [`crate::keyspace`]. Keys are given and returned relative to it.
+    pub fn set_keyspace(&mut self, keyspace: Option<Keyspace>) {
+        self.keyspace = keyspace;
+    }
+
+    /// Format for [`Client::get_as`] and [`Client::set_as`].
+    #[cfg(feature = "serde")]
+    pub fn set_format(&mut self, format: Option<Format>) {
+        self.format = format;
+    }
+
+    /// Add a CRC32C trailer to requests from now on and require one on
+    /// their responses, failing with [`Error::Integrity`] if it is missing
+    /// or wrong. Needs a v3 connection; earlier versions have no room for
+    /// it and go unchecked.
+    pub fn set_checksums(&mut self, enabled: bool) {
+        self.checksums = enabled;
+    }
+
+    /// Refuse requests larger than `limits` from now on; see
+    /// [`crate::limits`].
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Adopt the limits the server reports in INFO, if it reports any, and
+    /// return the limits now in force.
+    pub fn fetch_limits(&mut self) -> Result<Limits> {
+        if let Some(limits) = self.info()?.limits {
+            self.limits = limits;
+        }
+        Ok(self.limits)
+    }
+
+    /// Split objects written from now on into chunks of `bytes`; see
+    /// [`crate::chunking`]. Readers refuse objects whose chunks are larger
+    /// than their value limit.
+    pub fn set_chunk_size(&mut self, bytes: usize) {
+        self.chunk_size = bytes;
+    }
+
+    /// Protocol version negotiated for this connection.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Fetch a value; `Ok(None)` if the key does not exist.
+    pub fn get(&mut self, key: &str) -> Result<Option<Vec<u8>>> {
+        let key = &self.key(key);
+        let response = self.request(create_get_request(key))?;
+        stored_value_reply(&response, key, &self.values)
+    }
+
+    pub fn set(&mut self, key: &str, value: &[u8]) -> Result<()> {
+        let key = &self.key(key);
+        let value = self.values.encode(key.as_bytes(), value);
+        let response = self.request(create_set_request(key, &value))?;
+        unit_reply(&response)
+    }
+
+    pub fn delete(&mut self, key: &str) -> Result<()> {
+        let key = &self.key(key);
+        let response = self.request(create_delete_request(key))?;
+        unit_reply(&response)
+    }
+
+    /// SET with per-request `options`. Returns the durability the server
+    /// reports for the write; `None` on connections older than v3, which
+    /// cannot carry options.
+    pub fn set_with(
+        &mut self,
+        key: &str,
+        value: &[u8],
+        options: &WriteOptions,
+    ) -> Result<Option<Durability>> {
+        let key = &self.key(key);
+        let frame = create_set_request(key, &self.values.encode(key.as_bytes(), value));
+        let response = self.request_with_flags(frame, options.flags())?;
+        unit_reply(&response)?;
+        Ok(honored_durability(&response))
+    }
+
+    /// DELETE with per-request `options`; see [`Client::set_with`].
+    pub fn delete_with(&mut self, key: &str, options: &WriteOptions) -> Result<Option<Durability>> {
+        let key = &self.key(key);
+        let response = self.request_with_flags(create_delete_request(key), options.flags())?;
+        unit_reply(&response)?;
+        Ok(honored_durability(&response))
+    }
+
+    /// Fetch and deserialize a value in the client's format; see
+    /// [`crate::format`]. Fails with [`Error::Codec`] if no format is set.
+    #[cfg(feature = "serde")]
+    pub fn get_as<T: DeserializeOwned>(&mut self, key: &str) -> Result<Option<T>> {
+        let format = self.format()?;
+        self.get_as_with(key, format)
+    }
+
+    /// Serialize and store a value in the client's format; see
+    /// [`Client::get_as`].
+    #[cfg(feature = "serde")]
+    pub fn set_as<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<()> {
+        let format = self.format()?;
+        self.set_as_with(key, value, format)
+    }
+
+    /// [`Client::get_as`] in `format`, whatever the client's format is.
+    #[cfg(feature = "serde")]
+    pub fn get_as_with<T: DeserializeOwned>(
+        &mut self,
+        key: &str,
+        format: Format,
+    ) -> Result<Option<T>> {
+        self.get(key)?
+            .map(|value| format.decode(&value))
+            .transpose()
+    }
+
+    /// [`Client::set_as`] in `format`, whatever the client's format is.
+    #[cfg(feature = "serde")]
+    pub fn set_as_with<T: Serialize + ?Sized>(
+        &mut self,
+        key: &str,
+        value: &T,
+        format: Format,
+    ) -> Result<()> {
+        self.set(key, &format.encode(value)?)
+    }
+
+    #[cfg(feature = "serde")]
+    fn format(&self) -> Result<Format> {
+        self.format
+            .ok_or_else(|| Error::Codec("no value format set".into()))
+    }
+
+    /// Check that the server is alive and answering.
+    pub fn ping(&mut self) -> Result<()> {
+        unit_reply(&self.request(create_ping_request())?)
+    }
+
+    /// Server statistics.
+    pub fn info(&mut self) -> Result<ServerInfo> {
+        info_reply(&self.request(create_info_request())?)
+    }
+
+    /// Whether `key` exists, without transferring its value.
+    pub fn exists(&mut self, key: &str) -> Result<bool> {
+        let key = &self.key(key);
+        let response = self.request(create_exists_request(key))?;
+        exists_reply(&response)
+    }
+
+    /// Store `value` only if `key` does not exist; `Ok(false)` if it does.
+    pub fn set_if_absent(&mut self, key: &str, value: &[u8]) -> Result<bool> {
+        let key = &self.key(key);
+        let value = self.values.encode(key.as_bytes(), value);
+        let response = self.request(create_set_if_absent_request(key, &value))?;
+        condition_reply(&response)
+    }
+
+    /// Replace the value of `key` only if it is currently `expected`;
+    /// `Ok(false)` if it is not, or if the key does not exist. Fails with
+    /// [`Error::Codec`] while compression or a keyring is set, since the
+    /// server compares the stored bytes.
+    pub fn compare_and_set(&mut self, key: &str, expected: &[u8], value: &[u8]) -> Result<bool> {
+        let key = &self.key(key);
+        let expected = self.values.encode_comparable(expected)?;
+        let value = self.values.encode_comparable(value)?;
+        let response = self.request(create_compare_and_set_request(key, &expected, &value))?;
+        condition_reply(&response)
+    }
+
+    /// Delete `key` only if its value is currently `expected`; `Ok(false)`
+    /// if it is not, or if the key does not exist. Fails like
+    /// [`Client::compare_and_set`] while compression or a keyring is set.
+    pub fn compare_and_delete(&mut self, key: &str, expected: &[u8]) -> Result<bool> {
+        let key = &self.key(key);
+        let expected = self.values.encode_comparable(expected)?;
+        let response = self.request(create_compare_and_delete_request(key, &expected))?;
+        condition_reply(&response)
+    }
+
+    /// Store `value` under `key` until `expiry`, after which the key reads
+    /// as absent.
+    pub fn set_with_expiry(&mut self, key: &str, value: &[u8], expiry: Expiry) -> Result<()> {
+        let key = &self.key(key);
+        let value = self.values.encode(key.as_bytes(), value);
+        let response = self.request(create_set_with_expiry_request(key, &value, expiry))?;
+        unit_reply(&response)
+    }
+
+    /// Remaining time to live of `key`; `Ok(None)` if it does not exist.
+    pub fn ttl(&mut self, key: &str) -> Result<Option<Ttl>> {
+        let key = &self.key(key);
+        let response = self.request(create_ttl_request(key))?;
+        ttl_reply(&response)
+    }
+
+    /// Remove the expiry of `key`; `Ok(false)` if it does not exist.
+    pub fn persist(&mut self, key: &str) -> Result<bool> {
+        let key = &self.key(key);
+        let response = self.request(create_persist_request(key))?;
+        exists_reply(&response)
+    }
+
+    /// Apply every write in `batch`, or none of them if any fails.
+    pub fn write_batch(&mut self, batch: &WriteBatch) -> Result<()> {
+        let batch = match &self.keyspace {
+            Some(keyspace) => Cow::Owned(keyspace.batch(batch)),
+            None => Cow::Borrowed(batch),
+        };
+        let batch = self.values.encode_batch(&batch);
+        self.limits.check_batch(&batch)?;
+        let response = self.request(create_batch_request(&batch))?;
+        unit_reply(&response)
+    }
+
+    /// Add `delta` to the counter at `key`, which starts at zero if absent,
+    /// and return the new value. Fails with [`Error::Overflow`] if the
+    /// result would not fit an `i64`.
+    pub fn incr_by(&mut self, key: &str, delta: i64) -> Result<i64> {
+        let key = &self.key(key);
+        let response = self.request(create_incr_by_request(key, delta))?;
+        counter_reply(&response)
+    }
+
+    /// Subtract `delta` from the counter at `key`; see [`Client::incr_by`].
+    pub fn decr_by(&mut self, key: &str, delta: i64) -> Result<i64> {
+        let key = &self.key(key);
+        let response = self.request(create_decr_by_request(key, delta))?;
+        counter_reply(&response)
+    }
+
+    /// Fetch one page of `scan`, starting at `cursor` or at the scan's start.
+    pub fn scan_page(&mut self, scan: &Scan, cursor: Option<&[u8]>) -> Result<ScanPage> {
+        let response = self.request(self.scan_request(scan, cursor))?;
+        let page = scan_reply(&response, &self.values)?;
+        Ok(match &self.keyspace {
+            Some(keyspace) => keyspace.page(page),
+            None => page,
+        })
+    }
+
+    /// Iterate over every entry of `scan`, fetching pages as needed.
+    pub fn scan(&mut self, scan: Scan) -> ScanIter<'_> {
+        ScanIter {
+            client: self,
+            scan,
+            cursor: None,
+            entries: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Store everything `reader` yields at `key` as a chunked object; see
+    /// [`crate::chunking`]. Replaces any value or object at `key`, and
+    /// deletes the chunks of an object it replaces.
+    pub fn put_object(&mut self, key: &str, mut reader: impl Read) -> Result<Manifest> {
+        let previous = self.manifest(key)?;
+        let mut manifest = Manifest::new(self.chunk_size);
+        let written = self
+            .write_chunks(key, &mut reader, &mut manifest)
+            .and_then(|()| self.set(key, &manifest.encode()));
+        if let Err(e) = written {
+            let _ = self.delete_chunks(key, &manifest);
+            return Err(e);
+        }
+        if let Some(previous) = previous {
+            self.delete_chunks(key, &previous)?;
+        }
+        Ok(manifest)
+    }
+
+    /// Read the object at `key` as it is fetched, one chunk at a time;
+    /// `Ok(None)` if the key does not exist. Fails with [`Error::Codec`] if
+    /// the key holds a plain value.
+    pub fn open_object(&mut self, key: &str) -> Result<Option<ObjectReader<'_>>> {
+        let Some(manifest) = self.object_manifest(key)? else {
+            return Ok(None);
+        };
+        Ok(Some(ObjectReader {
+            client: self,
+            key: key.to_string(),
+            manifest,
+            next: 0,
+            chunk: Vec::new(),
+            position: 0,
+        }))
+    }
+
+    /// Fetch the whole object at `key`; `Ok(None)` if the key does not
+    /// exist.
+    pub fn get_object(&mut self, key: &str) -> Result<Option<Vec<u8>>> {
+        let Some(manifest) = self.object_manifest(key)? else {
+            return Ok(None);
+        };
+        let mut object = Vec::new();
+        for index in 0..manifest.chunk_count() {
+            object.extend_from_slice(&self.read_chunk(key, &manifest, index)?);
+        }
+        Ok(Some(object))
+    }
+
+    /// Delete the object at `key` and its chunks, or a plain value;
+    /// `Ok(false)` if the key does not exist.
+    pub fn delete_object(&mut self, key: &str) -> Result<bool> {
+        let Some(stored) = self.get(key)? else {
+            return Ok(false);
+        };
+        self.delete(key)?;
+        if let Some(manifest) = Manifest::decode(&stored) {
+            self.delete_chunks(key, &manifest)?;
+        }
+        Ok(true)
+    }
+
+    /// Delete chunks of the object at `key` that its manifest does not
+    /// use, and return how many there were. A write to `key` in progress
+    /// loses its chunks, so only sweep keys nothing is writing.
+    pub fn sweep_object(&mut self, key: &str) -> Result<usize> {
+        let current = self.manifest(key)?;
+        let orphans = self
+            .scan(Scan::prefix(chunk_prefix(key)).keys_only())
+            .filter(|entry| match entry {
+                Ok(entry) => is_orphan(key, &entry.key, current.as_ref()),
+                Err(_) => true,
+            })
+            .map(|entry| entry.map(|entry| String::from_utf8_lossy(&entry.key).into_owned()))
+            .collect::<Result<Vec<_>>>()?;
+        for orphan in &orphans {
+            self.delete(orphan)?;
+        }
+        Ok(orphans.len())
+    }
+
+    /// The manifest at `key`, if it holds one.
+    fn manifest(&mut self, key: &str) -> Result<Option<Manifest>> {
+        Ok(self.get(key)?.as_deref().and_then(Manifest::decode))
+    }
+
+    /// The manifest at `key`, failing if the key holds a plain value or a
+    /// manifest whose chunks are over the value limit.
+    fn object_manifest(&mut self, key: &str) -> Result<Option<Manifest>> {
+        self.get(key)?
+            .map(|stored| Manifest::decode_within(key, &stored, &self.limits))
+            .transpose()
+    }
+
+    fn write_chunks(
+        &mut self,
+        key: &str,
+        reader: &mut impl Read,
+        manifest: &mut Manifest,
+    ) -> Result<()> {
+        loop {
+            let mut chunk = Vec::new();
+            reader
+                .take(manifest.chunk_size() as u64)
+                .read_to_end(&mut chunk)?;
+            if chunk.is_empty() {
+                return Ok(());
+            }
+            self.set(&manifest.push(key, &chunk), &chunk)?;
+            if chunk.len() < manifest.chunk_size() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn read_chunk(&mut self, key: &str, manifest: &Manifest, index: u32) -> Result<Vec<u8>> {
+        let chunk = self.get(&manifest.chunk_key(key, index))?;
+        manifest.check(key, index, chunk)
+    }
+
+    fn delete_chunks(&mut self, key: &str, manifest: &Manifest) -> Result<()> {
+        for chunk_key in manifest.chunk_keys(key) {
+            self.delete(&chunk_key)?;
+        }
+        Ok(())
+    }
+
+    /// `key` as stored on the server.
+    fn key<'k>(&self, key: &'k str) -> Cow<'k, str> {
+        match &self.keyspace {
+            Some(keyspace) => Cow::Owned(keyspace.key(key)),
+            None => Cow::Borrowed(key),
+        }
+    }
+
+    /// A SCAN request for the page of `scan` at `cursor`, inside the
+    /// keyspace.
+    fn scan_request(&self, scan: &Scan, cursor: Option<&[u8]>) -> Vec<u8> {
+        match &self.keyspace {
+            Some(keyspace) => {
+                let cursor = cursor.map(|cursor| keyspace.server_key(cursor));
+                create_scan_request(&keyspace.scan(scan), cursor.as_deref())
+            }
+            None => create_scan_request(scan, cursor),
+        }
+    }
+
+    /// Encode a v1 request frame for the negotiated version and exchange
+    /// it, checking that the response echoes the request id.
+    fn request(&mut self, frame: Vec<u8>) -> Result<Vec<u8>> {
+        self.request_with_flags(frame, 0)
+    }
+
+    fn request_with_flags(&mut self, frame: Vec<u8>, flags: u8) -> Result<Vec<u8>> {
+        self.last_id = self.last_id.wrapping_add(1);
+        let mut frame = encode_frame_with_flags(frame, self.version, self.last_id, flags);
+        if self.checksums {
+            frame = add_checksum(frame);
+        }
+        self.limits.check(&frame)?;
+        let response = self.round_trip(&frame)?;
+        check_integrity(&response, has_checksum(&frame))?;
+        let expected = (self.version > PROTOCOL_VERSION).then_some(self.last_id);
+        if request_id(&response) != expected {
+            return Err(Error::Protocol("response does not match request id".into()));
+        }
+        Ok(response)
+    }
+
+    /// Send one request frame and read back exactly one response frame.
+    fn round_trip(&mut self, request: &[u8]) -> Result<Vec<u8>> {
+        self.stream.write_all(request)?;
+
+        let mut header = [0u8; HEADER_SIZE];
+        self.stream.read_exact(&mut header)?;
+        let mut response = frame_buffer(&header)?;
+        self.stream.read_exact(&mut response[HEADER_SIZE..])?;
+        Ok(response)
+    }
+}
+
+/// Reader over a chunked object, returned by [`Client::open_object`].
+/// Chunks are fetched as they are needed and checked against their hashes;
+/// errors other than [`Error::Io`] surface as [`io::ErrorKind::InvalidData`]
+/// wrapping the [`Error`].
+pub struct ObjectReader<'a> {
+    client: &'a mut Client,
+    key: String,
+    manifest: Manifest,
+    next: u32,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl ObjectReader<'_> {
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+}
+
+impl Read for ObjectReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.chunk.len() {
+            if self.next == self.manifest.chunk_count() {
+                return Ok(0);
+            }
+            self.chunk = self
+                .client
+                .read_chunk(&self.key, &self.manifest, self.next)
+                .map_err(io::Error::from)?;
+            self.next += 1;
+            self.position = 0;
+        }
+        let n = buf.len().min(self.chunk.len() - self.position);
+        buf[..n].copy_from_slice(&self.chunk[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+/// Iterator over a scan, returned by [`Client::scan`]. Stops after the
+/// first error.
+pub struct ScanIter<'a> {
+    client: &'a mut Client,
+    scan: Scan,
+    cursor: Option<Vec<u8>>,
+    entries: VecDeque<Entry>,
+    done: bool,
+}
+
+impl Iterator for ScanIter<'_> {
+    type Item = Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.entries.is_empty() && !self.done {
+            match self.client.scan_page(&self.scan, self.cursor.as_deref()) {
+                Ok(page) => {
+                    self.done = page.cursor.is_none();
+                    self.cursor = page.cursor;
+                    self.entries = page.entries.into();
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        self.entries.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::info::INFO_FORMAT;
+    use crate::limits::MAX_VALUE_LENGTH;
+    use crate::mock::{Fault, MockServer};
+    use crate::spec::LATEST_VERSION;
+    use crate::Error;
+    use std::time::SystemTime;
+
+    #[test]
+    fn set_get_delete() {
+        let server = MockServer::start().unwrap();
+        let mut client = Client::connect(server.addr()).unwrap();
+
+        client.set("k", b"v").unwrap();
+        assert_eq!(client.get("k").unwrap(), Some(b"v".to_vec()));
+        assert!(client.exists("k").unwrap());
+        client.delete("k").unwrap();
+        assert_eq!(client.get("k").unwrap(), None);
+        assert!(!client.exists("k").unwrap());
+    }
+
+    #[test]
+    fn conditional_writes() {
+        let server = MockServer::start().unwrap();
+        let mut client = Client::connect(server.addr()).unwrap();
+
+        assert!(client.set_if_absent("lock", b"a").unwrap());
+        assert!(!client.set_if_absent("lock", b"b").unwrap());
+        assert!(!client.compare_and_set("lock", b"b", b"c").unwrap());
+        assert!(client.compare_and_set("lock", b"a", b"c").unwrap());
+        assert_eq!(client.get("lock").unwrap(), Some(b"c".to_vec()));
+
+        assert!(!client.compare_and_delete("lock", b"a").unwrap());
+        assert!(client.compare_and_delete("lock", b"c").unwrap());
+        assert!(!client.exists("lock").unwrap());
+        assert!(!client.compare_and_set("lock", b"c", b"d").unwrap());
+    }
+
+    #[test]
+    fn escapes_values_that_look_like_envelopes() {
+        let server = MockServer::start().unwrap();
+        let mut client = Client::connect(server.addr()).unwrap();
+        let lookalike = [0xC5, b'O', b'Z', 1, 4, 0, 0, 0, b'd', b'a', b't', b'a'];
+
+        client.set("k", &lookalike).unwrap();
+        assert_ne!(server.get(b"k").unwrap(), lookalike);
+        assert_eq!(client.get("k").unwrap(), Some(lookalike.to_vec()));
+        let short = &lookalike[..4];
+        assert!(client.compare_and_set("k", &lookalike, short).unwrap());
+        assert_eq!(client.get("k").unwrap(), Some(short.to_vec()));
+        assert!(client.compare_and_delete("k", short).unwrap());
+
+        let sealed = [0xC5, b'O', b'E', 1, 0, 0, 0, b'd', b'a', b't', b'a'];
+        client.set("s", &sealed).unwrap();
+        assert_ne!(server.get(b"s").unwrap(), sealed);
+        assert_eq!(client.get("s").unwrap(), Some(sealed.to_vec()));
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn keyrings_read_back_values_that_look_sealed() {
+        let server = MockServer::start().unwrap();
+        let mut client = Client::connect(server.addr()).unwrap();
+        let sealed = [0xC5, b'O', b'E', 9, 0, 0, 0, b'd', b'a', b't', b'a'];
+        client.set("plain", &sealed).unwrap();
+
+        client.set_keyring(Some(Keyring::new(1, [7; 32]).allow_plaintext()));
+        assert_eq!(client.get("plain").unwrap(), Some(sealed.to_vec()));
+        client.set_keyring(Some(Keyring::new(1, [7; 32])));
+        client.set("k", &sealed).unwrap();
+        assert_eq!(client.get("k").unwrap(), Some(sealed.to_vec()));
+    }
+
+    #[test]
+    fn counters() {
+        let server = MockServer::start().unwrap();
+        let mut client = Client::connect(server.addr()).unwrap();
+
+        assert_eq!(client.incr_by("n", 5).unwrap(), 5);
+        assert_eq!(client.decr_by("n", 7).unwrap(), -2);
+        assert_eq!(
+            client.get("n").unwrap(),
+            Some((-2i64).to_le_bytes().to_vec())
+        );
+
+        let err = client.decr_by("n", i64::MAX).unwrap_err();
+        assert!(matches!(err, Error::Overflow));
+        assert!(!err.is_fatal());
+        assert_eq!(client.incr_by("n", 0).unwrap(), -2);
+
+        client.set("s", b"text").unwrap();
+        assert!(matches!(client.incr_by("s", 1), Err(Error::Server(_))));
+    }
+
+    #[test]
+    fn expiring_keys() {
+        let server = MockServer::start().unwrap();
+        let mut client = Client::connect(server.addr()).unwrap();
+        let hour = Duration::from_secs(3600);
+
+        client
+            .set_with_expiry("session", b"s", Expiry::After(hour))
+            .unwrap();
+        let Some(Ttl::Remaining(remaining)) = client.ttl("session").unwrap() else {
+            panic!("expected a time to live");
+        };
+        assert!(remaining <= hour && remaining > hour / 2);
+        assert!(client.persist("session").unwrap());
+        assert_eq!(client.ttl("session").unwrap(), Some(Ttl::Persistent));
+
+        let past = SystemTime::now() - hour;
+        client
+            .set_with_expiry("old", b"o", Expiry::At(past))
+            .unwrap();
+        assert_eq!(client.get("old").unwrap(), None);
+        assert_eq!(client.ttl("old").unwrap(), None);
+        assert!(!client.persist("old").unwrap());
+    }
+
+    #[test]
+    fn write_batches() {
+        let server = MockServer::start().unwrap();
+        server.insert("old", "x");
+        let mut client = Client::connect(server.addr()).unwrap();
+
+        let batch = WriteBatch::new().set("a", "1").set("b", "2").delete("old");
+        client.write_batch(&batch).unwrap();
+        assert_eq!(client.get("a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(client.get("b").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(client.get("old").unwrap(), None);
+
+        let invalid = WriteBatch::new().set("c", "3").set("", "4");
+        assert!(matches!(
+            client.write_batch(&invalid),
+            Err(Error::Server(_))
+        ));
+        assert_eq!(client.get("c").unwrap(), None);
+    }
+
+    #[test]
+    fn reports_honored_durability() {
+        let server = MockServer::start().unwrap();
+        let mut client = Client::connect(server.addr()).unwrap();
+        let strict = WriteOptions::new().durability(Durability::Strict);
+
+        assert_eq!(
+            client.set_with("k", b"v", &strict).unwrap(),
+            Some(Durability::Strict)
+        );
+        assert_eq!(
+            client.delete_with("k", &WriteOptions::new()).unwrap(),
+            Some(Durability::Fast)
+        );
+
+        let server = MockServer::start_with_versions(&[1, 2]).unwrap();
+        let mut client = Client::connect(server.addr()).unwrap();
+        assert_eq!(client.set_with("k", b"v", &strict).unwrap(), None);
+        assert_eq!(client.get("k").unwrap(), Some(b"v".to_vec()));
+    }
+
+    #[test]
+    fn pings_and_reports_info() {
+        let server = MockServer::start().unwrap();
+        let mut client = Client::connect(server.addr()).unwrap();
+        client.ping().unwrap();
+        client.set("k", b"v").unwrap();
+
+        let info = client.info().unwrap();
+        assert_eq!(info.format, INFO_FORMAT);
+        assert_eq!(info.durability, Some(Durability::Fast));
+        assert_eq!(info.memtable_bytes, 2);
+    }
+
+    #[test]
+    fn requires_auth_when_the_server_has_a_token() {
+        let server = MockServer::start_with_token(b"secret").unwrap();
+        let mut client = Client::connect(server.addr()).unwrap();
+        client.ping().unwrap();
+        assert!(matches!(client.get("k"), Err(Error::Unauthorized)));
+        assert!(matches!(
+            client.authenticate(b"wrong"),
+            Err(Error::Unauthorized)
+        ));
+
+        let mut client = Client::connect_with_token(server.addr(), b"secret").unwrap();
+        client.set("k", b"v").unwrap();
+        assert_eq!(client.get("k").unwrap(), Some(b"v".to_vec()));
+
+        let server = MockServer::start().unwrap();
+        Client::connect_with_token(server.addr(), b"any").unwrap();
+    }
+
+    #[test]
+    fn reconnect_replays_the_token() {
+        let server = MockServer::start_with_token(b"secret").unwrap();
+        let mut client = Client::connect_with_token(server.addr(), b"secret").unwrap();
+        client.set("k", b"v").unwrap();
+        server.push_fault(Fault::Drop);
+        assert!(client.get("k").unwrap_err().is_fatal());
+
+        client.reconnect().unwrap();
+        assert_eq!(client.get("k").unwrap(), Some(b"v".to_vec()));
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn compresses_values_transparently() {
+        use crate::compression::Codec;
+
+        let server = MockServer::start().unwrap();
+        let mut client = Client::connect(server.addr()).unwrap();
+        client.set_compression(Some(Compression::new(Codec::Lz4)));
+        let json = br#"{"event":"login","ok":true}"#.repeat(200);
+        client.set("k", &json).unwrap();
+        client
+            .write_batch(&WriteBatch::new().set("b", json.clone()))
+            .unwrap();
+
+        assert!(server.get(b"k").unwrap().len() < json.len() / 5);
+        assert!(matches!(
+            client.compare_and_set("k", &json, b"new"),
+            Err(Error::Codec(_))
+        ));
+        assert!(matches!(
+            client.compare_and_delete("k", &json),
+            Err(Error::Codec(_))
+        ));
+        let mut reader = Client::connect(server.addr()).unwrap();
+        assert_eq!(reader.get("k").unwrap(), Some(json.clone()));
+        let page = reader.scan_page(&Scan::all(), None).unwrap();
+        assert_eq!(page.entries[0].value.as_deref(), Some(&json[..]));
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn encrypts_values_and_detects_tampering() {
+        let server = MockServer::start().unwrap();
+        let mut client = Client::connect(server.addr()).unwrap();
+        client.set_keyring(Some(Keyring::new(1, [7; 32])));
+        client.set("a", b"secret").unwrap();
+        client
+            .write_batch(&WriteBatch::new().set("b", b"batched".to_vec()))
+            .unwrap();
+
+        let stored = server.get(b"a").unwrap();
+        assert!(!stored.windows(6).any(|w| w == b"secret"));
+        assert_eq!(client.get("a").unwrap(), Some(b"secret".to_vec()));
+        let page = client.scan_page(&Scan::all(), None).unwrap();
+        assert_eq!(page.entries[1].value.as_deref(), Some(&b"batched"[..]));
+        assert!(matches!(
+            client.compare_and_set("a", b"secret", b"new"),
+            Err(Error::Codec(_))
+        ));
+
+        server.insert("c", stored);
+        assert!(matches!(client.get("c"), Err(Error::Tampered)));
+        let mut plain = Client::connect(server.addr()).unwrap();
+        assert!(matches!(plain.get("a"), Err(Error::Codec(_))));
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn counters_bypass_the_keyring() {
+        use crate::counter::encode_counter;
+
+        let server = MockServer::start().unwrap();
+        let mut client = Client::connect(server.addr()).unwrap();
+        client.set_keyring(Some(Keyring::new(1, [7; 32])));
+
+        assert_eq!(client.incr_by("n", 5).unwrap(), 5);
+        assert_eq!(server.get(b"n").unwrap(), encode_counter(5));
+        assert!(matches!(client.get("n"), Err(Error::Tampered)));
+        assert_eq!(client.incr_by("n", 0).unwrap(), 5);
+
+        client.set("m", &encode_counter(1)).unwrap();
+        assert!(matches!(client.incr_by("m", 1), Err(Error::Server(_))));
+    }
+
+    #[cfg(all(feature = "json", feature = "cbor"))]
+    #[test]
+    fn reads_and_writes_typed_values() {
+        use std::collections::BTreeMap;
+
+        let server = MockServer::start().unwrap();
+        let mut client = Client::connect(server.addr()).unwrap();
+        let scores = BTreeMap::from([("ada".to_string(), 3u32), ("bob".to_string(), 5)]);
+        assert!(matches!(client.set_as("s", &scores), Err(Error::Codec(_))));
+
+        client.set_format(Some(Format::Json));
+        client.set_as("s", &scores).unwrap();
+        assert_eq!(server.get(b"s").unwrap(), br#"{"ada":3,"bob":5}"#);
+        assert_eq!(client.get_as("s").unwrap(), Some(scores.clone()));
+        assert_eq!(client.get_as::<u32>("missing").unwrap(), None);
+
+        client.set_as_with("c", &scores, Format::Cbor).unwrap();
+        assert_eq!(client.get_as_with("c", Format::Cbor).unwrap(), Some(scores));
+        let error = client.get_as::<Vec<u32>>("s").unwrap_err();
+        assert!(matches!(error, Error::Deserialize(_)));
+        assert!(!error.is_fatal());
+        assert!(matches!(
+            client.get_as::<String>("c"),
+            Err(Error::Deserialize(_))
+        ));
+    }
+
+    #[test]
+    fn keyspaces_isolate_keys() {
+        use crate::keyspace::{decode_key, encode_key};
+
+        let server = MockServer::start().unwrap();
+        let billing = Keyspace::new("billing");
+        let mut a = Client::connect(server.addr()).unwrap();
+        a.set_keyspace(Some(billing.clone()));
+        let mut b = Client::connect(server.addr()).unwrap();
+        b.set_keyspace(Some(billing.nested("jobs")));
+        let mut plain = Client::connect(server.addr()).unwrap();
+
+        a.set("k", b"a").unwrap();
+        b.set("k", b"b").unwrap();
+        plain.set("k", b"plain").unwrap();
+        assert_eq!(a.get("k").unwrap(), Some(b"a".to_vec()));
+        assert_eq!(b.get("k").unwrap(), Some(b"b".to_vec()));
+        assert_eq!(server.get(billing.key("k").as_bytes()), Some(b"a".to_vec()));
+
+        for id in [10u64, 9, 100] {
+            let key = encode_key(&("invoice", id));
+            a.write_batch(&WriteBatch::new().set(key, id.to_string()))
+                .unwrap();
+        }
+        let keys: Vec<(String, u64)> = a
+            .scan(Scan::prefix(encode_key("invoice")))
+            .map(|entry| decode_key(std::str::from_utf8(&entry.unwrap().key).unwrap()).unwrap())
+            .collect();
+        let ids: Vec<u64> = keys.iter().map(|(_, id)| *id).collect();
+        assert_eq!(ids, [9, 10, 100]);
+        let all: Vec<Vec<u8>> = a
+            .scan(Scan::all().limit(2))
+            .map(|entry| entry.unwrap().key)
+            .collect();
+        assert_eq!(all.len(), 4);
+        assert_eq!(all[0], b"k");
+    }
+
+    #[test]
+    fn checksums_detect_corrupted_responses() {
+        let server = MockServer::start().unwrap();
+        server.insert("k", b"value".to_vec());
+        let mut client = Client::connect(server.addr()).unwrap();
+        client.set_checksums(true);
+        assert_eq!(client.get("k").unwrap(), Some(b"value".to_vec()));
+
+        let last = HEADER_SIZE + 9 + 4;
+        server.push_fault(Fault::Corrupt(last));
+        let error = client.get("k").unwrap_err();
+        assert!(matches!(error, Error::Integrity));
+        assert!(error.is_fatal());
+
+        let mut unchecked = Client::connect(server.addr()).unwrap();
+        server.push_fault(Fault::Corrupt(last));
+        assert_eq!(unchecked.get("k").unwrap(), Some(b"valud".to_vec()));
+    }
+
+    #[test]
+    fn stores_large_objects_in_chunks() {
+        let server = MockServer::start().unwrap();
+        let mut client = Client::connect(server.addr()).unwrap();
+        client.set_chunk_size(4);
+        let first = client.put_object("model", &b"0123456789"[..]).unwrap();
+        assert_eq!((first.len(), first.chunk_count()), (10, 3));
+        assert_eq!(
+            server.get(first.chunk_key("model", 2).as_bytes()),
+            Some(b"89".to_vec())
+        );
+
+        let mut object = client.open_object("model").unwrap().unwrap();
+        let mut buffer = [0; 3];
+        object.read_exact(&mut buffer).unwrap();
+        assert_eq!(&buffer, b"012");
+        let mut rest = Vec::new();
+        object.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"3456789");
+
+        let second = client.put_object("model", &b"abcd"[..]).unwrap();
+        assert_eq!(client.get_object("model").unwrap(), Some(b"abcd".to_vec()));
+        assert!(first
+            .chunk_keys("model")
+            .all(|key| server.get(key.as_bytes()).is_none()));
+
+        server.insert(second.chunk_key("model", 0), "abcD");
+        assert!(matches!(client.get_object("model"), Err(Error::Codec(_))));
+        let mut object = client.open_object("model").unwrap().unwrap();
+        let err = object.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        server.insert(first.chunk_key("model", 0), "orphan");
+        assert_eq!(client.sweep_object("model").unwrap(), 1);
+        assert!(server
+            .get(second.chunk_key("model", 0).as_bytes())
+            .is_some());
+
+        assert!(client.delete_object("model").unwrap());
+        assert!(!client.delete_object("model").unwrap());
+        assert_eq!(client.sweep_object("model").unwrap(), 0);
+        assert_eq!(client.open_object("model").unwrap().map(|_| ()), None);
+        client.set("plain", b"v").unwrap();
+        assert!(matches!(client.get_object("plain"), Err(Error::Codec(_))));
+        client.put_object("empty", std::io::empty()).unwrap();
+        assert_eq!(client.get_object("empty").unwrap(), Some(Vec::new()));
+
+        // A manifest claiming chunks no server would store is refused
+        // before any chunk is read or any buffer sized from it.
+        let mut huge = Manifest::new(MAX_VALUE_LENGTH + 1);
+        huge.push("huge", b"x");
+        let mut stored = huge.encode();
+        stored[11..19].copy_from_slice(&(MAX_VALUE_LENGTH as u64 + 1).to_le_bytes());
+        server.insert("huge", stored);
+        assert!(matches!(client.get_object("huge"), Err(Error::Codec(_))));
+        assert!(matches!(client.open_object("huge"), Err(Error::Codec(_))));
+    }
+
+    #[test]
+    fn refuses_oversized_requests_before_sending() {
+        let server = MockServer::start_with_limits(Limits {
+            max_packet_size: 1024,
+            max_key_length: 16,
+            max_value_length: 256,
+        })
+        .unwrap();
+        let mut client = Client::connect(server.addr()).unwrap();
+        let err = client.set("k", &vec![0; MAX_VALUE_LENGTH + 1]).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::PayloadTooLarge {
+                limit: MAX_VALUE_LENGTH,
+                ..
+            }
+        ));
+        assert!(!err.is_fatal());
+
+        let limits = client.fetch_limits().unwrap();
+        assert_eq!(limits.max_value_length, 256);
+        assert!(matches!(
+            client.set("k", &[0; 257]),
+            Err(Error::PayloadTooLarge {
+                size: 257,
+                limit: 256
+            })
+        ));
+        assert!(matches!(
+            client.get(&"k".repeat(17)),
+            Err(Error::KeyTooLong {
+                length: 17,
+                limit: 16
+            })
+        ));
+        let batch = WriteBatch::new().set("k", vec![0; 257]);
+        assert!(matches!(
+            client.write_batch(&batch),
+            Err(Error::PayloadTooLarge { .. })
+        ));
+        assert_eq!(server.request_count(), 1);
+        client.set("k", &[0; 256]).unwrap();
+        assert_eq!(server.get(b"k"), Some(vec![0; 256]));
+    }
+
+    #[test]
+    fn negotiates_latest_version() {
+        let server = MockServer::start().unwrap();
+        let mut client = Client::connect(server.addr()).unwrap();
+        assert_eq!(client.version(), LATEST_VERSION);
+
+        client.set("k", b"v").unwrap();
+        assert_eq!(client.get("k").unwrap(), Some(b"v".to_vec()));
+    }
+
+    #[test]
+    fn falls_back_to_v1_against_older_servers() {
+        let server = MockServer::start_with_versions(&[1]).unwrap();
+        let mut client = Client::connect(server.addr()).unwrap();
+        assert_eq!(client.version(), 1);
+
+        client.set("k", b"v").unwrap();
+        assert_eq!(client.get("k").unwrap(), Some(b"v".to_vec()));
+    }
+
+    #[test]
+    fn scan_pages_transparently() {
+        let server = MockServer::start().unwrap();
+        for key in ["a", "b1", "b2", "b3", "c"] {
+            server.insert(key, key.to_uppercase());
+        }
+        let mut client = Client::connect(server.addr()).unwrap();
+
+        let keys: Vec<_> = client
+            .scan(Scan::prefix("b").limit(2).keys_only())
+            .map(|entry| entry.unwrap().key)
+            .collect();
+        assert_eq!(keys, [b"b1", b"b2", b"b3"]);
+        assert_eq!(server.request_count(), 2);
+
+        let page = client.scan_page(&Scan::range("a", "b2"), None).unwrap();
+        assert_eq!(page.entries.len(), 2);
+        assert_eq!(page.entries[1].value.as_deref(), Some(&b"B1"[..]));
+        assert_eq!(page.cursor, None);
+    }
+
+    #[test]
+    fn server_errors_keep_the_connection() {
+        let server = MockServer::start().unwrap();
+        server.insert("k", "v");
+        server.push_fault(Fault::Error("boom".into()));
+        let mut client = Client::connect(server.addr()).unwrap();
+
+        let err = client.get("k").unwrap_err();
+        assert!(matches!(&err, Error::Server(m) if m == "boom"));
+        assert!(!err.is_fatal());
+        assert_eq!(client.get("k").unwrap(), Some(b"v".to_vec()));
+    }
+
+    #[test]
+    fn truncated_response_is_fatal() {
+        let server = MockServer::start().unwrap();
+        server.push_fault(Fault::Truncate(5));
+        let mut client = Client::connect(server.addr()).unwrap();
+
+        let err = client.get("k").unwrap_err();
+        assert!(matches!(err, Error::Io(_)));
+        assert!(err.is_fatal());
+    }
+}
@@ -0,0 +1,284 @@
+//! Large objects: values split into chunks under their own keys.
+//!
+//! A value larger than a frame can carry is stored as chunks of at most the
+//! client's chunk size ([`DEFAULT_CHUNK_SIZE`] unless set), each under a
+//! chunk key, and a manifest under the object's key, little-endian:
+//!
+//! ```text
+//! magic [0xC5, 'O', 'M'] | object id u64 | length u64 | chunk size u32
+//! | chunk count u32 | chunk count × SHA-256 of the chunk (32 bytes)
+//! ```
+//!
+//! Chunk `i` of object `id` at `key` is stored at [`chunk_key`]: the key, a
+//! NUL, `id` as 16 hex digits, a NUL and `i` as 8 hex digits. Each write
+//! of an object draws a new id. Its chunks are written first and the
+//! manifest last, so readers never see a manifest whose chunks are not all
+//! there; the replaced object's chunks are deleted after that. Readers
+//! refuse a manifest whose chunk size is over their value limit, check
+//! every chunk against its hash, and fail the read with [`Error::Codec`]
+//! on a chunk that is missing or does not match.
+//!
+//! Chunks and manifests are ordinary values, so compression, encryption and
+//! keyspaces apply to them. A write that fails part-way deletes the chunks
+//! it wrote if it still can; chunks left behind by a crash, or by two
+//! writes to one key racing, are removed by the clients' `sweep_object`.
+
+#[cfg(any(feature = "blocking", feature = "tokio"))]
+use std::collections::hash_map::RandomState;
+#[cfg(any(feature = "blocking", feature = "tokio"))]
+use std::hash::{BuildHasher, Hasher};
+#[cfg(any(feature = "blocking", feature = "tokio"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+use crate::limits::Limits;
+
+const MAGIC: [u8; 3] = [0xC5, b'O', b'M'];
+const HASH_SIZE: usize = 32;
+const MANIFEST_HEADER_SIZE: usize = MAGIC.len() + 8 + 8 + 4 + 4;
+
+/// Chunk size clients start with (4 MiB), well inside the value limit
+/// with room for compression and encryption envelopes.
+pub const DEFAULT_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Where the chunks of one write of an object are and what they hash to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Manifest {
+    id: u64,
+    length: u64,
+    chunk_size: u32,
+    hashes: Vec<[u8; HASH_SIZE]>,
+}
+
+impl Manifest {
+    /// An empty manifest with a fresh id, for chunks of `chunk_size` bytes.
+    #[cfg(any(feature = "blocking", feature = "tokio"))]
+    pub(crate) fn new(chunk_size: usize) -> Self {
+        Manifest {
+            id: new_id(),
+            length: 0,
+            chunk_size: chunk_size.clamp(1, u32::MAX as usize) as u32,
+            hashes: Vec::new(),
+        }
+    }
+
+    /// Id of the write this manifest describes.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Length of the object in bytes.
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Size of every chunk but the last.
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size as usize
+    }
+
+    pub fn chunk_count(&self) -> u32 {
+        self.hashes.len() as u32
+    }
+
+    /// Key of chunk `index` of this object, stored at `key`.
+    pub fn chunk_key(&self, key: &str, index: u32) -> String {
+        chunk_key(key, self.id, index)
+    }
+
+    /// Keys of every chunk of this object, stored at `key`, in order.
+    pub fn chunk_keys<'a>(&'a self, key: &'a str) -> impl Iterator<Item = String> + 'a {
+        (0..self.chunk_count()).map(move |index| self.chunk_key(key, index))
+    }
+
+    /// Record `chunk` as the next chunk and return the key to store it at.
+    #[cfg(any(feature = "blocking", feature = "tokio"))]
+    pub(crate) fn push(&mut self, key: &str, chunk: &[u8]) -> String {
+        self.length += chunk.len() as u64;
+        self.hashes.push(Sha256::digest(chunk).into());
+        self.chunk_key(key, self.chunk_count() - 1)
+    }
+
+    /// Chunk `index` of the object at `key`, as read back: fails if it is
+    /// missing or does not match its hash.
+    pub fn check(&self, key: &str, index: u32, chunk: Option<Vec<u8>>) -> Result<Vec<u8>> {
+        let chunk =
+            chunk.ok_or_else(|| Error::Codec(format!("chunk {index} of {key:?} is missing")))?;
+        if self.hashes.get(index as usize) != Some(&Sha256::digest(&chunk).into()) {
+            return Err(Error::Codec(format!(
+                "chunk {index} of {key:?} does not match its hash"
+            )));
+        }
+        Ok(chunk)
+    }
+
+    /// The manifest as stored.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(MANIFEST_HEADER_SIZE + self.hashes.len() * HASH_SIZE);
+        buffer.extend_from_slice(&MAGIC);
+        buffer.extend_from_slice(&self.id.to_le_bytes());
+        buffer.extend_from_slice(&self.length.to_le_bytes());
+        buffer.extend_from_slice(&self.chunk_size.to_le_bytes());
+        buffer.extend_from_slice(&self.chunk_count().to_le_bytes());
+        for hash in &self.hashes {
+            buffer.extend_from_slice(hash);
+        }
+        buffer
+    }
+
+    /// Decode the manifest of the object at `key` as read back: fails with
+    /// [`Error::Codec`] if `stored` is not one, or if its chunks are larger
+    /// than `limits` let a value be, as no honest writer stores them.
+    pub fn decode_within(key: &str, stored: &[u8], limits: &Limits) -> Result<Self> {
+        let manifest = Manifest::decode(stored)
+            .ok_or_else(|| Error::Codec(format!("{key:?} is not a chunked object")))?;
+        if manifest.chunk_size() > limits.max_value_length {
+            return Err(Error::Codec(format!(
+                "chunks of {key:?} are larger than the value limit"
+            )));
+        }
+        Ok(manifest)
+    }
+
+    /// Decode a stored manifest; `None` if `stored` is not one.
+    pub fn decode(stored: &[u8]) -> Option<Self> {
+        let header = stored.get(..MANIFEST_HEADER_SIZE)?;
+        if !header.starts_with(&MAGIC) {
+            return None;
+        }
+        let field = |at: usize, n: usize| &header[MAGIC.len() + at..MAGIC.len() + at + n];
+        let id = u64::from_le_bytes(field(0, 8).try_into().unwrap());
+        let length = u64::from_le_bytes(field(8, 8).try_into().unwrap());
+        let chunk_size = u32::from_le_bytes(field(16, 4).try_into().unwrap());
+        let count = u32::from_le_bytes(field(20, 4).try_into().unwrap());
+        let hashes = &stored[MANIFEST_HEADER_SIZE..];
+        if chunk_size == 0
+            || length.div_ceil(chunk_size as u64) != count as u64
+            || hashes.len() != count as usize * HASH_SIZE
+        {
+            return None;
+        }
+        Some(Manifest {
+            id,
+            length,
+            chunk_size,
+            hashes: hashes
+                .chunks_exact(HASH_SIZE)
+                .map(|hash| hash.try_into().unwrap())
+                .collect(),
+        })
+    }
+}
+
+/// Key of chunk `index` of write `id` of the object at `key`.
+pub fn chunk_key(key: &str, id: u64, index: u32) -> String {
+    format!("{key}\0{id:016x}\0{index:08x}")
+}
+
+/// Common prefix of the chunk keys of every write of the object at `key`.
+pub fn chunk_prefix(key: &str) -> String {
+    format!("{key}\0")
+}
+
+/// Whether `candidate` is the key of a chunk of the object at `key` that
+/// `current`, the object's manifest if any, does not use.
+pub fn is_orphan(key: &str, candidate: &[u8], current: Option<&Manifest>) -> bool {
+    let Some(rest) = candidate
+        .strip_prefix(key.as_bytes())
+        .and_then(|rest| rest.strip_prefix(b"\0"))
+    else {
+        return false;
+    };
+    let is_hex = |digits: &[u8]| {
+        digits
+            .iter()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    };
+    if rest.len() != 16 + 1 + 8 || rest[16] != 0 || !is_hex(&rest[..16]) || !is_hex(&rest[17..]) {
+        return false;
+    }
+    let id = std::str::from_utf8(&rest[..16])
+        .ok()
+        .and_then(|digits| u64::from_str_radix(digits, 16).ok());
+    id != current.map(Manifest::id)
+}
+
+/// A fresh object id: the time, hashed with a randomly keyed hasher so
+/// that ids drawn in the same instant by different clients differ too.
+#[cfg(any(feature = "blocking", feature = "tokio"))]
+fn new_id() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    hasher.write_u128(now.as_nanos());
+    hasher.finish()
+}
+
+#[cfg(all(test, any(feature = "blocking", feature = "tokio")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifests_round_trip_and_check_chunks() {
+        let mut manifest = Manifest::new(4);
+        assert_eq!(
+            manifest.push("model", b"abcd"),
+            chunk_key("model", manifest.id(), 0)
+        );
+        manifest.push("model", b"ef");
+        assert_eq!(manifest.len(), 6);
+        assert_eq!(Manifest::decode(&manifest.encode()), Some(manifest.clone()));
+        assert_eq!(Manifest::decode(b"plain value"), None);
+        let encoded = manifest.encode();
+        assert_eq!(Manifest::decode(&encoded[..encoded.len() - 1]), None);
+        let limits = Limits {
+            max_value_length: 4,
+            ..Limits::default()
+        };
+        assert_eq!(
+            Manifest::decode_within("model", &encoded, &limits).unwrap(),
+            manifest
+        );
+        let limits = Limits {
+            max_value_length: 3,
+            ..limits
+        };
+        assert!(matches!(
+            Manifest::decode_within("model", &encoded, &limits),
+            Err(Error::Codec(_))
+        ));
+        assert!(matches!(
+            Manifest::decode_within("model", b"plain value", &limits),
+            Err(Error::Codec(_))
+        ));
+
+        assert_eq!(
+            manifest.check("model", 1, Some(b"ef".to_vec())).unwrap(),
+            b"ef"
+        );
+        assert!(matches!(
+            manifest.check("model", 1, Some(b"eF".to_vec())),
+            Err(Error::Codec(_))
+        ));
+        assert!(matches!(
+            manifest.check("model", 0, None),
+            Err(Error::Codec(_))
+        ));
+        assert_ne!(Manifest::new(4).id(), manifest.id());
+
+        let current = manifest.chunk_key("model", 0);
+        let stale = chunk_key("model", manifest.id() ^ 1, 0);
+        assert!(!is_orphan("model", current.as_bytes(), Some(&manifest)));
+        assert!(is_orphan("model", stale.as_bytes(), Some(&manifest)));
+        assert!(is_orphan("model", current.as_bytes(), None));
+        assert!(!is_orphan("model", b"model\0other", None));
+        assert!(!is_orphan("mode", current.as_bytes(), None));
+    }
+}
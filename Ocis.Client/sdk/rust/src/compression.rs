@@ -0,0 +1,221 @@
+//! Client-side value compression.
+//!
+//! With [`Compression`] configured, a client compresses values of at least
+//! the threshold size before SET and stores them in an envelope:
+//!
+//! ```text
+//! magic [0xC5, 'O', 'Z'] | codec u8 | original length u32 | compressed bytes
+//! ```
+//!
+//! Plain SETs, SETs with an expiry, SET-if-absent and batch SETs are
+//! compressed. The server compares the values of compare-and-set and
+//! compare-and-delete byte for byte with what it stores, which compression
+//! makes unpredictable, so they fail with [`Error::Codec`] while
+//! compression is set.
+//!
+//! The server stores the envelope like any other value. GET and SCAN
+//! decompress envelopes transparently, whatever the reading client's own
+//! configuration, as long as the codec's feature is enabled. Values that
+//! do not shrink are stored as they are; values that would be mistaken for
+//! an envelope, or for a sealed value (see [`crate::encryption`]), are
+//! wrapped in one with codec 0 (stored).
+//!
+//! Clients of this crate escape such values whether or not they compress or
+//! encrypt, and compare-and-set and compare-and-delete escape theirs the
+//! same way. A value written by other clients that happens to start with
+//! either magic is misread; the prefixes are chosen to make that unlikely
+//! for text and common binary formats.
+
+use crate::error::{Error, Result};
+
+const MAGIC: [u8; 3] = [0xC5, b'O', b'Z'];
+/// Magic of sealed values. Escaped in every build, with or without the
+/// `encryption` feature, so that builds with it read such values back.
+pub(crate) const SEALED_MAGIC: [u8; 3] = [0xC5, b'O', b'E'];
+const STORED: u8 = 0;
+const ZSTD: u8 = 1;
+const LZ4: u8 = 2;
+const ENVELOPE_HEADER_SIZE: usize = MAGIC.len() + 1 + 4;
+
+/// Largest value an envelope may expand to (256 MiB), so that a corrupt or
+/// hostile length cannot exhaust memory.
+pub const MAX_DECOMPRESSED_SIZE: usize = 256 * 1024 * 1024;
+
+/// Compression algorithm; each is behind the feature of the same name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Codec {
+    #[cfg(feature = "zstd")]
+    Zstd,
+    #[cfg(feature = "lz4")]
+    Lz4,
+}
+
+/// When and how a client compresses values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Compression {
+    codec: Codec,
+    threshold: usize,
+    #[cfg_attr(not(feature = "zstd"), allow(dead_code))]
+    level: i32,
+}
+
+impl Compression {
+    /// Compress values of 1 KiB and more with `codec`.
+    pub fn new(codec: Codec) -> Self {
+        Compression {
+            codec,
+            threshold: 1024,
+            level: 3,
+        }
+    }
+
+    /// Only compress values of at least `bytes`.
+    pub fn threshold(mut self, bytes: usize) -> Self {
+        self.threshold = bytes;
+        self
+    }
+
+    /// Compression level, for codecs that have one (zstd: 1-22).
+    pub fn level(mut self, level: i32) -> Self {
+        self.level = level;
+        self
+    }
+
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    /// The bytes to store for `value`.
+    pub fn encode(&self, value: &[u8]) -> Vec<u8> {
+        if value.len() >= self.threshold && value.len() <= MAX_DECOMPRESSED_SIZE {
+            if let Some(compressed) = self.compress(value) {
+                if compressed.len() + ENVELOPE_HEADER_SIZE < value.len() {
+                    return envelope(self.codec_id(), value.len(), &compressed);
+                }
+            }
+        }
+        escape(value)
+    }
+
+    fn codec_id(&self) -> u8 {
+        match self.codec {
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => ZSTD,
+            #[cfg(feature = "lz4")]
+            Codec::Lz4 => LZ4,
+        }
+    }
+
+    #[cfg_attr(not(any(feature = "zstd", feature = "lz4")), allow(unused_variables))]
+    fn compress(&self, value: &[u8]) -> Option<Vec<u8>> {
+        match self.codec {
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => zstd::bulk::compress(value, self.level).ok(),
+            #[cfg(feature = "lz4")]
+            Codec::Lz4 => Some(lz4_flex::block::compress(value)),
+        }
+    }
+}
+
+/// `value`, wrapped in a stored envelope if it would be mistaken for one or
+/// for a sealed value.
+pub(crate) fn escape(value: &[u8]) -> Vec<u8> {
+    if value.starts_with(&MAGIC) || value.starts_with(&SEALED_MAGIC) {
+        envelope(STORED, value.len(), value)
+    } else {
+        value.to_vec()
+    }
+}
+
+fn envelope(codec: u8, length: usize, payload: &[u8]) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(ENVELOPE_HEADER_SIZE + payload.len());
+    buffer.extend_from_slice(&MAGIC);
+    buffer.push(codec);
+    buffer.extend_from_slice(&(length as u32).to_le_bytes());
+    buffer.extend_from_slice(payload);
+    buffer
+}
+
+/// The original value stored as `stored`: decompressed if it is an
+/// envelope, unchanged otherwise.
+pub fn decode_value(stored: Vec<u8>) -> Result<Vec<u8>> {
+    if stored.len() < ENVELOPE_HEADER_SIZE || !stored.starts_with(&MAGIC) {
+        return Ok(stored);
+    }
+    let codec = stored[MAGIC.len()];
+    let length = u32::from_le_bytes(
+        stored[MAGIC.len() + 1..ENVELOPE_HEADER_SIZE]
+            .try_into()
+            .unwrap(),
+    );
+    let length = length as usize;
+    if length > MAX_DECOMPRESSED_SIZE {
+        return Err(Error::Codec(format!(
+            "compressed value too large: {length} bytes"
+        )));
+    }
+    let payload = &stored[ENVELOPE_HEADER_SIZE..];
+    let value = match codec {
+        STORED => Ok(payload.to_vec()),
+        #[cfg(feature = "zstd")]
+        ZSTD => zstd::bulk::decompress(payload, length).map_err(|e| e.to_string()),
+        #[cfg(feature = "lz4")]
+        LZ4 => lz4_flex::block::decompress(payload, length).map_err(|e| e.to_string()),
+        #[cfg(not(feature = "zstd"))]
+        ZSTD => Err("zstd support is not enabled".into()),
+        #[cfg(not(feature = "lz4"))]
+        LZ4 => Err("lz4 support is not enabled".into()),
+        _ => Err(format!("unknown compression codec {codec}")),
+    };
+    match value {
+        Ok(value) if value.len() == length => Ok(value),
+        Ok(_) => Err(Error::Codec("compressed value has the wrong length".into())),
+        Err(message) => Err(Error::Codec(message)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_plain_values_and_rejects_corrupt_envelopes() {
+        assert_eq!(decode_value(b"plain".to_vec()).unwrap(), b"plain");
+        let stored = envelope(STORED, 5, b"plain");
+        assert_eq!(decode_value(stored).unwrap(), b"plain");
+        let unknown = envelope(9, 4, b"????");
+        assert!(matches!(decode_value(unknown), Err(Error::Codec(_))));
+        let short = envelope(STORED, 6, b"plain");
+        assert!(matches!(decode_value(short), Err(Error::Codec(_))));
+    }
+
+    #[cfg(any(feature = "zstd", feature = "lz4"))]
+    fn codecs() -> Vec<Codec> {
+        [
+            #[cfg(feature = "zstd")]
+            Codec::Zstd,
+            #[cfg(feature = "lz4")]
+            Codec::Lz4,
+        ]
+        .to_vec()
+    }
+
+    #[cfg(any(feature = "zstd", feature = "lz4"))]
+    #[test]
+    fn compresses_large_values_and_escapes_lookalikes() {
+        let json = br#"{"user":"ada","roles":["admin","dev"]}"#.repeat(100);
+        let mut lookalike = MAGIC.to_vec();
+        lookalike.extend_from_slice(&[ZSTD, 1, 0, 0, 0, 0]);
+        for codec in codecs() {
+            let compression = Compression::new(codec).threshold(16);
+            let stored = compression.encode(&json);
+            assert!(stored.len() < json.len() / 5);
+            assert_eq!(decode_value(stored).unwrap(), json);
+
+            let stored = compression.encode(&lookalike);
+            assert_ne!(stored, lookalike);
+            assert_eq!(decode_value(stored).unwrap(), lookalike);
+            assert_eq!(compression.encode(b"short"), b"short");
+        }
+    }
+}
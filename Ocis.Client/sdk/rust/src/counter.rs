@@ -0,0 +1,111 @@
+//! INCRBY and DECRBY: atomic 64-bit counters.
+//!
+//! A counter is stored as an 8-byte little-endian `i64`; a missing key
+//! counts as zero. Requests carry the delta in the same encoding as their
+//! value, and a success response's value is the counter after the update.
+//! An update that would leave the `i64` range is answered with
+//! [`StatusCode::Overflow`](crate::spec::StatusCode::Overflow) and leaves
+//! the counter unchanged.
+//!
+//! The server reads and writes counters itself, so they bypass the
+//! clients' compression and keyrings. Read a counter with an update by 0
+//! rather than GET: GET decodes it like any other value, which fails with
+//! [`Error::Tampered`](crate::Error::Tampered) while a keyring is set, and
+//! with [`Error::Codec`](crate::Error::Codec) for the rare counter whose
+//! bytes start like a compression envelope. Likewise, a counter SET while
+//! a keyring is set is sealed, and later updates find no counter there.
+
+use crate::request::create_request;
+use crate::spec::{CommandType, RequestPacket};
+
+/// Encoded size of a counter value and of a delta.
+pub const COUNTER_SIZE: usize = 8;
+
+/// Why a stored value could not be updated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CounterError {
+    /// The stored value is not an encoded counter.
+    NotACounter,
+    /// The result would leave the `i64` range.
+    Overflow,
+}
+
+pub fn encode_counter(value: i64) -> [u8; COUNTER_SIZE] {
+    value.to_le_bytes()
+}
+
+/// Decode a counter; `None` if `bytes` is not [`COUNTER_SIZE`] long.
+pub fn decode_counter(bytes: &[u8]) -> Option<i64> {
+    bytes.try_into().ok().map(i64::from_le_bytes)
+}
+
+/// Build an INCRBY request adding `delta` to `key`.
+pub fn create_incr_by_request(key: &str, delta: i64) -> Vec<u8> {
+    let delta = encode_counter(delta).to_vec();
+    create_request(CommandType::IncrBy, key.as_bytes(), Some(delta))
+}
+
+/// Build a DECRBY request subtracting `delta` from `key`.
+pub fn create_decr_by_request(key: &str, delta: i64) -> Vec<u8> {
+    let delta = encode_counter(delta).to_vec();
+    create_request(CommandType::DecrBy, key.as_bytes(), Some(delta))
+}
+
+/// Server side: the counter an INCRBY or DECRBY `request` leaves behind,
+/// given the `current` stored value. `None` if the request is not a
+/// well-formed counter update.
+pub fn apply(request: &RequestPacket, current: Option<&[u8]>) -> Option<Result<i64, CounterError>> {
+    let update: fn(i64, i64) -> Option<i64> = match request.command()? {
+        CommandType::IncrBy => i64::checked_add,
+        CommandType::DecrBy => i64::checked_sub,
+        _ => return None,
+    };
+    let delta = decode_counter(request.value.as_deref()?)?;
+    let current = match current {
+        None => 0,
+        Some(bytes) => match decode_counter(bytes) {
+            Some(value) => value,
+            None => return Some(Err(CounterError::NotACounter)),
+        },
+    };
+    Some(update(current, delta).ok_or(CounterError::Overflow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::try_parse_request_packet;
+
+    fn request(frame: Vec<u8>) -> RequestPacket {
+        try_parse_request_packet(&frame).unwrap()
+    }
+
+    #[test]
+    fn applies_deltas_from_zero() {
+        let incr = request(create_incr_by_request("n", 5));
+        assert_eq!(apply(&incr, None), Some(Ok(5)));
+        assert_eq!(apply(&incr, Some(&encode_counter(-7))), Some(Ok(-2)));
+
+        let decr = request(create_decr_by_request("n", 0));
+        assert_eq!(apply(&decr, Some(&encode_counter(3))), Some(Ok(3)));
+    }
+
+    #[test]
+    fn reports_overflow_and_non_counters() {
+        let incr = request(create_incr_by_request("n", 1));
+        assert_eq!(
+            apply(&incr, Some(&encode_counter(i64::MAX))),
+            Some(Err(CounterError::Overflow))
+        );
+        let decr = request(create_decr_by_request("n", i64::MIN));
+        assert_eq!(apply(&decr, None), Some(Err(CounterError::Overflow)));
+        assert_eq!(
+            apply(&incr, Some(b"abc")),
+            Some(Err(CounterError::NotACounter))
+        );
+
+        let mut malformed = incr.clone();
+        malformed.value = Some(vec![1, 2, 3]);
+        assert_eq!(apply(&malformed, None), None);
+    }
+}
@@ -0,0 +1,92 @@
+//! Per-request durability.
+//!
+//! v3 frames carry a flags byte (see [`crate::protocol`]). In a write
+//! request, bits 0-1 ask for a [`Durability`] level for that write; zero
+//! leaves it to the server's default. In the response to a successful
+//! write they report the level the write actually reached, which may be
+//! higher or lower than requested. Connections on earlier versions cannot
+//! ask and are not told.
+
+use crate::protocol::frame_flags;
+
+const DURABILITY_MASK: u8 = 0b11;
+
+/// How far a write has to get before it is acknowledged, weakest first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Durability {
+    /// Acknowledged once applied in memory.
+    Fast = 1,
+    /// Acknowledged once applied; flushed to disk with other writes
+    /// shortly after.
+    Balanced = 2,
+    /// Acknowledged only once flushed to disk.
+    Strict = 3,
+}
+
+impl Durability {
+    /// The level encoded in `flags`, if any.
+    pub fn from_flags(flags: u8) -> Option<Self> {
+        match flags & DURABILITY_MASK {
+            1 => Some(Durability::Fast),
+            2 => Some(Durability::Balanced),
+            3 => Some(Durability::Strict),
+            _ => None,
+        }
+    }
+
+    pub fn flags(self) -> u8 {
+        self as u8
+    }
+}
+
+/// Options for a single SET or DELETE.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+    durability: Option<Durability>,
+}
+
+impl WriteOptions {
+    pub fn new() -> Self {
+        WriteOptions::default()
+    }
+
+    /// Ask for `durability` instead of the server's default.
+    pub fn durability(mut self, durability: Durability) -> Self {
+        self.durability = Some(durability);
+        self
+    }
+
+    pub fn requested_durability(&self) -> Option<Durability> {
+        self.durability
+    }
+
+    /// Flags byte for a request with these options.
+    pub fn flags(&self) -> u8 {
+        self.durability.map_or(0, Durability::flags)
+    }
+}
+
+/// Durability a response frame reports; `None` for frames older than v3
+/// and for responses that report none.
+pub fn honored_durability(frame: &[u8]) -> Option<Durability> {
+    frame_flags(frame).and_then(Durability::from_flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{encode_frame_with_flags, serialize_response, success_response};
+
+    #[test]
+    fn durability_round_trips_through_flags() {
+        let options = WriteOptions::new().durability(Durability::Strict);
+        let response = serialize_response(&success_response(None));
+        let v3 = encode_frame_with_flags(response.clone(), 3, 1, options.flags());
+        assert_eq!(honored_durability(&v3), Some(Durability::Strict));
+
+        let v2 = encode_frame_with_flags(response, 2, 1, options.flags());
+        assert_eq!(honored_durability(&v2), None);
+        assert_eq!(Durability::from_flags(WriteOptions::new().flags()), None);
+        assert!(Durability::Fast < Durability::Balanced);
+    }
+}
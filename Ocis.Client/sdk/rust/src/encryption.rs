@@ -0,0 +1,188 @@
+//! Client-side value encryption.
+//!
+//! With a [`Keyring`] configured, a client seals every value it stores with
+//! AES-256-GCM, after compressing it, so the server only ever sees
+//! ciphertext:
+//!
+//! ```text
+//! magic [0xC5, 'O', 'E'] | key id u32 | nonce (12 bytes) | ciphertext | tag (16 bytes)
+//! ```
+//!
+//! The value's key is the associated data, so a sealed value copied under
+//! another key fails to open. Nonces are random; rotate to a new key well
+//! before one key has sealed 2^32 values.
+//!
+//! To rotate, make the new key current and keep the old one with
+//! [`Keyring::with_key`]: reads open values sealed with either, writes use
+//! the new one. [`sealed_with`] tells which key a stored value needs.
+//!
+//! A value that fails to authenticate, or a value that is not sealed at all
+//! when the keyring does not [allow plaintext](Keyring::allow_plaintext),
+//! reads as [`Error::Tampered`]. Compare-and-set and compare-and-delete
+//! cannot work on sealed values and fail with [`Error::Codec`].
+
+use std::collections::HashMap;
+use std::fmt;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+
+use crate::compression::SEALED_MAGIC as MAGIC;
+use crate::error::{Error, Result};
+
+const NONCE_SIZE: usize = 12;
+const TAG_SIZE: usize = 16;
+const HEADER_SIZE: usize = MAGIC.len() + 4 + NONCE_SIZE;
+
+/// Keys that seal and open values. One key is current and seals new
+/// values; every key opens values sealed with it.
+#[derive(Clone)]
+pub struct Keyring {
+    current: u32,
+    ciphers: HashMap<u32, Aes256Gcm>,
+    allow_plaintext: bool,
+}
+
+impl Keyring {
+    /// Seal values with `key`, recorded as key `id`.
+    pub fn new(id: u32, key: [u8; 32]) -> Self {
+        Keyring {
+            current: id,
+            ciphers: HashMap::from([(id, Aes256Gcm::new(&key.into()))]),
+            allow_plaintext: false,
+        }
+    }
+
+    /// Also open values sealed with `key` as key `id`, e.g. a retired key.
+    /// Replaces any key already known by `id` other than the current one.
+    pub fn with_key(mut self, id: u32, key: [u8; 32]) -> Self {
+        if id != self.current {
+            self.ciphers.insert(id, Aes256Gcm::new(&key.into()));
+        }
+        self
+    }
+
+    /// Read values that are not sealed as they are, instead of failing with
+    /// [`Error::Tampered`]; for keyspaces written before encryption was
+    /// enabled. Anyone who can write to the server can then plant values.
+    pub fn allow_plaintext(mut self) -> Self {
+        self.allow_plaintext = true;
+        self
+    }
+
+    /// Id of the key new values are sealed with.
+    pub fn current_id(&self) -> u32 {
+        self.current
+    }
+
+    /// Seal `value` stored under `key` with the current key.
+    pub fn seal(&self, key: &[u8], value: &[u8]) -> Vec<u8> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self.ciphers[&self.current]
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: value,
+                    aad: key,
+                },
+            )
+            .expect("value too large to encrypt");
+        let mut buffer = Vec::with_capacity(HEADER_SIZE + ciphertext.len());
+        buffer.extend_from_slice(&MAGIC);
+        buffer.extend_from_slice(&self.current.to_le_bytes());
+        buffer.extend_from_slice(&nonce);
+        buffer.extend_from_slice(&ciphertext);
+        buffer
+    }
+
+    /// The value sealed as `stored` under `key`.
+    pub fn open(&self, key: &[u8], stored: Vec<u8>) -> Result<Vec<u8>> {
+        let Some(id) = sealed_with(&stored) else {
+            return if self.allow_plaintext {
+                Ok(stored)
+            } else {
+                Err(Error::Tampered)
+            };
+        };
+        if stored.len() < HEADER_SIZE + TAG_SIZE {
+            return Err(Error::Tampered);
+        }
+        let cipher = self
+            .ciphers
+            .get(&id)
+            .ok_or_else(|| Error::Codec(format!("no key with id {id}")))?;
+        let nonce = Nonce::from_slice(&stored[MAGIC.len() + 4..HEADER_SIZE]);
+        let payload = Payload {
+            msg: &stored[HEADER_SIZE..],
+            aad: key,
+        };
+        cipher.decrypt(nonce, payload).map_err(|_| Error::Tampered)
+    }
+}
+
+impl fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ids: Vec<_> = self.ciphers.keys().collect();
+        ids.sort();
+        f.debug_struct("Keyring")
+            .field("current", &self.current)
+            .field("ids", &ids)
+            .field("allow_plaintext", &self.allow_plaintext)
+            .finish()
+    }
+}
+
+/// Id of the key `stored` was sealed with; `None` if it is not sealed.
+pub fn sealed_with(stored: &[u8]) -> Option<u32> {
+    if stored.len() < MAGIC.len() + 4 || !stored.starts_with(&MAGIC) {
+        return None;
+    }
+    Some(u32::from_le_bytes(
+        stored[MAGIC.len()..MAGIC.len() + 4].try_into().unwrap(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seals_rotates_and_rejects_tampering() {
+        let old = Keyring::new(1, [1; 32]);
+        let sealed = old.seal(b"user:1", b"secret");
+        assert_eq!(sealed_with(&sealed), Some(1));
+        assert!(!sealed.windows(6).any(|w| w == b"secret"));
+        assert_ne!(old.seal(b"user:1", b"secret"), sealed);
+        assert_eq!(old.open(b"user:1", sealed.clone()).unwrap(), b"secret");
+
+        let rotated = Keyring::new(2, [2; 32]).with_key(1, [1; 32]);
+        assert_eq!(rotated.open(b"user:1", sealed.clone()).unwrap(), b"secret");
+        assert_eq!(sealed_with(&rotated.seal(b"user:1", b"secret")), Some(2));
+        assert!(matches!(
+            Keyring::new(2, [2; 32]).open(b"user:1", sealed.clone()),
+            Err(Error::Codec(_))
+        ));
+
+        assert!(matches!(
+            old.open(b"user:2", sealed.clone()),
+            Err(Error::Tampered)
+        ));
+        let mut flipped = sealed.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        assert!(matches!(old.open(b"user:1", flipped), Err(Error::Tampered)));
+        assert!(matches!(
+            old.open(b"user:1", sealed[..HEADER_SIZE].to_vec()),
+            Err(Error::Tampered)
+        ));
+
+        assert!(matches!(
+            old.open(b"user:1", b"plain".to_vec()),
+            Err(Error::Tampered)
+        ));
+        let lenient = old.allow_plaintext();
+        assert_eq!(
+            lenient.open(b"user:1", b"plain".to_vec()).unwrap(),
+            b"plain"
+        );
+    }
+}
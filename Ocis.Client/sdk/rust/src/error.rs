@@ -0,0 +1,103 @@
+//! Error type for the client transports.
+
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The connection failed, closed or timed out.
+    Io(io::Error),
+    /// The server sent bytes that are not a valid response.
+    Protocol(String),
+    /// A response failed its checksum, or came without one although the
+    /// request asked for it.
+    Integrity,
+    /// The server answered with an error response.
+    Server(String),
+    /// A counter update would have overflowed `i64`; the counter is
+    /// unchanged.
+    Overflow,
+    /// The server requires AUTH first, or rejected the token.
+    Unauthorized,
+    /// A value could not be encoded or decoded, e.g. a corrupt compressed
+    /// envelope.
+    Codec(String),
+    /// A stored value failed to authenticate under the client's keyring:
+    /// it was modified, moved to another key, or is not encrypted.
+    Tampered,
+    /// A stored value does not deserialize as the requested type.
+    Deserialize(String),
+    /// A request, or the value in it, is larger than the client's limits
+    /// allow; nothing was sent. See [`crate::limits`].
+    PayloadTooLarge { size: usize, limit: usize },
+    /// A key is longer than the client's limits allow; nothing was sent.
+    KeyTooLong { length: usize, limit: usize },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Whether the connection can no longer be used. Server errors leave
+    /// the connection in sync; anything else may not.
+    pub fn is_fatal(&self) -> bool {
+        !matches!(
+            self,
+            Error::Server(_)
+                | Error::Overflow
+                | Error::Unauthorized
+                | Error::Codec(_)
+                | Error::Tampered
+                | Error::Deserialize(_)
+                | Error::PayloadTooLarge { .. }
+                | Error::KeyTooLong { .. }
+        )
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "connection error: {e}"),
+            Error::Protocol(message) => write!(f, "protocol error: {message}"),
+            Error::Integrity => f.write_str("response failed its checksum"),
+            Error::Server(message) => f.write_str(message),
+            Error::Overflow => f.write_str("counter overflow"),
+            Error::Unauthorized => f.write_str("unauthorized"),
+            Error::Codec(message) => write!(f, "cannot decode value: {message}"),
+            Error::Tampered => f.write_str("value failed authentication"),
+            Error::Deserialize(message) => write!(f, "cannot deserialize value: {message}"),
+            Error::PayloadTooLarge { size, limit } => {
+                write!(f, "payload of {size} bytes exceeds the limit of {limit}")
+            }
+            Error::KeyTooLong { length, limit } => {
+                write!(f, "key of {length} bytes exceeds the limit of {limit}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// For readers: [`Error::Io`] unwraps, anything else is
+/// [`io::ErrorKind::InvalidData`] carrying the error.
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(e) => e,
+            other => io::Error::new(io::ErrorKind::InvalidData, other),
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Expiring keys: SET with an expiry, TTL and PERSIST.
+//!
+//! A SETEX request carries the expiry in front of the value:
+//!
+//! ```text
+//! kind u8 (0 = relative, 1 = absolute) | milliseconds u64 | value
+//! ```
+//!
+//! Relative expiries count from when the server handles the request;
+//! absolute ones are milliseconds since the Unix epoch. Once a key expires
+//! it behaves as if it had been deleted: reads answer NotFound.
+//!
+//! A TTL response is a success whose value, if any, is the remaining time
+//! to live as a little-endian `u64` of milliseconds; a success without a
+//! value means the key does not expire. PERSIST removes a key's expiry and
+//! answers NotFound if there is no such key. A plain SET also clears the
+//! expiry; conditional writes and counter updates keep it.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::request::create_request;
+use crate::spec::CommandType;
+
+const RELATIVE: u8 = 0;
+const ABSOLUTE: u8 = 1;
+
+/// Size of the expiry in front of a SETEX value.
+pub const EXPIRY_SIZE: usize = 9;
+
+/// When a key written with SETEX expires.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Expiry {
+    /// This long after the server handles the request.
+    After(Duration),
+    /// At this point in time, to millisecond precision.
+    At(SystemTime),
+}
+
+impl Expiry {
+    /// The point in time the key expires, for a request handled at `now`.
+    pub fn deadline(&self, now: SystemTime) -> SystemTime {
+        match *self {
+            Expiry::After(duration) => now
+                .checked_add(duration)
+                .unwrap_or_else(|| from_unix_millis(u64::MAX)),
+            Expiry::At(at) => at,
+        }
+    }
+}
+
+/// Remaining lifetime of an existing key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ttl {
+    /// The key does not expire.
+    Persistent,
+    /// The key expires after this long.
+    Remaining(Duration),
+}
+
+/// Milliseconds since the Unix epoch, saturating at both ends.
+pub fn unix_millis(time: SystemTime) -> u64 {
+    let millis = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    millis.try_into().unwrap_or(u64::MAX)
+}
+
+/// Inverse of [`unix_millis`], saturating at the latest time the platform
+/// can represent; deadlines come from untrusted clients.
+pub fn from_unix_millis(millis: u64) -> SystemTime {
+    let at = |millis| UNIX_EPOCH.checked_add(Duration::from_millis(millis));
+    if let Some(time) = at(millis) {
+        return time;
+    }
+    // Largest representable count of milliseconds, between `low` (which
+    // fits) and `high` (which does not).
+    let (mut low, mut high) = (0, millis);
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        match at(mid) {
+            Some(_) => low = mid,
+            None => high = mid,
+        }
+    }
+    UNIX_EPOCH + Duration::from_millis(low)
+}
+
+/// Build a SETEX request storing `value` under `key` until `expiry`.
+pub fn create_set_with_expiry_request(key: &str, value: &[u8], expiry: Expiry) -> Vec<u8> {
+    let (kind, millis) = match expiry {
+        Expiry::After(duration) => (
+            RELATIVE,
+            duration.as_millis().try_into().unwrap_or(u64::MAX),
+        ),
+        Expiry::At(at) => (ABSOLUTE, unix_millis(at)),
+    };
+    let mut body = Vec::with_capacity(EXPIRY_SIZE + value.len());
+    body.push(kind);
+    body.extend_from_slice(&millis.to_le_bytes());
+    body.extend_from_slice(value);
+    create_request(CommandType::SetWithExpiry, key.as_bytes(), Some(body))
+}
+
+pub fn create_ttl_request(key: &str) -> Vec<u8> {
+    create_request(CommandType::Ttl, key.as_bytes(), None)
+}
+
+pub fn create_persist_request(key: &str) -> Vec<u8> {
+    create_request(CommandType::Persist, key.as_bytes(), None)
+}
+
+/// Server side: split a SETEX value into the expiry and the value to
+/// store. `None` if it is malformed or the value is empty.
+pub fn split_set_with_expiry_value(value: &[u8]) -> Option<(Expiry, &[u8])> {
+    if value.len() <= EXPIRY_SIZE {
+        return None;
+    }
+    let (head, value) = value.split_at(EXPIRY_SIZE);
+    let millis = u64::from_le_bytes(head[1..].try_into().unwrap());
+    let expiry = match head[0] {
+        RELATIVE => Expiry::After(Duration::from_millis(millis)),
+        ABSOLUTE => Expiry::At(from_unix_millis(millis)),
+        _ => return None,
+    };
+    Some((expiry, value))
+}
+
+/// Server side: the TTL response value for a key expiring at `deadline`.
+pub fn encode_ttl(deadline: Option<SystemTime>, now: SystemTime) -> Option<Vec<u8>> {
+    let remaining = deadline?.duration_since(now).unwrap_or_default();
+    let millis: u64 = remaining.as_millis().try_into().unwrap_or(u64::MAX);
+    Some(millis.to_le_bytes().to_vec())
+}
+
+/// Decode a TTL response value; `None` if it is malformed.
+pub fn decode_ttl(value: Option<&[u8]>) -> Option<Ttl> {
+    match value {
+        None => Some(Ttl::Persistent),
+        Some(bytes) => {
+            let millis = u64::from_le_bytes(bytes.try_into().ok()?);
+            Some(Ttl::Remaining(Duration::from_millis(millis)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::try_parse_request_packet;
+
+    #[test]
+    fn set_with_expiry_round_trips() {
+        let at = from_unix_millis(1_700_000_000_123);
+        for expiry in [Expiry::After(Duration::from_secs(30)), Expiry::At(at)] {
+            let frame = create_set_with_expiry_request("k", b"v", expiry);
+            let request = try_parse_request_packet(&frame).unwrap();
+            assert_eq!(request.command(), Some(CommandType::SetWithExpiry));
+            let value = request.value.unwrap();
+            assert_eq!(
+                split_set_with_expiry_value(&value),
+                Some((expiry, &b"v"[..]))
+            );
+        }
+
+        let now = from_unix_millis(1_000);
+        assert_eq!(
+            Expiry::After(Duration::from_millis(500)).deadline(now),
+            from_unix_millis(1_500)
+        );
+        assert_eq!(split_set_with_expiry_value(&[0; EXPIRY_SIZE]), None);
+        assert_eq!(split_set_with_expiry_value(&[2; EXPIRY_SIZE + 1]), None);
+    }
+
+    #[test]
+    fn far_deadlines_saturate() {
+        let latest = from_unix_millis(u64::MAX);
+        assert!(latest >= from_unix_millis(u64::MAX / 2));
+        assert_eq!(
+            Expiry::After(Duration::MAX).deadline(SystemTime::now()),
+            latest
+        );
+        let (expiry, _) =
+            split_set_with_expiry_value(&[[ABSOLUTE].as_slice(), &[0xFF; 8], b"v"].concat())
+                .unwrap();
+        assert_eq!(expiry, Expiry::At(latest));
+    }
+
+    #[test]
+    fn ttl_round_trips() {
+        let now = from_unix_millis(10_000);
+        let encoded = encode_ttl(Some(from_unix_millis(12_500)), now);
+        assert_eq!(
+            decode_ttl(encoded.as_deref()),
+            Some(Ttl::Remaining(Duration::from_millis(2_500)))
+        );
+        assert_eq!(
+            decode_ttl(encode_ttl(None, now).as_deref()),
+            Some(Ttl::Persistent)
+        );
+        assert_eq!(decode_ttl(Some(b"abc")), None);
+    }
+}
@@ -0,0 +1,99 @@
+//! Bridge to the Fable-generated codec.
+//!
+//! Helpers for calling the generated functions and converting their packet
+//! types to [`crate::spec`]. No `fable_library_rust` type is part of the
+//! public API.
+
+use fable_library_rust::NativeArray_::{array_from, Array};
+use fable_library_rust::Native_::LrcPtr;
+use fable_library_rust::String_::string;
+
+pub(crate) use ocis_client_fable::Ocis::Client::SDK::{Protocol, Request, Response};
+use ocis_client_fable::Ocis::Server::ProtocolSpec;
+
+use crate::protocol::ParseResult;
+use crate::response::ClientResult;
+use crate::spec::{RequestPacket, ResponsePacket};
+
+pub(crate) fn array(bytes: &[u8]) -> Array<u8> {
+    array_from(bytes.to_vec())
+}
+
+pub(crate) fn text(value: &str) -> string {
+    string(value)
+}
+
+pub(crate) fn request_from(packet: &ProtocolSpec::RequestPacket) -> RequestPacket {
+    RequestPacket {
+        magic_number: packet.MagicNumber,
+        version: packet.Version,
+        command_type: packet.CommandType,
+        total_packet_length: packet.TotalPacketLength,
+        key_length: packet.KeyLength,
+        value_length: packet.ValueLength,
+        key: packet.Key.to_vec(),
+        value: packet.Value.as_ref().map(|v| v.to_vec()),
+    }
+}
+
+pub(crate) fn request_into(packet: &RequestPacket) -> LrcPtr<ProtocolSpec::RequestPacket> {
+    LrcPtr::new(ProtocolSpec::RequestPacket {
+        MagicNumber: packet.magic_number,
+        Version: packet.version,
+        CommandType: packet.command_type,
+        TotalPacketLength: packet.total_packet_length,
+        KeyLength: packet.key_length,
+        ValueLength: packet.value_length,
+        Key: array(&packet.key),
+        Value: packet.value.as_deref().map(array),
+    })
+}
+
+pub(crate) fn response_from(packet: &ProtocolSpec::ResponsePacket) -> ResponsePacket {
+    ResponsePacket {
+        magic_number: packet.MagicNumber,
+        version: packet.Version,
+        status_code: packet.StatusCode,
+        total_packet_length: packet.TotalPacketLength,
+        value_length: packet.ValueLength,
+        error_message_length: packet.ErrorMessageLength,
+        value: packet.Value.as_ref().map(|v| v.to_vec()),
+        error_message: packet.ErrorMessage.as_ref().map(|m| m.to_string()),
+    }
+}
+
+pub(crate) fn response_into(packet: &ResponsePacket) -> LrcPtr<ProtocolSpec::ResponsePacket> {
+    LrcPtr::new(ProtocolSpec::ResponsePacket {
+        MagicNumber: packet.magic_number,
+        Version: packet.version,
+        StatusCode: packet.status_code,
+        TotalPacketLength: packet.total_packet_length,
+        ValueLength: packet.value_length,
+        ErrorMessageLength: packet.error_message_length,
+        Value: packet.value.as_deref().map(array),
+        ErrorMessage: packet.error_message.as_deref().map(string),
+    })
+}
+
+pub(crate) fn parse_result_into(
+    result: &ParseResult<ResponsePacket>,
+) -> LrcPtr<Protocol::ParseResult_1<LrcPtr<ProtocolSpec::ResponsePacket>>> {
+    LrcPtr::new(match result {
+        ParseResult::ParseSuccess(packet) => {
+            Protocol::ParseResult_1::ParseSuccess(response_into(packet))
+        }
+        ParseResult::ParseError(message) => Protocol::ParseResult_1::ParseError(string(message)),
+        ParseResult::InsufficientData => Protocol::ParseResult_1::InsufficientData,
+    })
+}
+
+pub(crate) fn client_result_from<T: Clone + 'static, U>(
+    result: &Response::ClientResult_1<T>,
+    value: impl FnOnce(&T) -> U,
+) -> ClientResult<U> {
+    match result {
+        Response::ClientResult_1::Success(v) => ClientResult::Success(value(v)),
+        Response::ClientResult_1::NotFound => ClientResult::NotFound,
+        Response::ClientResult_1::Error(message) => ClientResult::Error(message.to_string()),
+    }
+}
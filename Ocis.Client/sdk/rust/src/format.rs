@@ -0,0 +1,145 @@
+//! Value formats for typed reads and writes.
+//!
+//! A [`Format`] turns any `serde` type into the bytes a client stores and
+//! back. The clients' `get_as` and `set_as` use the format set on the
+//! client; `get_as_with` and `set_as_with` take one per call. Each format
+//! is behind the feature of the same name:
+//!
+//! | Format | Feature | Crate |
+//! |--------|---------|-------|
+//! | [`Format::Json`] | `json` | `serde_json` |
+//! | [`Format::Bincode`] | `bincode` | `bincode` 1.x, default options |
+//! | [`Format::MessagePack`] | `msgpack` | `rmp-serde`, fields by name |
+//! | [`Format::Cbor`] | `cbor` | `ciborium` |
+//!
+//! Values are stored as the format produces them, with no tag, so readers
+//! must use the format the value was written with. A value that does not
+//! deserialize reads as [`Error::Deserialize`]; a value that cannot be
+//! serialized fails with [`Error::Codec`] before anything is sent.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+
+/// Serialization format for typed values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Format {
+    #[cfg(feature = "json")]
+    Json,
+    #[cfg(feature = "bincode")]
+    Bincode,
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+impl Format {
+    /// The bytes to store for `value`.
+    pub fn encode<T: Serialize + ?Sized>(self, value: &T) -> Result<Vec<u8>> {
+        self.serialize(value)
+            .map_err(|message| Error::Codec(format!("{self:?}: {message}")))
+    }
+
+    /// Deserialize a stored value.
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T> {
+        self.deserialize(bytes)
+            .map_err(|message| Error::Deserialize(format!("{self:?}: {message}")))
+    }
+
+    #[cfg_attr(
+        not(any(
+            feature = "json",
+            feature = "bincode",
+            feature = "msgpack",
+            feature = "cbor"
+        )),
+        allow(unused_variables)
+    )]
+    fn serialize<T: Serialize + ?Sized>(self, value: &T) -> std::result::Result<Vec<u8>, String> {
+        match self {
+            #[cfg(feature = "json")]
+            Format::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+            #[cfg(feature = "bincode")]
+            Format::Bincode => bincode::serialize(value).map_err(|e| e.to_string()),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => rmp_serde::to_vec_named(value).map_err(|e| e.to_string()),
+            #[cfg(feature = "cbor")]
+            Format::Cbor => {
+                let mut buffer = Vec::new();
+                ciborium::into_writer(value, &mut buffer)
+                    .map(|()| buffer)
+                    .map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    #[cfg_attr(
+        not(any(
+            feature = "json",
+            feature = "bincode",
+            feature = "msgpack",
+            feature = "cbor"
+        )),
+        allow(unused_variables)
+    )]
+    fn deserialize<T: DeserializeOwned>(self, bytes: &[u8]) -> std::result::Result<T, String> {
+        match self {
+            #[cfg(feature = "json")]
+            Format::Json => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+            #[cfg(feature = "bincode")]
+            Format::Bincode => bincode::deserialize(bytes).map_err(|e| e.to_string()),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => rmp_serde::from_slice(bytes).map_err(|e| e.to_string()),
+            #[cfg(feature = "cbor")]
+            Format::Cbor => ciborium::from_reader(bytes).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(any(
+        feature = "json",
+        feature = "bincode",
+        feature = "msgpack",
+        feature = "cbor"
+    ))]
+    #[test]
+    fn formats_round_trip_and_reject_garbage() {
+        use super::*;
+        use serde::Deserialize;
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct User {
+            name: String,
+            roles: Vec<String>,
+            age: Option<u32>,
+        }
+
+        let user = User {
+            name: "ada".into(),
+            roles: vec!["admin".into()],
+            age: Some(36),
+        };
+        let formats = [
+            #[cfg(feature = "json")]
+            Format::Json,
+            #[cfg(feature = "bincode")]
+            Format::Bincode,
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack,
+            #[cfg(feature = "cbor")]
+            Format::Cbor,
+        ];
+        for format in formats {
+            let bytes = format.encode(&user).unwrap();
+            assert_eq!(format.decode::<User>(&bytes).unwrap(), user, "{format:?}");
+            assert!(matches!(
+                format.decode::<User>(&bytes[..bytes.len() / 2]),
+                Err(Error::Deserialize(_))
+            ));
+        }
+    }
+}
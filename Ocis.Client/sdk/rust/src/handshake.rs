@@ -0,0 +1,83 @@
+//! HELLO version negotiation.
+//!
+//! A client opens a connection with a HELLO request whose value lists the
+//! protocol versions it speaks. HELLO is always framed as v1 so that any
+//! server can parse it. A server that understands HELLO answers with a
+//! one-byte value, the highest version both sides support, and both sides
+//! use that version for every later frame on the connection.
+//!
+//! Servers that predate HELLO answer with an error response (an unknown
+//! command or an invalid empty key); the client then stays on v1.
+
+use crate::error::{Error, Result};
+use crate::protocol::{deserialize_response, error_response, success_response, ParseResult};
+use crate::request::create_request;
+use crate::spec::{CommandType, ResponsePacket, StatusCode, PROTOCOL_VERSION};
+
+/// Build a HELLO request offering `versions`.
+pub fn create_hello_request(versions: &[u8]) -> Vec<u8> {
+    create_request(CommandType::Hello, &[], Some(versions.to_vec()))
+}
+
+/// Highest version present in both `offered` and `supported`.
+pub fn negotiate(offered: &[u8], supported: &[u8]) -> Option<u8> {
+    offered
+        .iter()
+        .copied()
+        .filter(|version| supported.contains(version))
+        .max()
+}
+
+/// Server side of the handshake: answer a HELLO offering `offered`.
+pub fn hello_response(offered: &[u8], supported: &[u8]) -> ResponsePacket {
+    match negotiate(offered, supported) {
+        Some(version) => success_response(Some(&[version])),
+        None => error_response("No common protocol version"),
+    }
+}
+
+/// Client side of the handshake: the version to use after receiving
+/// `frame` in reply to a HELLO offering `offered`. An error response means
+/// the server predates HELLO and yields v1.
+pub fn accepted_version(frame: &[u8], offered: &[u8]) -> Result<u8> {
+    let packet = match deserialize_response(frame) {
+        ParseResult::ParseSuccess(packet) => packet,
+        ParseResult::ParseError(message) => return Err(Error::Protocol(message)),
+        ParseResult::InsufficientData => return Err(Error::Protocol("truncated response".into())),
+    };
+    match (packet.status(), packet.value.as_deref()) {
+        (Some(StatusCode::Error), _) => Ok(PROTOCOL_VERSION),
+        (Some(StatusCode::Success), Some(&[version])) if offered.contains(&version) => Ok(version),
+        _ => Err(Error::Protocol("invalid HELLO response".into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{serialize_response, try_parse_request_packet};
+
+    #[test]
+    fn negotiate_picks_highest_common_version() {
+        assert_eq!(negotiate(&[1, 2], &[1, 2, 3]), Some(2));
+        assert_eq!(negotiate(&[3, 1], &[1]), Some(1));
+        assert_eq!(negotiate(&[2], &[1]), None);
+    }
+
+    #[test]
+    fn hello_round_trips() {
+        let request = try_parse_request_packet(&create_hello_request(&[1, 2])).unwrap();
+        assert_eq!(request.command(), Some(CommandType::Hello));
+        assert_eq!(request.version, PROTOCOL_VERSION);
+
+        let offered = request.value.unwrap();
+        let reply = serialize_response(&hello_response(&offered, &[1, 2]));
+        assert_eq!(accepted_version(&reply, &offered).unwrap(), 2);
+    }
+
+    #[test]
+    fn error_reply_falls_back_to_v1() {
+        let reply = serialize_response(&error_response("Unknown command type: 4"));
+        assert_eq!(accepted_version(&reply, &[1, 2]).unwrap(), 1);
+    }
+}
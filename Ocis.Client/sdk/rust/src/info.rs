@@ -0,0 +1,161 @@
+//! PING and INFO admin commands.
+//!
+//! Both carry an empty key and no value. PING answers a plain success. An
+//! INFO response is a success whose value describes the server,
+//! little-endian:
+//!
+//! ```text
+//! format u8 | durability u8 (0 = unknown) | memtable bytes u64 | WAL bytes u64
+//! | queue depth u64 | levels u8 | levels × SSTable count u32
+//! | max packet size u32 | max key length u32 | max value length u32
+//! ```
+//!
+//! The format byte is [`INFO_FORMAT`] today; format 1 ends before the
+//! limits (see [`crate::limits`]). Later formats only append fields, so a
+//! decoder reads the fields it knows and ignores the rest.
+
+use crate::durability::Durability;
+use crate::limits::Limits;
+use crate::request::create_request;
+use crate::spec::CommandType;
+
+/// Format of the INFO payload this crate writes.
+pub const INFO_FORMAT: u8 = 2;
+
+/// Server statistics reported by INFO.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ServerInfo {
+    /// Payload format the server wrote.
+    pub format: u8,
+    /// Durability writes get unless a request asks for more.
+    pub durability: Option<Durability>,
+    /// Bytes held in the in-memory table.
+    pub memtable_bytes: u64,
+    /// Bytes in the write-ahead log.
+    pub wal_bytes: u64,
+    /// Requests received but not yet answered.
+    pub queue_depth: u64,
+    /// SSTable count per level, level 0 first; empty for servers that do
+    /// not keep SSTables.
+    pub sstables_per_level: Vec<u32>,
+    /// Largest requests the server accepts; `None` for servers that do not
+    /// report them (format 1).
+    pub limits: Option<Limits>,
+}
+
+pub fn create_ping_request() -> Vec<u8> {
+    create_request(CommandType::Ping, &[], None)
+}
+
+pub fn create_info_request() -> Vec<u8> {
+    create_request(CommandType::Info, &[], None)
+}
+
+/// Server side: the INFO response value for `info`, in [`INFO_FORMAT`].
+pub fn encode_info(info: &ServerInfo) -> Vec<u8> {
+    let levels = &info.sstables_per_level[..info.sstables_per_level.len().min(u8::MAX as usize)];
+    let mut buffer = vec![INFO_FORMAT, info.durability.map_or(0, Durability::flags)];
+    buffer.extend_from_slice(&info.memtable_bytes.to_le_bytes());
+    buffer.extend_from_slice(&info.wal_bytes.to_le_bytes());
+    buffer.extend_from_slice(&info.queue_depth.to_le_bytes());
+    buffer.push(levels.len() as u8);
+    for count in levels {
+        buffer.extend_from_slice(&count.to_le_bytes());
+    }
+    let limits = info.limits.unwrap_or_default();
+    for limit in [
+        limits.max_packet_size,
+        limits.max_key_length,
+        limits.max_value_length,
+    ] {
+        buffer.extend_from_slice(&(limit.min(u32::MAX as usize) as u32).to_le_bytes());
+    }
+    buffer
+}
+
+/// Decode an INFO response value; `None` if it is malformed.
+pub fn decode_info(mut buffer: &[u8]) -> Option<ServerInfo> {
+    let format = take(&mut buffer, 1)?[0];
+    if format == 0 {
+        return None;
+    }
+    let durability = Durability::from_flags(take(&mut buffer, 1)?[0]);
+    let memtable_bytes = take_u64(&mut buffer)?;
+    let wal_bytes = take_u64(&mut buffer)?;
+    let queue_depth = take_u64(&mut buffer)?;
+    let levels = take(&mut buffer, 1)?[0];
+    let sstables_per_level = (0..levels)
+        .map(|_| take_u32(&mut buffer))
+        .collect::<Option<_>>()?;
+    let limits = if format >= 2 {
+        Some(Limits {
+            max_packet_size: take_u32(&mut buffer)? as usize,
+            max_key_length: take_u32(&mut buffer)? as usize,
+            max_value_length: take_u32(&mut buffer)? as usize,
+        })
+    } else {
+        None
+    };
+    Some(ServerInfo {
+        format,
+        durability,
+        memtable_bytes,
+        wal_bytes,
+        queue_depth,
+        sstables_per_level,
+        limits,
+    })
+}
+
+fn take<'a>(buffer: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if buffer.len() < n {
+        return None;
+    }
+    let (head, tail) = buffer.split_at(n);
+    *buffer = tail;
+    Some(head)
+}
+
+fn take_u32(buffer: &mut &[u8]) -> Option<u32> {
+    Some(u32::from_le_bytes(take(buffer, 4)?.try_into().unwrap()))
+}
+
+fn take_u64(buffer: &mut &[u8]) -> Option<u64> {
+    Some(u64::from_le_bytes(take(buffer, 8)?.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn info_round_trips_and_tolerates_later_formats() {
+        let info = ServerInfo {
+            format: INFO_FORMAT,
+            durability: Some(Durability::Balanced),
+            memtable_bytes: 4096,
+            wal_bytes: 1 << 20,
+            queue_depth: 3,
+            sstables_per_level: vec![4, 2, 1],
+            limits: Some(Limits::default()),
+        };
+        let encoded = encode_info(&info);
+        assert_eq!(decode_info(&encoded), Some(info.clone()));
+
+        let mut later = encoded.clone();
+        later[0] = INFO_FORMAT + 1;
+        later.extend_from_slice(b"new fields");
+        let decoded = decode_info(&later).unwrap();
+        assert_eq!(decoded.format, INFO_FORMAT + 1);
+        assert_eq!(decoded.sstables_per_level, info.sstables_per_level);
+
+        assert_eq!(decoded.limits, info.limits);
+
+        let mut v1 = encoded[..encoded.len() - 12].to_vec();
+        v1[0] = 1;
+        assert_eq!(decode_info(&v1).unwrap().limits, None);
+
+        assert_eq!(decode_info(&encoded[..encoded.len() - 1]), None);
+        assert_eq!(decode_info(&[0]), None);
+    }
+}
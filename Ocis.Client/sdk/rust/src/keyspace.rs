@@ -0,0 +1,356 @@
+//! Keyspaces: key namespaces on a shared server, and order-preserving
+//! typed keys.
+//!
+//! A key is a sequence of tagged components. Integers are a tag and 16
+//! lowercase hex digits; signed ones have the sign bit flipped so that
+//! negatives sort first. Strings are a tag, the text with each NUL written
+//! as NUL U+00FF, and a NUL terminator. Components of the same types
+//! therefore compare like the values they encode, so a scan visits
+//! `("user", 9)` before `("user", 10)`. Encoded keys are valid UTF-8.
+//!
+//! | Component | Tag |
+//! |-----------|-----|
+//! | signed integer | `i` |
+//! | keyspace name | `n` |
+//! | string | `s` |
+//! | unsigned integer | `u` |
+//!
+//! A [`Keyspace`] is a chain of name components. With one set on a client,
+//! every key the client sends is stored as the keyspace's prefix followed by
+//! the key as a string component, and keys it returns have that stripped.
+//! Because components are self-delimiting, no two keyspaces' keys can
+//! collide, and keyspace names never collide with keys. Scans and batches
+//! stay inside the keyspace; scans skip the keys of keyspaces nested in
+//! it.
+
+use crate::batch::{BatchOp, WriteBatch};
+use crate::scan::{Scan, ScanPage};
+
+const SIGNED: u8 = b'i';
+const NAMESPACE: u8 = b'n';
+const STRING: u8 = b's';
+const UNSIGNED: u8 = b'u';
+const TERMINATOR: u8 = 0;
+/// U+00FF, which follows a NUL that is part of a string.
+const ESCAPE: [u8; 2] = [0xC3, 0xBF];
+
+/// A value that can be a key component.
+pub trait EncodeKey {
+    /// Append the encoded component to `out`.
+    fn encode_key(&self, out: &mut Vec<u8>);
+}
+
+/// A key component that can be read back.
+pub trait DecodeKey: Sized {
+    /// Read one component from the front of `input`, advancing it.
+    fn decode_key(input: &mut &[u8]) -> Option<Self>;
+}
+
+/// `key` encoded, ready to pass to a client.
+pub fn encode_key<K: EncodeKey + ?Sized>(key: &K) -> String {
+    let mut out = Vec::new();
+    key.encode_key(&mut out);
+    String::from_utf8(out).expect("encoded keys are UTF-8")
+}
+
+/// Decode a key written by [`encode_key`]; `None` if it does not hold
+/// exactly a `K`.
+pub fn decode_key<K: DecodeKey>(key: &str) -> Option<K> {
+    let mut input = key.as_bytes();
+    let decoded = K::decode_key(&mut input)?;
+    input.is_empty().then_some(decoded)
+}
+
+fn encode_text(tag: u8, text: &[u8], out: &mut Vec<u8>) {
+    out.push(tag);
+    escape(text, out);
+    out.push(TERMINATOR);
+}
+
+fn escape(text: &[u8], out: &mut Vec<u8>) {
+    for &byte in text {
+        out.push(byte);
+        if byte == TERMINATOR {
+            out.extend_from_slice(&ESCAPE);
+        }
+    }
+}
+
+fn decode_text(tag: u8, input: &mut &[u8]) -> Option<Vec<u8>> {
+    let (&first, mut rest) = input.split_first()?;
+    if first != tag {
+        return None;
+    }
+    let mut text = Vec::new();
+    loop {
+        let (&byte, tail) = rest.split_first()?;
+        rest = tail;
+        if byte != TERMINATOR {
+            text.push(byte);
+        } else if rest.starts_with(&ESCAPE) {
+            text.push(byte);
+            rest = &rest[ESCAPE.len()..];
+        } else {
+            *input = rest;
+            return Some(text);
+        }
+    }
+}
+
+fn encode_u64(tag: u8, value: u64, out: &mut Vec<u8>) {
+    out.push(tag);
+    out.extend_from_slice(format!("{value:016x}").as_bytes());
+}
+
+fn decode_u64(tag: u8, input: &mut &[u8]) -> Option<u64> {
+    if input.len() < 17 || input[0] != tag {
+        return None;
+    }
+    let digits = std::str::from_utf8(&input[1..17]).ok()?;
+    if !digits
+        .bytes()
+        .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    {
+        return None;
+    }
+    let value = u64::from_str_radix(digits, 16).ok()?;
+    *input = &input[17..];
+    Some(value)
+}
+
+impl EncodeKey for str {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        encode_text(STRING, self.as_bytes(), out);
+    }
+}
+
+impl EncodeKey for String {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        self.as_str().encode_key(out);
+    }
+}
+
+impl DecodeKey for String {
+    fn decode_key(input: &mut &[u8]) -> Option<Self> {
+        String::from_utf8(decode_text(STRING, input)?).ok()
+    }
+}
+
+impl<T: EncodeKey + ?Sized> EncodeKey for &T {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        (**self).encode_key(out);
+    }
+}
+
+macro_rules! unsigned_keys {
+    ($($t:ty),*) => {$(
+        impl EncodeKey for $t {
+            fn encode_key(&self, out: &mut Vec<u8>) {
+                encode_u64(UNSIGNED, *self as u64, out);
+            }
+        }
+
+        impl DecodeKey for $t {
+            fn decode_key(input: &mut &[u8]) -> Option<Self> {
+                let mut rest = *input;
+                let value = decode_u64(UNSIGNED, &mut rest)?.try_into().ok()?;
+                *input = rest;
+                Some(value)
+            }
+        }
+    )*};
+}
+
+macro_rules! signed_keys {
+    ($($t:ty),*) => {$(
+        impl EncodeKey for $t {
+            fn encode_key(&self, out: &mut Vec<u8>) {
+                encode_u64(SIGNED, (*self as i64 as u64) ^ (1 << 63), out);
+            }
+        }
+
+        impl DecodeKey for $t {
+            fn decode_key(input: &mut &[u8]) -> Option<Self> {
+                let mut rest = *input;
+                let value = (decode_u64(SIGNED, &mut rest)? ^ (1 << 63)) as i64;
+                let value = value.try_into().ok()?;
+                *input = rest;
+                Some(value)
+            }
+        }
+    )*};
+}
+
+unsigned_keys!(u8, u16, u32, u64, usize);
+signed_keys!(i8, i16, i32, i64, isize);
+
+macro_rules! tuple_keys {
+    ($($name:ident),+) => {
+        impl<$($name: EncodeKey),+> EncodeKey for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn encode_key(&self, out: &mut Vec<u8>) {
+                let ($($name,)+) = self;
+                $($name.encode_key(out);)+
+            }
+        }
+
+        impl<$($name: DecodeKey),+> DecodeKey for ($($name,)+) {
+            fn decode_key(input: &mut &[u8]) -> Option<Self> {
+                let mut rest = *input;
+                let decoded = ($($name::decode_key(&mut rest)?,)+);
+                *input = rest;
+                Some(decoded)
+            }
+        }
+    };
+}
+
+tuple_keys!(A);
+tuple_keys!(A, B);
+tuple_keys!(A, B, C);
+tuple_keys!(A, B, C, D);
+
+/// A key namespace. Set one on a client to confine it to the namespace.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Keyspace {
+    /// Name components, then the string tag every key starts with.
+    prefix: Vec<u8>,
+}
+
+impl Keyspace {
+    /// The top-level keyspace `name`.
+    pub fn new(name: &str) -> Self {
+        let mut prefix = Vec::new();
+        encode_text(NAMESPACE, name.as_bytes(), &mut prefix);
+        prefix.push(STRING);
+        Keyspace { prefix }
+    }
+
+    /// The keyspace `name` inside this one.
+    pub fn nested(&self, name: &str) -> Self {
+        let mut prefix = self.prefix[..self.prefix.len() - 1].to_vec();
+        encode_text(NAMESPACE, name.as_bytes(), &mut prefix);
+        prefix.push(STRING);
+        Keyspace { prefix }
+    }
+
+    /// Common prefix of every server key in this keyspace.
+    pub fn prefix(&self) -> &str {
+        std::str::from_utf8(&self.prefix).expect("keyspace prefixes are UTF-8")
+    }
+
+    /// The server key for `key`.
+    pub fn key(&self, key: &str) -> String {
+        String::from_utf8(self.server_key(key.as_bytes())).expect("server keys are UTF-8")
+    }
+
+    /// The server key for `key`, as bytes.
+    pub fn server_key(&self, key: &[u8]) -> Vec<u8> {
+        let mut out = self.open_key(key);
+        out.push(TERMINATOR);
+        out
+    }
+
+    /// The key stored as `server_key`; `None` if it is outside this
+    /// keyspace, or in a keyspace nested in it.
+    pub fn user_key(&self, server_key: &[u8]) -> Option<Vec<u8>> {
+        let mut rest = server_key.strip_prefix(&self.prefix[..self.prefix.len() - 1])?;
+        let key = decode_text(STRING, &mut rest)?;
+        rest.is_empty().then_some(key)
+    }
+
+    /// `scan`, over this keyspace's keys instead of the whole server.
+    pub fn scan(&self, scan: &Scan) -> Scan {
+        scan.within(&self.prefix, |key| self.open_key(key))
+    }
+
+    /// `page`, as returned for [`Keyspace::scan`], with keys and cursor
+    /// mapped back by [`Keyspace::user_key`].
+    pub fn page(&self, mut page: ScanPage) -> ScanPage {
+        page.entries
+            .retain_mut(|entry| match self.user_key(&entry.key) {
+                Some(key) => {
+                    entry.key = key;
+                    true
+                }
+                None => false,
+            });
+        page.cursor = page.cursor.and_then(|cursor| self.user_key(&cursor));
+        page
+    }
+
+    /// `batch`, with every key in this keyspace.
+    pub fn batch(&self, batch: &WriteBatch) -> WriteBatch {
+        batch
+            .ops()
+            .iter()
+            .fold(WriteBatch::new(), |mapped, op| match op {
+                BatchOp::Set { key, value } => mapped.set(self.server_key(key), value.clone()),
+                BatchOp::Delete { key } => mapped.delete(self.server_key(key)),
+            })
+    }
+
+    /// The server key for `key` without its terminator: a lower bound for
+    /// every key starting with `key`.
+    fn open_key(&self, key: &[u8]) -> Vec<u8> {
+        let mut out = self.prefix.clone();
+        escape(key, &mut out);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_keys_round_trip_and_keep_their_order() {
+        let keys = [
+            ("user", -5i64, 0u32),
+            ("user", -1, 7),
+            ("user", 0, 0),
+            ("user", 9, 1),
+            ("user", 10, 0),
+            ("user\0", 0, 0),
+            ("user\0x", 0, 0),
+            ("users", i64::MIN, u32::MAX),
+        ];
+        let encoded: Vec<String> = keys.iter().map(encode_key).collect();
+        assert!(encoded.windows(2).all(|pair| pair[0] < pair[1]));
+        for (key, encoded) in keys.iter().zip(&encoded) {
+            let decoded: (String, i64, u32) = decode_key(encoded).unwrap();
+            assert_eq!((decoded.0.as_str(), decoded.1, decoded.2), *key);
+        }
+
+        assert_eq!(decode_key::<u8>(&encode_key(&300u16)), None);
+        assert_eq!(decode_key::<String>(&encode_key(&("a", "b"))), None);
+        assert_eq!(
+            decode_key::<(String,)>(&encode_key("a\0b")).unwrap().0,
+            "a\0b"
+        );
+    }
+
+    #[test]
+    fn keyspaces_are_disjoint_and_nest() {
+        let app = Keyspace::new("app");
+        let other = Keyspace::new("app\0");
+        let jobs = app.nested("jobs");
+        assert_eq!(app.user_key(&app.server_key(b"k")), Some(b"k".to_vec()));
+        for (a, b) in [(&app, &other), (&app, &jobs), (&other, &jobs)] {
+            assert_eq!(a.user_key(&b.server_key(b"k")), None);
+            assert_eq!(b.user_key(&a.server_key(b"k")), None);
+        }
+        assert!(jobs
+            .prefix()
+            .starts_with(&app.prefix()[..app.prefix().len() - 1]));
+        assert_ne!(app.key("jobs"), jobs.prefix());
+
+        let scan = app.scan(&Scan::prefix("a"));
+        assert!(scan.includes(&app.server_key(b"ab")));
+        assert!(!scan.includes(&app.server_key(b"b")));
+        assert!(!scan.includes(&other.server_key(b"ab")));
+        let scan = app.scan(&Scan::all());
+        assert!(scan.includes(&app.server_key(b"")));
+        assert!(!scan.includes(&jobs.server_key(b"a")));
+    }
+}
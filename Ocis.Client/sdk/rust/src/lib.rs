@@ -0,0 +1,66 @@
+//! Rust client SDK for the Ocis key-value server.
+//!
+//! The wire codec is generated by Fable from `Ocis.Client.SDK` and lives in
+//! the private `ocis-client-fable` crate; this crate wraps it in plain Rust
+//! types under stable paths:
+//!
+//! - [`spec`]: constants and packet types from `Ocis.Server.ProtocolSpec`
+//! - [`protocol`]: frame encoding and decoding
+//! - [`request`]: request frame builders
+//! - [`response`]: response parsing into [`response::ClientResult`]
+//! - [`handshake`]: HELLO version negotiation
+//! - [`scan`]: SCAN options and result pages
+//! - [`counter`]: INCRBY/DECRBY counters
+//! - [`expiry`]: expiring keys, TTL and PERSIST
+//! - [`batch`]: atomic multi-key write batches
+//! - [`auth`]: shared-token authentication
+//! - [`compression`]: client-side value compression
+//! - [`encryption`]: client-side value encryption
+//! - [`format`]: serde formats for typed values
+//! - [`keyspace`]: key namespaces and order-preserving typed keys
+//! - [`chunking`]: large objects stored as chunks and a manifest
+//! - [`limits`]: request size limits checked before sending
+//! - [`durability`]: per-request durability levels
+//! - [`info`]: PING and INFO admin commands
+//!
+//! Transports are optional: [`blocking`] (default feature `blocking`) and
+//! [`aio`] (feature `tokio`). Feature `mock` enables [`mock::MockServer`];
+//! features `zstd` and `lz4` enable the [`compression`] codecs, and feature
+//! `encryption` enables [`encryption`]. Features `json`, `bincode`, `msgpack`
+//! and `cbor` enable the [`format`]s behind the clients' `get_as` and
+//! `set_as`.
+
+mod error;
+mod fable;
+#[cfg(any(feature = "blocking", feature = "tokio"))]
+mod transport;
+
+pub mod auth;
+pub mod batch;
+pub mod chunking;
+pub mod compression;
+pub mod counter;
+pub mod durability;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+pub mod expiry;
+#[cfg(feature = "serde")]
+pub mod format;
+pub mod handshake;
+pub mod info;
+pub mod keyspace;
+pub mod limits;
+pub mod protocol;
+pub mod request;
+pub mod response;
+pub mod scan;
+pub mod spec;
+
+#[cfg(feature = "tokio")]
+pub mod aio;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+
+pub use error::{Error, Result};
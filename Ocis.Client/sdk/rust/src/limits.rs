@@ -0,0 +1,161 @@
+//! Request size limits.
+//!
+//! A server rejects frames over [`MAX_PACKET_SIZE`] (the codec's
+//! `IsValidPacketSize`), keys over [`MAX_KEY_LENGTH`] and values over
+//! [`MAX_VALUE_LENGTH`], but only once it has read the whole frame. The
+//! clients check every request against their [`Limits`] before writing any
+//! of it, and fail with [`Error::PayloadTooLarge`] or
+//! [`Error::KeyTooLong`] instead; the connection stays usable. Checks apply
+//! to the bytes sent, after compression, encryption and keyspace prefixes.
+//!
+//! Clients start with [`Limits::default`], the reference server's limits.
+//! Servers that report their own in INFO (format 2 and later, see
+//! [`crate::info`]) replace them when a client calls `fetch_limits`.
+
+use crate::batch::{BatchOp, WriteBatch};
+use crate::error::{Error, Result};
+use crate::expiry::EXPIRY_SIZE;
+use crate::protocol::try_parse_request_header;
+use crate::spec::CommandType;
+
+pub use crate::spec::MAX_PACKET_SIZE;
+
+/// Longest key the reference server accepts (1 MiB).
+pub const MAX_KEY_LENGTH: usize = 1024 * 1024;
+
+/// Longest value the reference server accepts for SET and conditional
+/// writes (8 MiB); SET with an expiry adds the expiry to it.
+pub const MAX_VALUE_LENGTH: usize = 8 * 1024 * 1024;
+
+/// Largest request a client sends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Limits {
+    /// Largest frame, header and trailer included.
+    pub max_packet_size: usize,
+    /// Longest key, in bytes.
+    pub max_key_length: usize,
+    /// Largest value of a SET, conditional write or batch SET.
+    pub max_value_length: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_packet_size: MAX_PACKET_SIZE,
+            max_key_length: MAX_KEY_LENGTH,
+            max_value_length: MAX_VALUE_LENGTH,
+        }
+    }
+}
+
+impl Limits {
+    /// Check an encoded request `frame`, of any version, before it is sent.
+    pub fn check(&self, frame: &[u8]) -> Result<()> {
+        if frame.len() > self.max_packet_size {
+            return Err(Error::PayloadTooLarge {
+                size: frame.len(),
+                limit: self.max_packet_size,
+            });
+        }
+        let Some(header) = try_parse_request_header(frame) else {
+            return Ok(());
+        };
+        self.check_key(header.key_length.max(0) as usize)?;
+        let value_length = header.value_length.max(0) as usize;
+        match header.command() {
+            Some(
+                CommandType::Set
+                | CommandType::SetIfAbsent
+                | CommandType::CompareAndSet
+                | CommandType::CompareAndDelete,
+            ) => self.check_value(value_length),
+            Some(CommandType::SetWithExpiry) => {
+                self.check_value(value_length.saturating_sub(EXPIRY_SIZE))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check every operation of `batch`, as it will be sent.
+    pub fn check_batch(&self, batch: &WriteBatch) -> Result<()> {
+        for op in batch.ops() {
+            self.check_key(op.key().len())?;
+            if let BatchOp::Set { value, .. } = op {
+                self.check_value(value.len())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_key(&self, length: usize) -> Result<()> {
+        if length > self.max_key_length {
+            return Err(Error::KeyTooLong {
+                length,
+                limit: self.max_key_length,
+            });
+        }
+        Ok(())
+    }
+
+    fn check_value(&self, size: usize) -> Result<()> {
+        if size > self.max_value_length {
+            return Err(Error::PayloadTooLarge {
+                size,
+                limit: self.max_value_length,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expiry::{create_set_with_expiry_request, Expiry};
+    use crate::protocol::{add_checksum, encode_frame_with_flags};
+    use crate::request::{create_get_request, create_set_request};
+    use std::time::Duration;
+
+    #[test]
+    fn checks_frames_keys_and_values() {
+        let limits = Limits {
+            max_packet_size: 128,
+            max_key_length: 8,
+            max_value_length: 16,
+        };
+        assert!(limits.check(&create_set_request("key", &[1; 16])).is_ok());
+        assert!(matches!(
+            limits.check(&create_set_request("key", &[1; 17])),
+            Err(Error::PayloadTooLarge {
+                size: 17,
+                limit: 16
+            })
+        ));
+        assert!(limits.check(&create_get_request("long key")).is_ok());
+        assert!(matches!(
+            limits.check(&create_get_request("longer key")),
+            Err(Error::KeyTooLong {
+                length: 10,
+                limit: 8
+            })
+        ));
+        let expiring = create_set_with_expiry_request("k", &[1; 16], Expiry::After(Duration::ZERO));
+        assert!(limits.check(&expiring).is_ok());
+
+        let frame = encode_frame_with_flags(create_set_request("k", &[1; 16]), 3, 1, 0);
+        assert!(limits.check(&add_checksum(frame)).is_ok());
+        let frame = create_set_request("k", &[1; 200]);
+        assert!(matches!(
+            limits.check(&frame),
+            Err(Error::PayloadTooLarge { limit: 128, .. })
+        ));
+
+        let batch = WriteBatch::new().set("k", vec![1; 16]).delete("k");
+        assert!(limits.check_batch(&batch).is_ok());
+        assert!(matches!(
+            limits.check_batch(&batch.delete("longer key")),
+            Err(Error::KeyTooLong { .. })
+        ));
+        assert_eq!(Limits::default().max_packet_size, MAX_PACKET_SIZE);
+    }
+}
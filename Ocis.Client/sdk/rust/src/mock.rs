@@ -0,0 +1,567 @@
+//! In-process fake Ocis server for tests.
+//!
+//! `MockServer` binds an ephemeral local port, decodes frames with the same
+//! codec the SDK uses and serves them from an in-memory map, so integration
+//! tests never need a running .NET server. Faults can be scripted per request
+//! to exercise client error paths.
+//!
+//! By default the server answers HELLO and speaks every version in
+//! [`SUPPORTED_VERSIONS`]; [`MockServer::start_with_versions`] restricts
+//! that, and a v1-only server behaves like one that predates HELLO.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use crate::auth::{auth_response, requires_auth};
+use crate::batch::{parse_batch_request, BatchOp};
+use crate::counter::{self, encode_counter, CounterError};
+use crate::durability::Durability;
+use crate::expiry::{encode_ttl, split_set_with_expiry_value};
+use crate::handshake::hello_response;
+use crate::info::{encode_info, ServerInfo, INFO_FORMAT};
+use crate::limits::Limits;
+use crate::protocol::{
+    add_checksum, condition_failed_response, encode_frame, encode_frame_with_flags, error_response,
+    frame_flags, frame_length, frame_version, has_checksum, not_found_response, overflow_response,
+    request_id, serialize_response, success_response, try_parse_request_header,
+    try_parse_request_packet, unauthorized_response,
+};
+use crate::request::split_compare_and_set_value;
+use crate::scan::{collect_page, encode_scan_page, parse_scan_request};
+use crate::spec::{
+    CommandType, RequestPacket, ResponsePacket, StatusCode, HEADER_SIZE, PROTOCOL_VERSION,
+    SUPPORTED_VERSIONS,
+};
+
+/// A scripted failure applied to the next request the server receives.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// Sleep before handling the request normally.
+    Delay(Duration),
+    /// Reply with an error response carrying this message.
+    Error(String),
+    /// Close the connection without replying.
+    Drop,
+    /// Write only the first `n` bytes of the real response, then close.
+    Truncate(usize),
+    /// Flip a bit in the byte at offset `n` of the real response, as a
+    /// faulty link would.
+    Corrupt(usize),
+}
+
+#[derive(Default)]
+struct State {
+    versions: Vec<u8>,
+    /// Token connections must AUTH with first; `None` needs no AUTH.
+    token: Option<Vec<u8>>,
+    /// Limits INFO reports; requests are not checked against them.
+    limits: Limits,
+    store: Mutex<BTreeMap<Vec<u8>, Vec<u8>>>,
+    /// Deadlines of expiring keys; locked after `store`.
+    expiries: Mutex<HashMap<Vec<u8>, SystemTime>>,
+    faults: Mutex<VecDeque<Fault>>,
+    requests: Mutex<usize>,
+}
+
+/// In-memory server speaking the wire protocol on `127.0.0.1`.
+///
+/// The server stops accepting connections when dropped.
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<State>,
+    stopping: Arc<AtomicBool>,
+    acceptor: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Bind an ephemeral port and start serving in the background.
+    pub fn start() -> io::Result<Self> {
+        Self::start_with_versions(SUPPORTED_VERSIONS)
+    }
+
+    /// Like [`MockServer::start`], but only speak `versions`. Frames tagged
+    /// with any other version close the connection.
+    pub fn start_with_versions(versions: &[u8]) -> io::Result<Self> {
+        Self::spawn(State {
+            versions: versions.to_vec(),
+            ..State::default()
+        })
+    }
+
+    fn spawn(state: State) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(state);
+        let stopping = Arc::new(AtomicBool::new(false));
+
+        let acceptor = {
+            let state = Arc::clone(&state);
+            let stopping = Arc::clone(&stopping);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stopping.load(Ordering::Acquire) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        let state = Arc::clone(&state);
+                        thread::spawn(move || serve_connection(stream, &state));
+                    }
+                }
+            })
+        };
+
+        Ok(MockServer {
+            addr,
+            state,
+            stopping,
+            acceptor: Some(acceptor),
+        })
+    }
+
+    /// Like [`MockServer::start`], but refuse commands other than HELLO,
+    /// PING and AUTH until a connection authenticates with `token`.
+    pub fn start_with_token(token: &[u8]) -> io::Result<Self> {
+        Self::spawn(State {
+            versions: SUPPORTED_VERSIONS.to_vec(),
+            token: Some(token.to_vec()),
+            ..State::default()
+        })
+    }
+
+    /// Like [`MockServer::start`], but report `limits` in INFO.
+    pub fn start_with_limits(limits: Limits) -> io::Result<Self> {
+        Self::spawn(State {
+            versions: SUPPORTED_VERSIONS.to_vec(),
+            limits,
+            ..State::default()
+        })
+    }
+
+    /// Address the server is listening on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Seed a key directly, bypassing the protocol.
+    pub fn insert(&self, key: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) {
+        self.state
+            .store
+            .lock()
+            .unwrap()
+            .insert(key.into(), value.into());
+    }
+
+    /// Read a key directly, bypassing the protocol.
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.state.store.lock().unwrap().get(key).cloned()
+    }
+
+    /// Queue a fault for the next request that has not been scripted yet.
+    /// HELLO requests never consume faults.
+    pub fn push_fault(&self, fault: Fault) {
+        self.state.faults.lock().unwrap().push_back(fault);
+    }
+
+    /// Number of well-formed requests received so far, not counting HELLO,
+    /// AUTH or requests refused for want of it.
+    pub fn request_count(&self) -> usize {
+        *self.state.requests.lock().unwrap()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::Release);
+        // Wake the acceptor so it observes the stop flag.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(acceptor) = self.acceptor.take() {
+            let _ = acceptor.join();
+        }
+    }
+}
+
+/// Per-connection request loop, mirroring the server: read a fixed-size
+/// header, then the rest of the frame. v1 requests are answered strictly in
+/// order; requests with an id are served on their own threads, so a
+/// delayed one does not hold up the rest.
+fn serve_connection(mut stream: TcpStream, state: &Arc<State>) {
+    let Ok(writer) = stream.try_clone() else {
+        return;
+    };
+    let writer = Arc::new(Mutex::new(writer));
+    let mut authenticated = state.token.is_none();
+
+    while let Some(frame) = read_frame(&mut stream, state) {
+        // The handshake is neither counted nor faulted, so scripts line up
+        // with the caller's own requests.
+        if frame[5] == CommandType::Hello as u8 {
+            if !serve_request(frame, None, state, &writer) {
+                break;
+            }
+            continue;
+        }
+        // Authentication is connection state, so it is settled here, in
+        // order, and like the handshake neither counted nor faulted.
+        let command = CommandType::from_i32(frame[5].into());
+        if command == Some(CommandType::Auth) || (!authenticated && requires_auth(command)) {
+            let response = match try_parse_request_packet(&frame) {
+                Some(request) if command == Some(CommandType::Auth) => {
+                    let offered = request.value.unwrap_or_default();
+                    let response = auth_response(state.token.as_deref(), &offered);
+                    authenticated = response.status() == Some(StatusCode::Success);
+                    response
+                }
+                _ => unauthorized_response(),
+            };
+            let reply = reply_to(&frame, &response);
+            if writer.lock().unwrap().write_all(&reply).is_err() {
+                break;
+            }
+            continue;
+        }
+        *state.requests.lock().unwrap() += 1;
+        let fault = state.faults.lock().unwrap().pop_front();
+
+        if request_id(&frame).is_some() {
+            let state = Arc::clone(state);
+            let writer = Arc::clone(&writer);
+            thread::spawn(move || serve_request(frame, fault, &state, &writer));
+        } else if !serve_request(frame, fault, state, &writer) {
+            break;
+        }
+    }
+    let _ = stream.shutdown(Shutdown::Both);
+}
+
+/// Apply `fault` to one request and answer it. Returns `false` once the
+/// connection should close.
+fn serve_request(
+    frame: Vec<u8>,
+    fault: Option<Fault>,
+    state: &State,
+    writer: &Mutex<TcpStream>,
+) -> bool {
+    let response = match fault {
+        Some(Fault::Drop) => {
+            let _ = writer.lock().unwrap().shutdown(Shutdown::Both);
+            return false;
+        }
+        Some(Fault::Error(message)) => reply_to(&frame, &error_response(&message)),
+        Some(Fault::Delay(delay)) => {
+            thread::sleep(delay);
+            handle_frame(frame, state)
+        }
+        Some(Fault::Truncate(n)) => {
+            let response = handle_frame(frame, state);
+            let mut writer = writer.lock().unwrap();
+            let _ = writer.write_all(&response[..n.min(response.len())]);
+            let _ = writer.shutdown(Shutdown::Both);
+            return false;
+        }
+        Some(Fault::Corrupt(n)) => {
+            let mut response = handle_frame(frame, state);
+            if let Some(byte) = response.get_mut(n) {
+                *byte ^= 1;
+            }
+            response
+        }
+        None => handle_frame(frame, state),
+    };
+    writer.lock().unwrap().write_all(&response).is_ok()
+}
+
+/// Read one complete frame, or `None` on disconnect, a malformed header or
+/// a version the server does not speak.
+fn read_frame(stream: &mut TcpStream, state: &State) -> Option<Vec<u8>> {
+    let mut header = [0u8; HEADER_SIZE];
+    stream.read_exact(&mut header).ok()?;
+
+    let packet = try_parse_request_header(&header)?;
+    if !state.versions.contains(&packet.version) {
+        return None;
+    }
+    let mut frame = vec![0u8; frame_length(&header)?];
+    frame[..HEADER_SIZE].copy_from_slice(&header);
+    stream.read_exact(&mut frame[HEADER_SIZE..]).ok()?;
+    Some(frame)
+}
+
+fn handle_frame(frame: Vec<u8>, state: &State) -> Vec<u8> {
+    let (response, flags) = match try_parse_request_packet(&frame) {
+        None => (error_response("Malformed request"), 0),
+        Some(request) => {
+            let write = request.command().is_some_and(CommandType::is_write);
+            let response = handle_request(request, state);
+            // Writes are in memory, so every requested level is honored
+            // as asked; the default is Fast.
+            let honored = if write && response.status() == Some(StatusCode::Success) {
+                let requested = frame_flags(&frame).and_then(Durability::from_flags);
+                requested.unwrap_or(Durability::Fast).flags()
+            } else {
+                0
+            };
+            (response, honored)
+        }
+    };
+    let version = frame_version(&frame).unwrap_or(PROTOCOL_VERSION);
+    let id = request_id(&frame).unwrap_or_default();
+    let reply = encode_frame_with_flags(serialize_response(&response), version, id, flags);
+    checksummed_like(&frame, reply)
+}
+
+/// Serialize `response` in the version of the request `frame`, echoing its
+/// request id.
+fn reply_to(frame: &[u8], response: &ResponsePacket) -> Vec<u8> {
+    let version = frame_version(frame).unwrap_or(PROTOCOL_VERSION);
+    let id = request_id(frame).unwrap_or_default();
+    checksummed_like(
+        frame,
+        encode_frame(serialize_response(response), version, id),
+    )
+}
+
+/// `reply` with a checksum if the request `frame` has one.
+fn checksummed_like(frame: &[u8], reply: Vec<u8>) -> Vec<u8> {
+    if has_checksum(frame) {
+        add_checksum(reply)
+    } else {
+        reply
+    }
+}
+
+fn handle_request(request: RequestPacket, state: &State) -> ResponsePacket {
+    let knows_hello = state.versions != [PROTOCOL_VERSION];
+    let mut store = state.store.lock().unwrap();
+    let mut expiries = state.expiries.lock().unwrap();
+    let now = SystemTime::now();
+    expiries.retain(|key, deadline| {
+        let live = *deadline > now;
+        if !live {
+            store.remove(key);
+        }
+        live
+    });
+    match (request.command(), request.value.as_deref()) {
+        (Some(CommandType::Set), Some(value)) => {
+            expiries.remove(&request.key);
+            store.insert(request.key, value.to_vec());
+            success_response(None)
+        }
+        (Some(CommandType::SetWithExpiry), Some(value)) => {
+            match split_set_with_expiry_value(value) {
+                Some((expiry, value)) => {
+                    expiries.insert(request.key.clone(), expiry.deadline(now));
+                    store.insert(request.key, value.to_vec());
+                    success_response(None)
+                }
+                None => error_response("Malformed SETEX value"),
+            }
+        }
+        (Some(CommandType::Ttl), _) if store.contains_key(&request.key) => {
+            let deadline = expiries.get(&request.key).copied();
+            success_response(encode_ttl(deadline, now).as_deref())
+        }
+        (Some(CommandType::Persist), _) if store.contains_key(&request.key) => {
+            expiries.remove(&request.key);
+            success_response(None)
+        }
+        (Some(CommandType::Ttl | CommandType::Persist), _) => not_found_response(),
+        (Some(CommandType::Set), None) => error_response("SET command requires a value"),
+        (Some(CommandType::Get), _) => match store.get(&request.key) {
+            Some(value) => success_response(Some(value.as_slice())),
+            None => not_found_response(),
+        },
+        (Some(CommandType::Exists), _) if store.contains_key(&request.key) => {
+            success_response(None)
+        }
+        (Some(CommandType::Exists), _) => not_found_response(),
+        (Some(CommandType::Delete), _) => {
+            expiries.remove(&request.key);
+            store.remove(&request.key);
+            success_response(None)
+        }
+        (Some(CommandType::SetIfAbsent), Some(value)) => {
+            if store.contains_key(&request.key) {
+                return condition_failed_response();
+            }
+            store.insert(request.key, value.to_vec());
+            success_response(None)
+        }
+        (Some(CommandType::CompareAndSet), Some(value)) => {
+            match split_compare_and_set_value(value) {
+                Some((expected, new))
+                    if store.get(&request.key).map(Vec::as_slice) == Some(expected) =>
+                {
+                    store.insert(request.key, new.to_vec());
+                    success_response(None)
+                }
+                Some(_) => condition_failed_response(),
+                None => error_response("Malformed compare-and-set value"),
+            }
+        }
+        (Some(CommandType::CompareAndDelete), Some(expected)) => {
+            if store.get(&request.key).map(Vec::as_slice) != Some(expected) {
+                return condition_failed_response();
+            }
+            expiries.remove(&request.key);
+            store.remove(&request.key);
+            success_response(None)
+        }
+        (
+            Some(
+                CommandType::SetIfAbsent
+                | CommandType::SetWithExpiry
+                | CommandType::CompareAndSet
+                | CommandType::CompareAndDelete,
+            ),
+            None,
+        ) => error_response("Command requires a value"),
+        (Some(CommandType::IncrBy | CommandType::DecrBy), _) => {
+            match counter::apply(&request, store.get(&request.key).map(Vec::as_slice)) {
+                Some(Ok(value)) => {
+                    store.insert(request.key, encode_counter(value).to_vec());
+                    success_response(Some(&encode_counter(value)))
+                }
+                Some(Err(CounterError::Overflow)) => overflow_response(),
+                Some(Err(CounterError::NotACounter)) => error_response("Value is not a counter"),
+                None => error_response("Malformed counter delta"),
+            }
+        }
+        (Some(CommandType::Batch), _) => match parse_batch_request(&request) {
+            Some(batch) if batch.ops().iter().all(|op| !op.key().is_empty()) => {
+                for op in batch.ops() {
+                    expiries.remove(op.key());
+                    match op {
+                        BatchOp::Set { key, value } => store.insert(key.clone(), value.clone()),
+                        BatchOp::Delete { key } => store.remove(key),
+                    };
+                }
+                success_response(None)
+            }
+            Some(_) => error_response("Invalid key length"),
+            None => error_response("Malformed BATCH"),
+        },
+        (Some(CommandType::Scan), _) => match parse_scan_request(&request) {
+            Some(scan) => {
+                let entries = store
+                    .range(scan.start().to_vec()..)
+                    .map(|(key, value)| (key.as_slice(), value.as_slice()));
+                success_response(Some(&encode_scan_page(&collect_page(&scan, entries))))
+            }
+            None => error_response("Malformed SCAN options"),
+        },
+        (Some(CommandType::Ping), _) => success_response(None),
+        (Some(CommandType::Info), _) => {
+            let info = ServerInfo {
+                format: INFO_FORMAT,
+                durability: Some(Durability::Fast),
+                memtable_bytes: store
+                    .iter()
+                    .map(|(key, value)| (key.len() + value.len()) as u64)
+                    .sum(),
+                limits: Some(state.limits),
+                ..ServerInfo::default()
+            };
+            success_response(Some(&encode_info(&info)))
+        }
+        (Some(CommandType::Auth), offered) => {
+            auth_response(state.token.as_deref(), offered.unwrap_or_default())
+        }
+        (Some(CommandType::Hello), offered) if knows_hello => {
+            hello_response(offered.unwrap_or_default(), &state.versions)
+        }
+        (Some(CommandType::Hello) | None, _) => {
+            error_response(&format!("Unknown command type: {}", request.command_type))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handshake::{accepted_version, create_hello_request};
+    use crate::request::{create_delete_request, create_get_request, create_set_request};
+    use crate::response::{parse_response, to_client_result_value, ClientResult};
+
+    fn roundtrip(stream: &mut TcpStream, request: &[u8]) -> io::Result<Vec<u8>> {
+        stream.write_all(request)?;
+        let mut header = [0u8; HEADER_SIZE];
+        stream.read_exact(&mut header)?;
+        let total = i32::from_le_bytes(header[6..10].try_into().unwrap()) as usize;
+        let mut response = header.to_vec();
+        response.resize(total, 0);
+        stream.read_exact(&mut response[HEADER_SIZE..])?;
+        Ok(response)
+    }
+
+    fn value_of(response: Vec<u8>) -> Option<Vec<u8>> {
+        match to_client_result_value(&parse_response(&response)) {
+            ClientResult::Success(value) => Some(value),
+            ClientResult::NotFound => None,
+            ClientResult::Error(message) => panic!("unexpected error: {message}"),
+        }
+    }
+
+    #[test]
+    fn serves_set_get_delete() {
+        let server = MockServer::start().unwrap();
+        let mut stream = TcpStream::connect(server.addr()).unwrap();
+
+        roundtrip(&mut stream, &create_set_request("k", b"v")).unwrap();
+        assert_eq!(server.get(b"k"), Some(b"v".to_vec()));
+
+        let got = roundtrip(&mut stream, &create_get_request("k")).unwrap();
+        assert_eq!(value_of(got), Some(b"v".to_vec()));
+
+        roundtrip(&mut stream, &create_delete_request("k")).unwrap();
+        let got = roundtrip(&mut stream, &create_get_request("k")).unwrap();
+        assert_eq!(value_of(got), None);
+        assert_eq!(server.request_count(), 4);
+    }
+
+    #[test]
+    fn scripted_faults_apply_in_order() {
+        let server = MockServer::start().unwrap();
+        server.insert("k", "v");
+        server.push_fault(Fault::Error("boom".into()));
+        server.push_fault(Fault::Truncate(5));
+
+        let mut stream = TcpStream::connect(server.addr()).unwrap();
+        let got = roundtrip(&mut stream, &create_get_request("k")).unwrap();
+        let parsed = to_client_result_value(&parse_response(&got));
+        assert_eq!(parsed, ClientResult::Error("boom".into()));
+
+        let err = roundtrip(&mut stream, &create_get_request("k")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn v1_only_server_rejects_hello_and_v2_frames() {
+        let server = MockServer::start_with_versions(&[1]).unwrap();
+        let mut stream = TcpStream::connect(server.addr()).unwrap();
+
+        let reply = roundtrip(&mut stream, &create_hello_request(SUPPORTED_VERSIONS)).unwrap();
+        assert_eq!(accepted_version(&reply, SUPPORTED_VERSIONS).unwrap(), 1);
+
+        let v2 = encode_frame(create_get_request("k"), 2, 1);
+        assert!(roundtrip(&mut stream, &v2).is_err());
+    }
+
+    #[test]
+    fn dropped_connection_reports_eof() {
+        let server = MockServer::start().unwrap();
+        server.push_fault(Fault::Drop);
+
+        let mut stream = TcpStream::connect(server.addr()).unwrap();
+        assert!(roundtrip(&mut stream, &create_get_request("k")).is_err());
+
+        let mut stream = TcpStream::connect(server.addr()).unwrap();
+        let got = roundtrip(&mut stream, &create_get_request("k")).unwrap();
+        assert_eq!(value_of(got), None);
+    }
+}
@@ -0,0 +1,401 @@
+//! Frame encoding and decoding, after `Ocis.Client.SDK.Protocol`.
+//!
+//! Frames form a version-tagged family: byte 4 of every header carries the
+//! protocol version. v2 frames extend the v1 header with a little-endian
+//! `u64` request id, which responses echo, and v3 frames add a flags byte
+//! after it (see [`crate::durability`]). The extension is counted in the
+//! total packet length. The generated codec only knows v1, so later frames are
+//! mapped onto the v1 layout before decoding, and the version tag and wire
+//! total length are restored on the decoded packet. Use [`request_id`] to
+//! read the id.
+//!
+//! A v3 frame with [`CHECKSUM_FLAG`] set ends in a little-endian CRC32C of
+//! every byte before it, counted in the total length (see
+//! [`add_checksum`]). The decoders verify and drop the trailer: a frame
+//! whose checksum does not match fails to decode. A server answers a
+//! checksummed request with a checksummed response.
+
+use std::borrow::Cow;
+
+use crate::fable::{self, Protocol};
+use crate::spec::{
+    header_size, is_supported_version, RequestPacket, ResponsePacket, StatusCode, HEADER_SIZE,
+    MAGIC_NUMBER, PROTOCOL_VERSION, REQUEST_ID_SIZE,
+};
+
+/// Offset of the flags byte in v3 and later frames.
+const FLAGS_OFFSET: usize = HEADER_SIZE + REQUEST_ID_SIZE;
+
+/// Flags bit marking a frame that ends in a CRC32C trailer.
+pub const CHECKSUM_FLAG: u8 = 0x80;
+
+/// Size of the CRC32C trailer.
+pub const CHECKSUM_SIZE: usize = 4;
+
+/// Outcome of decoding a response frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseResult<T> {
+    ParseSuccess(T),
+    ParseError(String),
+    /// The buffer is shorter than the header or the declared frame.
+    InsufficientData,
+}
+
+/// Parse the fixed-size header of a request frame. The returned packet has
+/// an empty key and no value.
+pub fn try_parse_request_header(buffer: &[u8]) -> Option<RequestPacket> {
+    let (version, v1) = as_v1(buffer);
+    Protocol::TryParseRequestHeader(fable::array(&v1)).map(|p| RequestPacket {
+        version,
+        total_packet_length: total_length(buffer),
+        ..fable::request_from(&p)
+    })
+}
+
+/// Parse a complete request frame.
+pub fn try_parse_request_packet(buffer: &[u8]) -> Option<RequestPacket> {
+    if is_complete(buffer) && !verify_checksum(buffer) {
+        return None;
+    }
+    let (version, v1) = as_v1(buffer);
+    Protocol::TryParseRequestPacket(fable::array(&v1)).map(|p| RequestPacket {
+        version,
+        total_packet_length: total_length(buffer),
+        ..fable::request_from(&p)
+    })
+}
+
+/// Encode `packet` as a v1 frame; pass the result to [`encode_frame`] for
+/// later versions.
+pub fn serialize_request(packet: &RequestPacket) -> Vec<u8> {
+    Protocol::SerializeRequest(fable::request_into(packet)).to_vec()
+}
+
+/// Encode `packet` as a v1 frame; pass the result to [`encode_frame`] for
+/// later versions.
+pub fn serialize_response(packet: &ResponsePacket) -> Vec<u8> {
+    Protocol::SerializeResponse(fable::response_into(packet)).to_vec()
+}
+
+pub fn deserialize_response(buffer: &[u8]) -> ParseResult<ResponsePacket> {
+    if is_complete(buffer) && !verify_checksum(buffer) {
+        return ParseResult::ParseError("Frame checksum mismatch".into());
+    }
+    let (version, v1) = as_v1(buffer);
+    match Protocol::DeserializeResponse(fable::array(&v1)).as_ref() {
+        Protocol::ParseResult_1::ParseSuccess(packet) => {
+            ParseResult::ParseSuccess(ResponsePacket {
+                version,
+                total_packet_length: total_length(buffer),
+                ..fable::response_from(packet)
+            })
+        }
+        Protocol::ParseResult_1::ParseError(message) => {
+            ParseResult::ParseError(message.to_string())
+        }
+        Protocol::ParseResult_1::InsufficientData => ParseResult::InsufficientData,
+    }
+}
+
+pub fn success_response(value: Option<&[u8]>) -> ResponsePacket {
+    fable::response_from(&Protocol::CreateSuccessResponse(value.map(fable::array)))
+}
+
+pub fn not_found_response() -> ResponsePacket {
+    fable::response_from(&Protocol::CreateNotFoundResponse())
+}
+
+/// Response to a conditional write whose condition did not hold.
+pub fn condition_failed_response() -> ResponsePacket {
+    ResponsePacket {
+        status_code: StatusCode::ConditionFailed as u8,
+        ..not_found_response()
+    }
+}
+
+/// Response to a counter update that would overflow.
+pub fn overflow_response() -> ResponsePacket {
+    ResponsePacket {
+        status_code: StatusCode::Overflow as u8,
+        ..not_found_response()
+    }
+}
+
+/// Response to a command sent before the connection authenticated, or to
+/// an AUTH with the wrong token.
+pub fn unauthorized_response() -> ResponsePacket {
+    ResponsePacket {
+        status_code: StatusCode::Unauthorized as u8,
+        ..not_found_response()
+    }
+}
+
+pub fn error_response(message: &str) -> ResponsePacket {
+    fable::response_from(&Protocol::CreateErrorResponse(fable::text(message)))
+}
+
+pub fn is_valid_packet_size(total_length: i32) -> bool {
+    Protocol::IsValidPacketSize(total_length)
+}
+
+/// Total frame length declared by a request or response header, if it is
+/// within the allowed packet size. Transports read this many bytes in all.
+pub fn frame_length(header: &[u8; HEADER_SIZE]) -> Option<usize> {
+    let total_length = i32::from_le_bytes(header[6..10].try_into().unwrap());
+    is_valid_packet_size(total_length).then_some(total_length as usize)
+}
+
+/// Protocol version a frame is tagged with, once enough of the header has
+/// arrived to tell. Does not check that the version is supported.
+pub fn frame_version(buffer: &[u8]) -> Option<u8> {
+    if buffer.len() <= 4 {
+        return None;
+    }
+    let magic = u32::from_le_bytes(buffer[..4].try_into().unwrap());
+    (magic == MAGIC_NUMBER).then_some(buffer[4])
+}
+
+/// Re-encode a v1 frame, as produced by [`crate::request`] or
+/// [`serialize_response`], as a frame of `version`. `request_id` is
+/// ignored for v1.
+///
+/// # Panics
+///
+/// If `version` is not supported or `frame` is shorter than a v1 header.
+pub fn encode_frame(frame: Vec<u8>, version: u8, request_id: u64) -> Vec<u8> {
+    encode_frame_with_flags(frame, version, request_id, 0)
+}
+
+/// Like [`encode_frame`], also setting the flags byte of v3 and later
+/// frames. Earlier versions have no room for flags and drop them.
+pub fn encode_frame_with_flags(
+    mut frame: Vec<u8>,
+    version: u8,
+    request_id: u64,
+    flags: u8,
+) -> Vec<u8> {
+    assert!(
+        is_supported_version(version),
+        "unsupported protocol version {version}"
+    );
+    if version == PROTOCOL_VERSION {
+        return frame;
+    }
+    let extension = header_size(version) - HEADER_SIZE;
+    frame[4] = version;
+    let total = total_length(&frame).wrapping_add(extension as i32);
+    frame[6..10].copy_from_slice(&total.to_le_bytes());
+    let mut header = request_id.to_le_bytes().to_vec();
+    header.push(flags);
+    header.truncate(extension);
+    frame.splice(HEADER_SIZE..HEADER_SIZE, header);
+    frame
+}
+
+/// Request id carried by a v2 or later frame; `None` for v1 frames or too
+/// short a buffer.
+pub fn request_id(frame: &[u8]) -> Option<u64> {
+    let version = frame_version(frame).filter(|&v| v > PROTOCOL_VERSION)?;
+    frame.get(HEADER_SIZE..header_size(version))?;
+    let id = &frame[HEADER_SIZE..HEADER_SIZE + REQUEST_ID_SIZE];
+    Some(u64::from_le_bytes(id.try_into().unwrap()))
+}
+
+/// Flags byte of a v3 or later frame; `None` for earlier versions or too
+/// short a buffer.
+pub fn frame_flags(frame: &[u8]) -> Option<u8> {
+    let version = frame_version(frame).filter(|&v| header_size(v) > FLAGS_OFFSET)?;
+    frame.get(FLAGS_OFFSET..header_size(version))?;
+    Some(frame[FLAGS_OFFSET])
+}
+
+/// Append a CRC32C trailer to a v3 or later `frame`, as produced by
+/// [`encode_frame_with_flags`], and set [`CHECKSUM_FLAG`]. Earlier
+/// versions have no flags byte and are returned unchanged.
+pub fn add_checksum(mut frame: Vec<u8>) -> Vec<u8> {
+    if frame_flags(&frame).is_none() {
+        return frame;
+    }
+    frame[FLAGS_OFFSET] |= CHECKSUM_FLAG;
+    let total = total_length(&frame).wrapping_add(CHECKSUM_SIZE as i32);
+    frame[6..10].copy_from_slice(&total.to_le_bytes());
+    let checksum = crc32c::crc32c(&frame);
+    frame.extend_from_slice(&checksum.to_le_bytes());
+    frame
+}
+
+/// Whether `frame` is flagged as ending in a CRC32C trailer.
+pub fn has_checksum(frame: &[u8]) -> bool {
+    frame_flags(frame).is_some_and(|flags| flags & CHECKSUM_FLAG != 0)
+}
+
+/// Whether the complete `frame` is intact: it carries no checksum, or its
+/// checksum matches.
+pub fn verify_checksum(frame: &[u8]) -> bool {
+    if !has_checksum(frame) {
+        return true;
+    }
+    let Some(end) = frame
+        .len()
+        .checked_sub(CHECKSUM_SIZE)
+        .filter(|&end| end >= header_size(frame[4]))
+    else {
+        return false;
+    };
+    let (body, trailer) = frame.split_at(end);
+    crc32c::crc32c(body).to_le_bytes() == trailer
+}
+
+fn is_complete(buffer: &[u8]) -> bool {
+    buffer.len() >= HEADER_SIZE && buffer.len() as i64 >= i64::from(total_length(buffer))
+}
+
+fn total_length(buffer: &[u8]) -> i32 {
+    buffer
+        .get(6..10)
+        .map_or(0, |b| i32::from_le_bytes(b.try_into().unwrap()))
+}
+
+/// The frame's version tag together with the frame in the v1 layout the
+/// generated codec decodes. Frames with a bad magic number, an unsupported
+/// version or a total length too short for their header pass through
+/// untouched so that the generated codec rejects them. A buffer holding
+/// less than the whole frame keeps only the fixed-size header, with its
+/// total length as is, so that the generated codec waits for the rest
+/// rather than mistaking a partial trailer for a complete v1 frame.
+fn as_v1(buffer: &[u8]) -> (u8, Cow<'_, [u8]>) {
+    let total = total_length(buffer);
+    let Some(version) = frame_version(buffer)
+        .filter(|&v| v != PROTOCOL_VERSION && is_supported_version(v))
+        .filter(|&v| total >= header_size(v) as i32)
+    else {
+        return (PROTOCOL_VERSION, Cow::Borrowed(buffer));
+    };
+
+    let size = header_size(version);
+    let mut frame = buffer[..buffer.len().min(HEADER_SIZE)].to_vec();
+    frame[4] = PROTOCOL_VERSION;
+    if buffer.len() >= total as usize {
+        let trailer = if has_checksum(buffer) {
+            CHECKSUM_SIZE
+        } else {
+            0
+        };
+        let v1_total = total - (size - HEADER_SIZE + trailer) as i32;
+        frame[6..10].copy_from_slice(&v1_total.to_le_bytes());
+        frame.extend_from_slice(&buffer[size..(total as usize).saturating_sub(trailer).max(size)]);
+    }
+    (version, Cow::Owned(frame))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_response_round_trips() {
+        let frame = serialize_response(&error_response("boom"));
+        let header: [u8; HEADER_SIZE] = frame[..HEADER_SIZE].try_into().unwrap();
+        assert_eq!(frame_length(&header), Some(frame.len()));
+
+        let ParseResult::ParseSuccess(packet) = deserialize_response(&frame) else {
+            panic!("expected a parsed response");
+        };
+        assert_eq!(packet.status(), Some(StatusCode::Error));
+        assert_eq!(packet.error_message.as_deref(), Some("boom"));
+    }
+
+    #[test]
+    fn frame_length_rejects_oversized_frames() {
+        let mut header = [0u8; HEADER_SIZE];
+        header[6..10].copy_from_slice(&(11 * 1024 * 1024i32).to_le_bytes());
+        assert_eq!(frame_length(&header), None);
+    }
+
+    #[test]
+    fn v2_frames_decode_with_their_version() {
+        let frame = encode_frame(serialize_response(&success_response(Some(b"v"))), 2, 7);
+        assert_eq!(frame_version(&frame), Some(2));
+        assert_eq!(request_id(&frame), Some(7));
+        let header: [u8; HEADER_SIZE] = frame[..HEADER_SIZE].try_into().unwrap();
+        assert_eq!(frame_length(&header), Some(frame.len()));
+
+        let ParseResult::ParseSuccess(packet) = deserialize_response(&frame) else {
+            panic!("expected a parsed response");
+        };
+        assert_eq!(packet.version, 2);
+        assert_eq!(packet.total_packet_length as usize, frame.len());
+        assert_eq!(packet.value.as_deref(), Some(&b"v"[..]));
+
+        let mut unknown = frame;
+        unknown[4] = 9;
+        assert!(matches!(
+            deserialize_response(&unknown),
+            ParseResult::ParseError(_)
+        ));
+    }
+
+    #[test]
+    fn v3_frames_carry_flags_after_the_request_id() {
+        let v1 = serialize_response(&success_response(Some(b"v")));
+        let frame = encode_frame_with_flags(v1.clone(), 3, 7, 0b10);
+        assert_eq!(frame.len(), v1.len() + 9);
+        assert_eq!(request_id(&frame), Some(7));
+        assert_eq!(frame_flags(&frame), Some(0b10));
+
+        let ParseResult::ParseSuccess(packet) = deserialize_response(&frame) else {
+            panic!("expected a parsed response");
+        };
+        assert_eq!(packet.version, 3);
+        assert_eq!(packet.value.as_deref(), Some(&b"v"[..]));
+
+        let v2 = encode_frame_with_flags(v1, 2, 7, 0b10);
+        assert_eq!(frame_flags(&v2), None);
+        assert_eq!(request_id(&v2), Some(7));
+    }
+
+    #[test]
+    fn checksummed_frames_verify_and_reject_corruption() {
+        let v1 = serialize_response(&success_response(Some(b"value")));
+        let frame = add_checksum(encode_frame_with_flags(v1.clone(), 3, 7, 0b10));
+        assert_eq!(frame.len(), v1.len() + 9 + CHECKSUM_SIZE);
+        assert_eq!(frame_flags(&frame), Some(0b10 | CHECKSUM_FLAG));
+        assert!(verify_checksum(&frame));
+        let ParseResult::ParseSuccess(packet) = deserialize_response(&frame) else {
+            panic!("expected a parsed response");
+        };
+        assert_eq!(packet.value.as_deref(), Some(&b"value"[..]));
+        assert_eq!(packet.total_packet_length as usize, frame.len());
+
+        let mut corrupt = frame.clone();
+        corrupt[frame.len() - CHECKSUM_SIZE - 1] ^= 1;
+        assert!(!verify_checksum(&corrupt));
+        assert!(matches!(
+            deserialize_response(&corrupt),
+            ParseResult::ParseError(_)
+        ));
+
+        let request = encode_frame_with_flags(crate::request::create_get_request("k"), 3, 1, 0);
+        let mut request = add_checksum(request);
+        assert_eq!(try_parse_request_packet(&request).unwrap().key, b"k");
+        request[HEADER_SIZE + REQUEST_ID_SIZE + 1] ^= 1;
+        assert_eq!(try_parse_request_packet(&request), None);
+
+        let v2 = encode_frame(v1, 2, 7);
+        assert_eq!(add_checksum(v2.clone()), v2);
+
+        let empty = serialize_response(&success_response(None));
+        let frame = add_checksum(encode_frame_with_flags(empty, 3, 7, 0));
+        for end in HEADER_SIZE..frame.len() {
+            assert!(matches!(
+                deserialize_response(&frame[..end]),
+                ParseResult::InsufficientData
+            ));
+        }
+        let request = encode_frame_with_flags(crate::request::create_get_request(""), 3, 1, 0);
+        let request = add_checksum(request);
+        for end in HEADER_SIZE..request.len() {
+            assert_eq!(try_parse_request_packet(&request[..end]), None);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Request frame builders, after `Ocis.Client.SDK.Request`.
+//!
+//! The conditional writes are built here rather than in the generated code.
+//! Their value layout:
+//!
+//! - SET-if-absent: the new value
+//! - compare-and-set: `expected length i32 | expected | new value`
+//! - compare-and-delete: the expected value
+
+use crate::fable::{self, Request};
+use crate::protocol::serialize_request;
+use crate::spec::{CommandType, RequestPacket, HEADER_SIZE, MAGIC_NUMBER, PROTOCOL_VERSION};
+
+pub fn create_set_request(key: &str, value: &[u8]) -> Vec<u8> {
+    Request::createSetRequest(fable::text(key), fable::array(value)).to_vec()
+}
+
+pub fn create_get_request(key: &str) -> Vec<u8> {
+    Request::createGetRequest(fable::text(key)).to_vec()
+}
+
+pub fn create_delete_request(key: &str) -> Vec<u8> {
+    Request::createDeleteRequest(fable::text(key)).to_vec()
+}
+
+pub fn create_exists_request(key: &str) -> Vec<u8> {
+    Request::createExistsRequest(fable::text(key)).to_vec()
+}
+
+/// SET `value` only if `key` does not exist.
+pub fn create_set_if_absent_request(key: &str, value: &[u8]) -> Vec<u8> {
+    create_request(
+        CommandType::SetIfAbsent,
+        key.as_bytes(),
+        Some(value.to_vec()),
+    )
+}
+
+/// SET `value` only if the current value of `key` is `expected`.
+pub fn create_compare_and_set_request(key: &str, expected: &[u8], value: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(4 + expected.len() + value.len());
+    body.extend_from_slice(&(expected.len() as i32).to_le_bytes());
+    body.extend_from_slice(expected);
+    body.extend_from_slice(value);
+    create_request(CommandType::CompareAndSet, key.as_bytes(), Some(body))
+}
+
+/// DELETE `key` only if its current value is `expected`.
+pub fn create_compare_and_delete_request(key: &str, expected: &[u8]) -> Vec<u8> {
+    create_request(
+        CommandType::CompareAndDelete,
+        key.as_bytes(),
+        Some(expected.to_vec()),
+    )
+}
+
+/// Server side: split a compare-and-set value into the expected and the new
+/// value. `None` if it is malformed.
+pub fn split_compare_and_set_value(value: &[u8]) -> Option<(&[u8], &[u8])> {
+    let length = i32::from_le_bytes(value.get(..4)?.try_into().unwrap());
+    let rest = &value[4..];
+    let length = usize::try_from(length).ok().filter(|&n| n <= rest.len())?;
+    Some(rest.split_at(length))
+}
+
+/// Build a v1 request frame for a command the generated builders do not
+/// cover. An empty value is sent as no value.
+pub(crate) fn create_request(command: CommandType, key: &[u8], value: Option<Vec<u8>>) -> Vec<u8> {
+    let value = value.filter(|value| !value.is_empty());
+    let value_length = value.as_ref().map_or(0, Vec::len);
+    serialize_request(&RequestPacket {
+        magic_number: MAGIC_NUMBER,
+        version: PROTOCOL_VERSION,
+        command_type: command as i32,
+        total_packet_length: (HEADER_SIZE + key.len() + value_length) as i32,
+        key_length: key.len() as i32,
+        value_length: value_length as i32,
+        key: key.to_vec(),
+        value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::try_parse_request_packet;
+
+    #[test]
+    fn compare_and_set_value_round_trips() {
+        let frame = create_compare_and_set_request("k", b"old", b"new");
+        let request = try_parse_request_packet(&frame).unwrap();
+        assert_eq!(request.command(), Some(CommandType::CompareAndSet));
+
+        let value = request.value.unwrap();
+        assert_eq!(
+            split_compare_and_set_value(&value),
+            Some((&b"old"[..], &b"new"[..]))
+        );
+        assert_eq!(split_compare_and_set_value(&[9, 0, 0, 0, 1]), None);
+        assert_eq!(split_compare_and_set_value(&[0xFF; 4]), None);
+    }
+}
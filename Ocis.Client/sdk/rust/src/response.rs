@@ -0,0 +1,62 @@
+//! Response parsing, after `Ocis.Client.SDK.Response`.
+
+use crate::fable::{self, Response};
+use crate::protocol::ParseResult;
+use crate::spec::ResponsePacket;
+
+/// What a request amounted to from the caller's point of view.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClientResult<T> {
+    Success(T),
+    NotFound,
+    Error(String),
+}
+
+pub fn parse_response(bytes: &[u8]) -> ParseResult<ResponsePacket> {
+    crate::protocol::deserialize_response(bytes)
+}
+
+/// Interpret a response to SET or DELETE.
+pub fn to_client_result(result: &ParseResult<ResponsePacket>) -> ClientResult<()> {
+    let result = Response::toClientResult(fable::parse_result_into(result));
+    fable::client_result_from(&result, |()| ())
+}
+
+/// Interpret a response to GET. A success without a value is an error.
+pub fn to_client_result_value(result: &ParseResult<ResponsePacket>) -> ClientResult<Vec<u8>> {
+    let result = Response::toClientResultValue(fable::parse_result_into(result));
+    fable::client_result_from(&result, |value| value.to_vec())
+}
+
+/// Interpret a response to EXISTS: NotFound is `Success(false)`.
+pub fn to_client_result_exists(result: &ParseResult<ResponsePacket>) -> ClientResult<bool> {
+    let result = Response::toClientResultExists(fable::parse_result_into(result));
+    fable::client_result_from(&result, |exists| *exists)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{not_found_response, serialize_response, success_response};
+
+    #[test]
+    fn maps_status_codes() {
+        let found = parse_response(&serialize_response(&success_response(Some(b"v"))));
+        assert_eq!(
+            to_client_result_value(&found),
+            ClientResult::Success(b"v".to_vec())
+        );
+
+        let missing = parse_response(&serialize_response(&not_found_response()));
+        assert_eq!(to_client_result(&missing), ClientResult::NotFound);
+        assert_eq!(
+            to_client_result_exists(&missing),
+            ClientResult::Success(false)
+        );
+
+        assert_eq!(
+            to_client_result(&ParseResult::InsufficientData),
+            ClientResult::Error("Insufficient data".into())
+        );
+    }
+}
@@ -0,0 +1,384 @@
+//! SCAN: ordered key iteration in pages.
+//!
+//! A SCAN request carries the key to start from (inclusive) as its key and
+//! the encoded [`Scan`] options as its value. The success response's value
+//! is an encoded [`ScanPage`]; its cursor, when present, is the key to start
+//! the next page from. Keys are compared as raw bytes.
+//!
+//! Options, little-endian:
+//!
+//! ```text
+//! flags u8 (1 = keys only) | limit u32 | bound u8 (0 none, 1 end, 2 prefix)
+//! | bound length i32 | bound bytes
+//! ```
+//!
+//! Page:
+//!
+//! ```text
+//! flags u8 (1 = keys only, 2 = has cursor) | count u32
+//! | count × (key length i32 | key | [value length i32 | value])
+//! | [cursor length i32 | cursor]
+//! ```
+
+use crate::request::create_request;
+use crate::spec::{CommandType, RequestPacket};
+
+/// Page size used when a scan does not set a limit.
+pub const DEFAULT_SCAN_LIMIT: u32 = 256;
+
+/// Largest page size a server returns, whatever the request asks for.
+pub const MAX_SCAN_LIMIT: u32 = 10_000;
+
+/// Encoded entries a server packs into one page before cutting it short,
+/// so that pages stay well under the packet size limit. A page always
+/// holds at least one entry.
+pub const MAX_SCAN_PAGE_BYTES: usize = 8 * 1024 * 1024;
+
+const KEYS_ONLY: u8 = 1;
+const HAS_CURSOR: u8 = 2;
+
+/// Which keys a scan visits, in ascending byte order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Scan {
+    start: Vec<u8>,
+    bound: Bound,
+    limit: u32,
+    keys_only: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+enum Bound {
+    #[default]
+    None,
+    /// Exclusive upper bound.
+    End(Vec<u8>),
+    Prefix(Vec<u8>),
+}
+
+impl Scan {
+    /// Every key.
+    pub fn all() -> Self {
+        Scan::default()
+    }
+
+    /// Keys from `start` (inclusive) onwards.
+    pub fn starting_at(start: impl Into<Vec<u8>>) -> Self {
+        Scan {
+            start: start.into(),
+            ..Scan::default()
+        }
+    }
+
+    /// Keys in `start..end`.
+    pub fn range(start: impl Into<Vec<u8>>, end: impl Into<Vec<u8>>) -> Self {
+        Scan {
+            start: start.into(),
+            bound: Bound::End(end.into()),
+            ..Scan::default()
+        }
+    }
+
+    /// Keys starting with `prefix`.
+    pub fn prefix(prefix: impl Into<Vec<u8>>) -> Self {
+        let prefix = prefix.into();
+        Scan {
+            start: prefix.clone(),
+            bound: Bound::Prefix(prefix),
+            ..Scan::default()
+        }
+    }
+
+    /// Ask for at most `limit` entries per page; `0` means
+    /// [`DEFAULT_SCAN_LIMIT`]. Servers cap it at [`MAX_SCAN_LIMIT`].
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Return keys without their values.
+    pub fn keys_only(mut self) -> Self {
+        self.keys_only = true;
+        self
+    }
+
+    /// First key the scan visits.
+    pub fn start(&self) -> &[u8] {
+        &self.start
+    }
+
+    pub fn is_keys_only(&self) -> bool {
+        self.keys_only
+    }
+
+    /// Page size a server should use for this scan.
+    pub fn page_size(&self) -> usize {
+        match self.limit {
+            0 => DEFAULT_SCAN_LIMIT,
+            limit => limit.min(MAX_SCAN_LIMIT),
+        }
+        .try_into()
+        .unwrap()
+    }
+
+    /// This scan inside the keys starting with `namespace`, with its start
+    /// and bound mapped by `open`, which must map every key to a lower
+    /// bound of the namespaced keys starting with it.
+    pub(crate) fn within(&self, namespace: &[u8], open: impl Fn(&[u8]) -> Vec<u8>) -> Scan {
+        let bound = match &self.bound {
+            Bound::None => Bound::Prefix(namespace.to_vec()),
+            Bound::End(end) => Bound::End(open(end)),
+            Bound::Prefix(prefix) => Bound::Prefix(open(prefix)),
+        };
+        Scan {
+            start: open(&self.start),
+            bound,
+            ..self.clone()
+        }
+    }
+
+    /// Whether `key`, at or after [`Scan::start`], is still within the
+    /// scan. Keys come in order, so the first one outside ends the scan.
+    pub fn includes(&self, key: &[u8]) -> bool {
+        match &self.bound {
+            Bound::None => true,
+            Bound::End(end) => key < end.as_slice(),
+            Bound::Prefix(prefix) => key.starts_with(prefix),
+        }
+    }
+}
+
+/// One scanned key; `value` is `None` for keys-only scans.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub key: Vec<u8>,
+    pub value: Option<Vec<u8>>,
+}
+
+/// One page of scan results.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScanPage {
+    pub entries: Vec<Entry>,
+    /// Where the next page starts; `None` once the scan is complete.
+    pub cursor: Option<Vec<u8>>,
+}
+
+/// Build a SCAN request for the page starting at `cursor`, or at the
+/// beginning of the scan if `None`.
+pub fn create_scan_request(scan: &Scan, cursor: Option<&[u8]>) -> Vec<u8> {
+    let key = cursor.unwrap_or(&scan.start);
+    create_request(CommandType::Scan, key, Some(encode_options(scan)))
+}
+
+/// Server side: the scan a SCAN request asks for, starting at its key.
+/// `None` if the options are malformed.
+pub fn parse_scan_request(request: &RequestPacket) -> Option<Scan> {
+    let mut reader = Reader(request.value.as_deref()?);
+    let flags = reader.u8()?;
+    let limit = reader.u32()?;
+    let kind = reader.u8()?;
+    let bound = reader.bytes()?.to_vec();
+    let bound = match kind {
+        0 if bound.is_empty() => Bound::None,
+        1 => Bound::End(bound),
+        2 => Bound::Prefix(bound),
+        _ => return None,
+    };
+    reader.finish()?;
+    Some(Scan {
+        start: request.key.clone(),
+        bound,
+        limit,
+        keys_only: flags & KEYS_ONLY != 0,
+    })
+}
+
+/// Server side: fill a page from `entries`, which must be in ascending key
+/// order and begin at or after [`Scan::start`].
+pub fn collect_page<'a, I>(scan: &Scan, entries: I) -> ScanPage
+where
+    I: IntoIterator<Item = (&'a [u8], &'a [u8])>,
+{
+    let mut page = ScanPage::default();
+    let mut bytes = 0;
+    for (key, value) in entries {
+        if !scan.includes(key) {
+            break;
+        }
+        let size = 4 + key.len() + if scan.keys_only { 0 } else { 4 + value.len() };
+        let full = page.entries.len() == scan.page_size()
+            || (!page.entries.is_empty() && bytes + size > MAX_SCAN_PAGE_BYTES);
+        if full {
+            page.cursor = Some(key.to_vec());
+            break;
+        }
+        bytes += size;
+        page.entries.push(Entry {
+            key: key.to_vec(),
+            value: (!scan.keys_only).then(|| value.to_vec()),
+        });
+    }
+    page
+}
+
+pub fn encode_scan_page(page: &ScanPage) -> Vec<u8> {
+    let keys_only = page.entries.iter().all(|entry| entry.value.is_none());
+    let mut flags = 0;
+    if keys_only {
+        flags |= KEYS_ONLY;
+    }
+    if page.cursor.is_some() {
+        flags |= HAS_CURSOR;
+    }
+
+    let mut buffer = vec![flags];
+    buffer.extend_from_slice(&(page.entries.len() as u32).to_le_bytes());
+    for entry in &page.entries {
+        put_bytes(&mut buffer, &entry.key);
+        if !keys_only {
+            put_bytes(&mut buffer, entry.value.as_deref().unwrap_or_default());
+        }
+    }
+    if let Some(cursor) = &page.cursor {
+        put_bytes(&mut buffer, cursor);
+    }
+    buffer
+}
+
+/// Decode a page; `None` if it is malformed.
+pub fn decode_scan_page(buffer: &[u8]) -> Option<ScanPage> {
+    let mut reader = Reader(buffer);
+    let flags = reader.u8()?;
+    let count = reader.u32()?;
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let key = reader.bytes()?.to_vec();
+        let value = match flags & KEYS_ONLY {
+            0 => Some(reader.bytes()?.to_vec()),
+            _ => None,
+        };
+        entries.push(Entry { key, value });
+    }
+    let cursor = match flags & HAS_CURSOR {
+        0 => None,
+        _ => Some(reader.bytes()?.to_vec()),
+    };
+    reader.finish()?;
+    Some(ScanPage { entries, cursor })
+}
+
+fn encode_options(scan: &Scan) -> Vec<u8> {
+    let (kind, bound): (u8, &[u8]) = match &scan.bound {
+        Bound::None => (0, &[]),
+        Bound::End(end) => (1, end),
+        Bound::Prefix(prefix) => (2, prefix),
+    };
+    let mut buffer = vec![if scan.keys_only { KEYS_ONLY } else { 0 }];
+    buffer.extend_from_slice(&scan.limit.to_le_bytes());
+    buffer.push(kind);
+    put_bytes(&mut buffer, bound);
+    buffer
+}
+
+fn put_bytes(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    buffer.extend_from_slice(&(bytes.len() as i32).to_le_bytes());
+    buffer.extend_from_slice(bytes);
+}
+
+/// Bounds-checked little-endian reader over an untrusted buffer.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let length = i32::from_le_bytes(self.take(4)?.try_into().unwrap());
+        self.take(usize::try_from(length).ok()?)
+    }
+
+    fn finish(&self) -> Option<()> {
+        self.0.is_empty().then_some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::try_parse_request_packet;
+
+    fn store(keys: &[&str]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        keys.iter()
+            .map(|k| (k.as_bytes().to_vec(), k.to_uppercase().into_bytes()))
+            .collect()
+    }
+
+    fn page(scan: &Scan, store: &[(Vec<u8>, Vec<u8>)]) -> ScanPage {
+        let entries = store
+            .iter()
+            .filter(|(k, _)| k.as_slice() >= scan.start())
+            .map(|(k, v)| (k.as_slice(), v.as_slice()));
+        collect_page(scan, entries)
+    }
+
+    fn keys(page: &ScanPage) -> Vec<&[u8]> {
+        page.entries.iter().map(|e| e.key.as_slice()).collect()
+    }
+
+    #[test]
+    fn request_options_round_trip() {
+        let scan = Scan::prefix("user:").limit(10).keys_only();
+        let request = try_parse_request_packet(&create_scan_request(&scan, Some(b"user:7")))
+            .expect("scan request parses");
+        let parsed = parse_scan_request(&request).unwrap();
+        assert_eq!(parsed.start(), b"user:7");
+        assert_eq!(
+            parsed,
+            Scan {
+                start: b"user:7".to_vec(),
+                ..scan
+            }
+        );
+    }
+
+    #[test]
+    fn pages_respect_bounds_limits_and_cursors() {
+        let store = store(&["a", "b", "c", "d", "da", "e"]);
+
+        let first = page(&Scan::range("b", "e").limit(2), &store);
+        assert_eq!(keys(&first), [b"b", b"c"]);
+        assert_eq!(first.cursor.as_deref(), Some(&b"d"[..]));
+
+        let rest = page(&Scan::range("d", "e").limit(2), &store);
+        assert_eq!(keys(&rest), [&b"d"[..], b"da"]);
+        assert_eq!(rest.cursor, None);
+
+        let prefixed = page(&Scan::prefix("d").keys_only(), &store);
+        assert_eq!(keys(&prefixed), [&b"d"[..], b"da"]);
+        assert!(prefixed.entries.iter().all(|e| e.value.is_none()));
+    }
+
+    #[test]
+    fn pages_round_trip_and_reject_garbage() {
+        let page = page(&Scan::all().limit(3), &store(&["a", "b", "c", "d"]));
+        let encoded = encode_scan_page(&page);
+        assert_eq!(decode_scan_page(&encoded), Some(page));
+
+        assert_eq!(decode_scan_page(&encoded[..encoded.len() - 1]), None);
+        assert_eq!(decode_scan_page(&[0, 0xFF, 0xFF, 0xFF, 0xFF]), None);
+    }
+}
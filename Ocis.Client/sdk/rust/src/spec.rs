@@ -0,0 +1,186 @@
+//! Wire-format constants and packet types, after `Ocis.Server.ProtocolSpec`.
+
+/// Protocol magic number for ASCII bytes "OCIS".
+pub const MAGIC_NUMBER: u32 = 0x5349_434F;
+
+/// Baseline protocol version. Every server speaks it, and the HELLO
+/// handshake itself is always framed as v1.
+pub const PROTOCOL_VERSION: u8 = 1;
+
+/// Protocol versions this crate can encode and decode, lowest first.
+pub const SUPPORTED_VERSIONS: &[u8] = &[1, 2, 3];
+
+/// Highest protocol version this crate speaks.
+pub const LATEST_VERSION: u8 = 3;
+
+/// Protocol header size in bytes. Every version starts with this header;
+/// see [`header_size`] for the full header of later versions.
+pub const HEADER_SIZE: usize = 18;
+
+/// Size of the request id that v2 frames append to the v1 header.
+pub const REQUEST_ID_SIZE: usize = 8;
+
+/// Size of the flags byte that v3 frames append after the request id.
+pub const FLAGS_SIZE: usize = 1;
+
+/// Largest frame either side accepts (10 MiB).
+pub const MAX_PACKET_SIZE: usize = 10 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CommandType {
+    Set = 1,
+    Get = 2,
+    Delete = 3,
+    /// Version handshake; see [`crate::handshake`].
+    Hello = 4,
+    Exists = 5,
+    /// Ordered key iteration; see [`crate::scan`].
+    Scan = 6,
+    /// SET only if the key does not exist.
+    SetIfAbsent = 7,
+    /// SET only if the current value equals an expected one.
+    CompareAndSet = 8,
+    /// DELETE only if the current value equals an expected one.
+    CompareAndDelete = 9,
+    /// Add to a 64-bit counter; see [`crate::counter`].
+    IncrBy = 10,
+    /// Subtract from a 64-bit counter; see [`crate::counter`].
+    DecrBy = 11,
+    /// SET with an expiry; see [`crate::expiry`].
+    SetWithExpiry = 12,
+    /// Remaining time to live of a key.
+    Ttl = 13,
+    /// Remove a key's expiry.
+    Persist = 14,
+    /// Atomic multi-key write; see [`crate::batch`].
+    Batch = 15,
+    /// Liveness check; see [`crate::info`].
+    Ping = 16,
+    /// Server statistics; see [`crate::info`].
+    Info = 17,
+    /// Authenticate the connection; see [`crate::auth`].
+    Auth = 18,
+}
+
+impl CommandType {
+    pub fn from_i32(value: i32) -> Option<Self> {
+        match value {
+            1 => Some(CommandType::Set),
+            2 => Some(CommandType::Get),
+            3 => Some(CommandType::Delete),
+            4 => Some(CommandType::Hello),
+            5 => Some(CommandType::Exists),
+            6 => Some(CommandType::Scan),
+            7 => Some(CommandType::SetIfAbsent),
+            8 => Some(CommandType::CompareAndSet),
+            9 => Some(CommandType::CompareAndDelete),
+            10 => Some(CommandType::IncrBy),
+            11 => Some(CommandType::DecrBy),
+            12 => Some(CommandType::SetWithExpiry),
+            13 => Some(CommandType::Ttl),
+            14 => Some(CommandType::Persist),
+            15 => Some(CommandType::Batch),
+            16 => Some(CommandType::Ping),
+            17 => Some(CommandType::Info),
+            18 => Some(CommandType::Auth),
+            _ => None,
+        }
+    }
+
+    /// Whether the command can modify stored data, and so reports a
+    /// durability level in v3 responses.
+    pub fn is_write(self) -> bool {
+        !matches!(
+            self,
+            CommandType::Get
+                | CommandType::Hello
+                | CommandType::Exists
+                | CommandType::Scan
+                | CommandType::Ttl
+                | CommandType::Ping
+                | CommandType::Info
+                | CommandType::Auth
+        )
+    }
+}
+
+/// Whether `version` is one of [`SUPPORTED_VERSIONS`].
+pub fn is_supported_version(version: u8) -> bool {
+    SUPPORTED_VERSIONS.contains(&version)
+}
+
+/// Full header size of a `version` frame, request id and flags included.
+pub fn header_size(version: u8) -> usize {
+    match version {
+        0 | 1 => HEADER_SIZE,
+        2 => HEADER_SIZE + REQUEST_ID_SIZE,
+        _ => HEADER_SIZE + REQUEST_ID_SIZE + FLAGS_SIZE,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StatusCode {
+    Success = 0,
+    NotFound = 1,
+    Error = 2,
+    /// A conditional command found the key in a different state than
+    /// required; nothing was changed.
+    ConditionFailed = 3,
+    /// A counter update would leave the `i64` range; nothing was changed.
+    Overflow = 4,
+    /// The connection has not authenticated; see [`crate::auth`].
+    Unauthorized = 5,
+}
+
+impl StatusCode {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(StatusCode::Success),
+            1 => Some(StatusCode::NotFound),
+            2 => Some(StatusCode::Error),
+            3 => Some(StatusCode::ConditionFailed),
+            4 => Some(StatusCode::Overflow),
+            5 => Some(StatusCode::Unauthorized),
+            _ => None,
+        }
+    }
+}
+
+/// Request packet. `command_type` is kept raw so that servers can report
+/// unknown commands; use [`RequestPacket::command`] to interpret it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestPacket {
+    pub magic_number: u32,
+    pub version: u8,
+    pub command_type: i32,
+    pub total_packet_length: i32,
+    pub key_length: i32,
+    pub value_length: i32,
+    pub key: Vec<u8>,
+    pub value: Option<Vec<u8>>,
+}
+
+impl RequestPacket {
+    pub fn command(&self) -> Option<CommandType> {
+        CommandType::from_i32(self.command_type)
+    }
+}
+
+/// Response packet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResponsePacket {
+    pub magic_number: u32,
+    pub version: u8,
+    pub status_code: u8,
+    pub total_packet_length: i32,
+    pub value_length: i32,
+    pub error_message_length: i32,
+    pub value: Option<Vec<u8>>,
+    pub error_message: Option<String>,
+}
+
+impl ResponsePacket {
+    pub fn status(&self) -> Option<StatusCode> {
+        StatusCode::from_u8(self.status_code)
+    }
+}
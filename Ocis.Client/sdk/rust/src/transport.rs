@@ -0,0 +1,216 @@
+//! Pieces shared by the blocking and async transports.
+
+#[cfg(feature = "encryption")]
+use std::sync::Arc;
+
+use crate::batch::{BatchOp, WriteBatch};
+use crate::compression::{decode_value, escape, Compression};
+use crate::counter::decode_counter;
+#[cfg(feature = "encryption")]
+use crate::encryption::Keyring;
+use crate::error::{Error, Result};
+use crate::expiry::{decode_ttl, Ttl};
+use crate::info::{decode_info, ServerInfo};
+use crate::protocol::{frame_length, has_checksum, verify_checksum, ParseResult};
+use crate::response::{
+    parse_response, to_client_result, to_client_result_exists, to_client_result_value, ClientResult,
+};
+use crate::scan::{decode_scan_page, ScanPage};
+use crate::spec::{ResponsePacket, StatusCode, HEADER_SIZE};
+
+/// How a client turns the values it is given into the bytes it stores:
+/// compressed, then sealed. Reads undo both.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ValueCodec {
+    pub(crate) compression: Option<Compression>,
+    #[cfg(feature = "encryption")]
+    pub(crate) keyring: Option<Arc<Keyring>>,
+}
+
+impl ValueCodec {
+    /// The bytes to store for `value` under `key`.
+    #[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+    pub(crate) fn encode(&self, key: &[u8], value: &[u8]) -> Vec<u8> {
+        let encoded = match &self.compression {
+            Some(compression) => compression.encode(value),
+            None => escape(value),
+        };
+        #[cfg(feature = "encryption")]
+        if let Some(keyring) = &self.keyring {
+            return keyring.seal(key, &encoded);
+        }
+        encoded
+    }
+
+    /// `batch` with the value of every SET encoded.
+    pub(crate) fn encode_batch(&self, batch: &WriteBatch) -> WriteBatch {
+        batch
+            .ops()
+            .iter()
+            .fold(WriteBatch::new(), |encoded, op| match op {
+                BatchOp::Set { key, value } => encoded.set(key.clone(), self.encode(key, value)),
+                BatchOp::Delete { key } => encoded.delete(key.clone()),
+            })
+    }
+
+    /// `value` as compare-and-set and compare-and-delete send it, for the
+    /// server to compare byte for byte with stored values. Fails if stored
+    /// values cannot be compared that way.
+    pub(crate) fn encode_comparable(&self, value: &[u8]) -> Result<Vec<u8>> {
+        if self.compression.is_some() {
+            return Err(Error::Codec(
+                "conditional writes cannot compare compressed values".into(),
+            ));
+        }
+        #[cfg(feature = "encryption")]
+        if self.keyring.is_some() {
+            return Err(Error::Codec(
+                "conditional writes cannot compare encrypted values".into(),
+            ));
+        }
+        Ok(escape(value))
+    }
+
+    /// The value stored as `stored` under `key`.
+    #[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+    pub(crate) fn decode(&self, key: &[u8], stored: Vec<u8>) -> Result<Vec<u8>> {
+        #[cfg(feature = "encryption")]
+        let stored = match &self.keyring {
+            Some(keyring) => keyring.open(key, stored)?,
+            None if crate::encryption::sealed_with(&stored).is_some() => {
+                return Err(Error::Codec(
+                    "value is encrypted and no keyring is set".into(),
+                ))
+            }
+            None => stored,
+        };
+        decode_value(stored)
+    }
+}
+
+/// Allocate a buffer for the frame announced by `header`, with the header
+/// already copied in; the caller reads the remaining bytes into it.
+pub(crate) fn frame_buffer(header: &[u8; HEADER_SIZE]) -> Result<Vec<u8>> {
+    let total_length = frame_length(header).ok_or_else(|| {
+        let declared = i32::from_le_bytes(header[6..10].try_into().unwrap());
+        Error::Protocol(format!("invalid response packet size: {declared}"))
+    })?;
+    let mut frame = vec![0u8; total_length];
+    frame[..HEADER_SIZE].copy_from_slice(header);
+    Ok(frame)
+}
+
+/// Fail with [`Error::Integrity`] unless the response `frame` is intact
+/// and, if `checksummed`, carries a checksum.
+pub(crate) fn check_integrity(frame: &[u8], checksummed: bool) -> Result<()> {
+    if verify_checksum(frame) && (!checksummed || has_checksum(frame)) {
+        Ok(())
+    } else {
+        Err(Error::Integrity)
+    }
+}
+
+/// Decode a GET response; `None` means the key does not exist.
+pub(crate) fn value_reply(frame: &[u8]) -> Result<Option<Vec<u8>>> {
+    outcome(to_client_result_value(&parsed(frame)?))
+}
+
+/// Decode a GET response, unwrapping compressed values.
+pub(crate) fn stored_value_reply(
+    frame: &[u8],
+    key: &str,
+    values: &ValueCodec,
+) -> Result<Option<Vec<u8>>> {
+    value_reply(frame)?
+        .map(|stored| values.decode(key.as_bytes(), stored))
+        .transpose()
+}
+
+/// Decode a SET or DELETE response.
+pub(crate) fn unit_reply(frame: &[u8]) -> Result<()> {
+    outcome(to_client_result(&parsed(frame)?)).map(|_| ())
+}
+
+/// Decode an EXISTS response.
+pub(crate) fn exists_reply(frame: &[u8]) -> Result<bool> {
+    outcome(to_client_result_exists(&parsed(frame)?)).map(|exists| exists.unwrap_or(false))
+}
+
+/// Decode a conditional write response: `false` if the condition failed.
+pub(crate) fn condition_reply(frame: &[u8]) -> Result<bool> {
+    let parsed = parsed(frame)?;
+    if status(&parsed) == Some(StatusCode::ConditionFailed) {
+        return Ok(false);
+    }
+    outcome(to_client_result(&parsed)).map(|_| true)
+}
+
+/// Decode an INCRBY or DECRBY response: the updated counter.
+pub(crate) fn counter_reply(frame: &[u8]) -> Result<i64> {
+    let parsed = parsed(frame)?;
+    if status(&parsed) == Some(StatusCode::Overflow) {
+        return Err(Error::Overflow);
+    }
+    let value = outcome(to_client_result_value(&parsed))?.unwrap_or_default();
+    decode_counter(&value).ok_or_else(|| Error::Protocol("malformed counter".into()))
+}
+
+/// Decode a TTL response; `None` means the key does not exist.
+pub(crate) fn ttl_reply(frame: &[u8]) -> Result<Option<Ttl>> {
+    let parsed = parsed(frame)?;
+    if outcome(to_client_result(&parsed))?.is_none() {
+        return Ok(None);
+    }
+    let ParseResult::ParseSuccess(packet) = parsed else {
+        unreachable!("parse failures are rejected above");
+    };
+    match decode_ttl(packet.value.as_deref()) {
+        Some(ttl) => Ok(Some(ttl)),
+        None => Err(Error::Protocol("malformed TTL".into())),
+    }
+}
+
+/// Decode a SCAN response.
+pub(crate) fn scan_reply(frame: &[u8], values: &ValueCodec) -> Result<ScanPage> {
+    let page = value_reply(frame)?.unwrap_or_default();
+    let mut page =
+        decode_scan_page(&page).ok_or_else(|| Error::Protocol("malformed scan page".into()))?;
+    for entry in &mut page.entries {
+        if let Some(value) = entry.value.take() {
+            entry.value = Some(values.decode(&entry.key, value)?);
+        }
+    }
+    Ok(page)
+}
+
+/// Decode an INFO response.
+pub(crate) fn info_reply(frame: &[u8]) -> Result<ServerInfo> {
+    let info = value_reply(frame)?.unwrap_or_default();
+    decode_info(&info).ok_or_else(|| Error::Protocol("malformed server info".into()))
+}
+
+/// Deserialize a response frame once, rejecting malformed and
+/// unauthorized ones; the `*_reply` helpers interpret the result.
+fn parsed(frame: &[u8]) -> Result<ParseResult<ResponsePacket>> {
+    match parse_response(frame) {
+        ParseResult::ParseError(message) => Err(Error::Protocol(message)),
+        ParseResult::InsufficientData => Err(Error::Protocol("truncated response".into())),
+        parsed if status(&parsed) == Some(StatusCode::Unauthorized) => Err(Error::Unauthorized),
+        parsed => Ok(parsed),
+    }
+}
+
+fn status(parsed: &ParseResult<ResponsePacket>) -> Option<StatusCode> {
+    match parsed {
+        ParseResult::ParseSuccess(packet) => packet.status(),
+        _ => None,
+    }
+}
+
+fn outcome<T>(result: ClientResult<T>) -> Result<Option<T>> {
+    match result {
+        ClientResult::Success(value) => Ok(Some(value)),
+        ClientResult::NotFound => Ok(None),
+        ClientResult::Error(message) => Err(Error::Server(message)),
+    }
+}
@@ -0,0 +1,179 @@
+//! Property tests for the wire codec: everything the SDK serializes must
+//! parse back to the same packet, and the length fields in the header must
+//! always describe the bytes that follow.
+
+use ocis_client::protocol::{
+    deserialize_response, encode_frame, error_response, not_found_response, request_id,
+    serialize_request, serialize_response, success_response, try_parse_request_header,
+    try_parse_request_packet, ParseResult,
+};
+use ocis_client::request::{create_delete_request, create_get_request, create_set_request};
+use ocis_client::spec::{RequestPacket, ResponsePacket, MAGIC_NUMBER, PROTOCOL_VERSION};
+use proptest::prelude::*;
+
+const HEADER_SIZE: i32 = ocis_client::spec::HEADER_SIZE as i32;
+const MAGIC: u32 = MAGIC_NUMBER;
+const VERSION: u8 = PROTOCOL_VERSION;
+
+fn bytes(max: usize) -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(any::<u8>(), 0..max)
+}
+
+/// Values are either absent or non-empty: on the wire a zero-length value
+/// and no value are the same thing, and both parse back as `None`.
+fn value() -> impl Strategy<Value = Option<Vec<u8>>> {
+    prop::option::of(prop::collection::vec(any::<u8>(), 1..2048))
+}
+
+fn request(command: i32, key: Vec<u8>, value: Option<Vec<u8>>) -> RequestPacket {
+    let key_len = key.len() as i32;
+    let value_len = value.as_ref().map_or(0, |v| v.len() as i32);
+    RequestPacket {
+        magic_number: MAGIC,
+        version: VERSION,
+        command_type: command,
+        total_packet_length: HEADER_SIZE + key_len + value_len,
+        key_length: key_len,
+        value_length: value_len,
+        key,
+        value,
+    }
+}
+
+fn parse_response(frame: &[u8]) -> ResponsePacket {
+    match deserialize_response(frame) {
+        ParseResult::ParseSuccess(packet) => packet,
+        ParseResult::ParseError(message) => panic!("parse error: {message}"),
+        ParseResult::InsufficientData => panic!("insufficient data"),
+    }
+}
+
+fn header_i32(frame: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes(frame[offset..offset + 4].try_into().unwrap())
+}
+
+proptest! {
+    #[test]
+    fn request_packets_round_trip(
+        command in 1i32..=3,
+        key in bytes(1024),
+        value in value(),
+    ) {
+        let packet = request(command, key, value);
+        let frame = serialize_request(&packet);
+
+        let parsed = try_parse_request_packet(&frame).expect("serialized request parses");
+        prop_assert_eq!(&parsed, &packet);
+
+        // Parsing the header alone agrees with the full parse.
+        let header = try_parse_request_header(&frame).expect("serialized header parses");
+        prop_assert_eq!(header.command_type, parsed.command_type);
+        prop_assert_eq!(header.total_packet_length, parsed.total_packet_length);
+        prop_assert_eq!(header.key_length, parsed.key_length);
+        prop_assert_eq!(header.value_length, parsed.value_length);
+    }
+
+    #[test]
+    fn request_builders_write_consistent_lengths(
+        key in ".{0,256}",
+        value in bytes(4096),
+    ) {
+        let key_len = key.len() as i32;
+        let cases = [
+            (1, create_set_request(&key, &value), value.len() as i32),
+            (2, create_get_request(&key), 0),
+            (3, create_delete_request(&key), 0),
+        ];
+        for (command, frame, value_len) in cases {
+            prop_assert_eq!(frame.len() as i32, HEADER_SIZE + key_len + value_len);
+            prop_assert_eq!(u32::from_le_bytes(frame[0..4].try_into().unwrap()), MAGIC);
+            prop_assert_eq!(frame[4], VERSION);
+            prop_assert_eq!(frame[5] as i32, command);
+            prop_assert_eq!(header_i32(&frame, 6), HEADER_SIZE + key_len + value_len);
+            prop_assert_eq!(header_i32(&frame, 10), key_len);
+            prop_assert_eq!(header_i32(&frame, 14), value_len);
+            prop_assert_eq!(&frame[18..18 + key.len()], key.as_bytes());
+        }
+    }
+
+    #[test]
+    fn success_responses_round_trip(value in value()) {
+        let packet = success_response(value.as_deref());
+        let frame = serialize_response(&packet);
+        let value_len = value.as_ref().map_or(0, Vec::len) as i32;
+        prop_assert_eq!(frame.len() as i32, HEADER_SIZE + value_len);
+        prop_assert_eq!(header_i32(&frame, 6), HEADER_SIZE + value_len);
+
+        let parsed = parse_response(&frame);
+        prop_assert_eq!(parsed.status_code, 0);
+        prop_assert_eq!(parsed.value_length, value_len);
+        prop_assert_eq!(parsed.error_message_length, 0);
+        prop_assert_eq!(&parsed, &packet);
+    }
+
+    #[test]
+    fn error_responses_round_trip(message in ".{1,512}") {
+        let frame = serialize_response(&error_response(&message));
+        prop_assert_eq!(frame.len(), HEADER_SIZE as usize + message.len());
+        prop_assert_eq!(header_i32(&frame, 6), HEADER_SIZE + message.len() as i32);
+
+        let parsed = parse_response(&frame);
+        prop_assert_eq!(parsed.status_code, 2);
+        prop_assert_eq!(parsed.value_length, 0);
+        prop_assert_eq!(parsed.error_message_length, message.len() as i32);
+        prop_assert!(parsed.value.is_none());
+        prop_assert_eq!(parsed.error_message.as_deref(), Some(message.as_str()));
+    }
+
+    #[test]
+    fn v2_frames_round_trip(
+        command in 1i32..=3,
+        key in bytes(1024),
+        value in value(),
+        id in any::<u64>(),
+    ) {
+        let packet = request(command, key, value.clone());
+        let frame = encode_frame(serialize_request(&packet), 2, id);
+        prop_assert_eq!(request_id(&frame), Some(id));
+        prop_assert_eq!(header_i32(&frame, 6), frame.len() as i32);
+
+        let parsed = try_parse_request_packet(&frame).expect("v2 request parses");
+        prop_assert_eq!(parsed.version, 2);
+        prop_assert_eq!(&parsed.key, &packet.key);
+        prop_assert_eq!(&parsed.value, &packet.value);
+
+        let response = encode_frame(serialize_response(&success_response(value.as_deref())), 2, id);
+        prop_assert_eq!(request_id(&response), Some(id));
+        let parsed = parse_response(&response);
+        prop_assert_eq!(parsed.version, 2);
+        prop_assert_eq!(parsed.value, value);
+    }
+
+    #[test]
+    fn truncated_responses_need_more_data(value in value(), cut in any::<prop::sample::Index>()) {
+        let frame = serialize_response(&success_response(value.as_deref()));
+        let cut = cut.index(frame.len());
+        prop_assert_eq!(deserialize_response(&frame[..cut]), ParseResult::InsufficientData);
+    }
+}
+
+#[test]
+fn not_found_response_round_trips() {
+    let frame = serialize_response(&not_found_response());
+    assert_eq!(frame.len() as i32, HEADER_SIZE);
+    assert_eq!(header_i32(&frame, 6), HEADER_SIZE);
+
+    let parsed = parse_response(&frame);
+    assert_eq!(parsed.status_code, 1);
+    assert_eq!(parsed.total_packet_length, HEADER_SIZE);
+    assert!(parsed.value.is_none());
+    assert!(parsed.error_message.is_none());
+}
+
+#[test]
+fn empty_set_value_parses_as_absent() {
+    let frame = create_set_request("key", b"");
+    let parsed = try_parse_request_packet(&frame).expect("request parses");
+    assert_eq!(parsed.value_length, 0);
+    assert!(parsed.value.is_none());
+}
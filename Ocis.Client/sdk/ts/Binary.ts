@@ -1,6 +1,7 @@
 import { int32, uint32, uint8 } from "./fable_modules/fable-library-ts.4.25.0/Int32.js";
 import { setItem, item } from "./fable_modules/fable-library-ts.4.25.0/Array.js";
 import { get_UTF8 } from "./fable_modules/fable-library-ts.4.25.0/Encoding.js";
+import { Option } from "./fable_modules/fable-library-ts.4.25.0/Option.js";
 
 function getUint32(b0: uint8, b1: uint8, b2: uint8, b3: uint8): uint32 {
     return (((((b0 | ((b1 << 8) >>> 0)) >>> 0) | ((b2 << 16) >>> 0)) >>> 0) | ((b3 << 24) >>> 0)) >>> 0;
@@ -22,6 +23,45 @@ export function readByte(buffer: uint8[], offset: int32): uint8 {
     return item(offset, buffer);
 }
 
+function isInBounds(buffer: uint8[], offset: int32, size: int32): boolean {
+    if (offset >= 0) {
+        return offset <= (buffer.length - size);
+    }
+    else {
+        return false;
+    }
+}
+
+/**
+ * Bounds-checked variants for reading untrusted input.
+ */
+export function tryReadUInt32LittleEndian(buffer: uint8[], offset: int32): Option<uint32> {
+    if (isInBounds(buffer, offset, 4)) {
+        return readUInt32LittleEndian(buffer, offset);
+    }
+    else {
+        return undefined;
+    }
+}
+
+export function tryReadInt32LittleEndian(buffer: uint8[], offset: int32): Option<int32> {
+    if (isInBounds(buffer, offset, 4)) {
+        return readInt32LittleEndian(buffer, offset);
+    }
+    else {
+        return undefined;
+    }
+}
+
+export function tryReadByte(buffer: uint8[], offset: int32): Option<uint8> {
+    if (isInBounds(buffer, offset, 1)) {
+        return readByte(buffer, offset);
+    }
+    else {
+        return undefined;
+    }
+}
+
 export function writeUInt32LittleEndian(value: uint32, buffer: uint8[], offset: int32): void {
     setItem(buffer, offset, value & 0xFF);
     setItem(buffer, offset + 1, (value >>> 8) & 0xFF);
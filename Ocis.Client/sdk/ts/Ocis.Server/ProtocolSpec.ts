@@ -27,7 +27,7 @@ export class RequestPacket extends Record implements IEquatable<RequestPacket>,
 }
 
 export function RequestPacket_$reflection(): TypeInfo {
-    return record_type("Ocis.Server.ProtocolSpec.RequestPacket", [], RequestPacket, () => [["MagicNumber", uint32_type], ["Version", uint8_type], ["CommandType", enum_type("Ocis.Server.ProtocolSpec.CommandType", int32_type, [["Set", 1], ["Get", 2], ["Delete", 3]])], ["TotalPacketLength", int32_type], ["KeyLength", int32_type], ["ValueLength", int32_type], ["Key", array_type(uint8_type)], ["Value", option_type(array_type(uint8_type))]]);
+    return record_type("Ocis.Server.ProtocolSpec.RequestPacket", [], RequestPacket, () => [["MagicNumber", uint32_type], ["Version", uint8_type], ["CommandType", enum_type("Ocis.Server.ProtocolSpec.CommandType", int32_type, [["Set", 1], ["Get", 2], ["Delete", 3], ["Exists", 5]])], ["TotalPacketLength", int32_type], ["KeyLength", int32_type], ["ValueLength", int32_type], ["Key", array_type(uint8_type)], ["Value", option_type(array_type(uint8_type))]]);
 }
 
 export class ResponsePacket extends Record implements IEquatable<ResponsePacket>, IComparable<ResponsePacket> {
@@ -7,6 +7,20 @@ import { get_UTF8 } from "./fable_modules/fable-library-ts.4.25.0/Encoding.js";
 import { Union } from "./fable_modules/fable-library-ts.4.25.0/Types.js";
 import { union_type, string_type, TypeInfo } from "./fable_modules/fable-library-ts.4.25.0/Reflection.js";
 import { printf, toText } from "./fable_modules/fable-library-ts.4.25.0/String.js";
+import { toInt64, op_Addition, fromInt32 } from "./fable_modules/fable-library-ts.4.25.0/BigInt.js";
+
+/**
+ * Whether the two body length fields are non-negative and add up to the
+ * declared total. Summed in 64 bits so hostile headers cannot overflow.
+ */
+export function HasConsistentLengths(totalPacketLength: int32, firstLength: int32, secondLength: int32): boolean {
+    if ((firstLength >= 0) && (secondLength >= 0)) {
+        return toInt64(fromInt32(totalPacketLength)) === toInt64(op_Addition(toInt64(op_Addition(18n, toInt64(fromInt32(firstLength)))), toInt64(fromInt32(secondLength))));
+    }
+    else {
+        return false;
+    }
+}
 
 export function TryParseRequestHeader(buffer: uint8[]): Option<RequestPacket> {
     if (buffer.length < 18) {
@@ -31,7 +45,10 @@ export function TryParseRequestPacket(buffer: uint8[]): Option<RequestPacket> {
     }
     else {
         const header: RequestPacket = value_3(matchValue);
-        if (buffer.length >= header.TotalPacketLength) {
+        if (!HasConsistentLengths(header.TotalPacketLength, header.KeyLength, header.ValueLength)) {
+            return undefined;
+        }
+        else if (buffer.length >= header.TotalPacketLength) {
             try {
                 return new RequestPacket(header.MagicNumber, header.Version, header.CommandType, header.TotalPacketLength, header.KeyLength, header.ValueLength, getSubArray<uint8>(buffer, 18, header.KeyLength), (header.ValueLength > 0) ? getSubArray<uint8>(buffer, 18 + header.KeyLength, header.ValueLength) : undefined);
             }
@@ -206,7 +223,7 @@ export function DeserializeResponse(buffer: uint8[]): ParseResult$1_$union<Respo
             else if ((valueLength < 0) ? true : (errorMessageLength < 0)) {
                 return ParseResult$1_ParseError<ResponsePacket>("Invalid length field");
             }
-            else if (totalPacketLength !== ((18 + valueLength) + errorMessageLength)) {
+            else if (!HasConsistentLengths(totalPacketLength, valueLength, errorMessageLength)) {
                 return ParseResult$1_ParseError<ResponsePacket>("Packet length mismatch");
             }
             else {
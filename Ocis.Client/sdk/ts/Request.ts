@@ -23,3 +23,7 @@ export function createDeleteRequest(key: string): uint8[] {
     return SerializeRequest(createPacket(3, key, undefined));
 }
 
+export function createExistsRequest(key: string): uint8[] {
+    return SerializeRequest(createPacket(5, key, undefined));
+}
+
@@ -98,3 +98,29 @@ export function toClientResultValue(parseResult: ParseResult$1_$union<ResponsePa
     }
 }
 
+/**
+ * Map an EXISTS response: NotFound is a successful `false`.
+ */
+export function toClientResultExists(parseResult: ParseResult$1_$union<ResponsePacket>): ClientResult$1_$union<boolean> {
+    switch (parseResult.tag) {
+        case /* ParseError */ 1:
+            return ClientResult$1_Error<boolean>(parseResult.fields[0]);
+        case /* InsufficientData */ 2:
+            return ClientResult$1_Error<boolean>("Insufficient data");
+        default: {
+            const response: ResponsePacket = parseResult.fields[0];
+            const matchValue: uint8 = response.StatusCode;
+            switch (matchValue) {
+                case 0:
+                    return ClientResult$1_Success<boolean>(true);
+                case 1:
+                    return ClientResult$1_Success<boolean>(false);
+                case 2:
+                    return ClientResult$1_Error<boolean>(defaultArg(response.ErrorMessage, "Unknown error"));
+                default:
+                    return ClientResult$1_Error<boolean>("Invalid status code");
+            }
+        }
+    }
+}
+
@@ -0,0 +1,118 @@
+module Ocis.Server.Tests.HandlerTests
+
+open System
+open System.IO
+open System.Text
+open NUnit.Framework
+open Ocis.OcisDB
+open Ocis.Server.DbDispatcher
+open Ocis.Server.Handler
+open Ocis.Server.ProtocolSpec
+
+[<TestFixture>]
+type HandlerTests () =
+  let testDbDir =
+    Path.Combine (
+      Path.GetTempPath (),
+      $"ocis_handler_tests_{Guid.NewGuid ():N}"
+    )
+
+  let toBytes (value : string) = Encoding.UTF8.GetBytes value
+
+  let openDbOrFail dir =
+    match OcisDB.Open (dir, 1000) with
+    | Ok opened -> opened
+    | Error msg ->
+      Assert.Fail $"Failed to open db: {msg}"
+      Unchecked.defaultof<OcisDB>
+
+  let request
+    (commandType : CommandType)
+    (key : byte array)
+    (value : byte array option)
+    =
+    let valueLength =
+      value
+      |> Option.map Array.length
+      |> Option.defaultValue 0
+
+    { MagicNumber = MAGIC_NUMBER
+      Version = PROTOCOL_VERSION
+      CommandType = commandType
+      TotalPacketLength = HEADER_SIZE + key.Length + valueLength
+      KeyLength = key.Length
+      ValueLength = valueLength
+      Key = key
+      Value = value }
+
+  let run dispatcher packet =
+    RequestHandler.ProcessValidRequest dispatcher packet
+    |> Async.RunSynchronously
+
+  [<SetUp>]
+  member _.SetUp () =
+    if not (Directory.Exists testDbDir) then
+      Directory.CreateDirectory testDbDir |> ignore
+
+  [<TearDown>]
+  member _.TearDown () =
+    try
+      if Directory.Exists testDbDir then
+        Directory.Delete (testDbDir, true)
+    with _ ->
+      ()
+
+  [<Test>]
+  member _.ExistsReportsPresenceWithoutValue () =
+    let db = openDbOrFail testDbDir
+    use db = db
+    use dispatcher = new OcisDbDispatcher (db, 32)
+    let key = toBytes "exists-key"
+
+    let absent = run dispatcher (request CommandType.Exists key None)
+    Assert.That (absent.StatusCode, Is.EqualTo StatusCode.NotFound)
+
+    let set =
+      run dispatcher (request CommandType.Set key (Some (toBytes "value")))
+
+    Assert.That (set.StatusCode, Is.EqualTo StatusCode.Success)
+
+    let present = run dispatcher (request CommandType.Exists key None)
+    Assert.That (present.StatusCode, Is.EqualTo StatusCode.Success)
+    Assert.That (present.Value.IsNone, Is.True)
+
+    let deleted = run dispatcher (request CommandType.Delete key None)
+    Assert.That (deleted.StatusCode, Is.EqualTo StatusCode.Success)
+
+    let gone = run dispatcher (request CommandType.Exists key None)
+    Assert.That (gone.StatusCode, Is.EqualTo StatusCode.NotFound)
+
+  [<Test>]
+  member _.ExistsRejectsValue () =
+    let db = openDbOrFail testDbDir
+    use db = db
+    use dispatcher = new OcisDbDispatcher (db, 32)
+
+    let response =
+      run
+        dispatcher
+        (request CommandType.Exists (toBytes "k") (Some (toBytes "v")))
+
+    Assert.That (response.StatusCode, Is.EqualTo StatusCode.Error)
+
+  [<Test>]
+  member _.RustOnlyCommandsAreUnknown () =
+    let db = openDbOrFail testDbDir
+    use db = db
+    use dispatcher = new OcisDbDispatcher (db, 32)
+
+    // SCAN in the Rust SDK's protocol extensions.
+    let response =
+      run dispatcher (request (enum<CommandType> 6) (toBytes "k") None)
+
+    Assert.That (response.StatusCode, Is.EqualTo StatusCode.Error)
+
+    Assert.That (
+      response.ErrorMessage,
+      Is.EqualTo (Some "Unknown command type: 6")
+    )
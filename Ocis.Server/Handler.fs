@@ -82,6 +82,26 @@ module RequestHandler =
         return Protocol.CreateErrorResponse msg
     }
 
+  let handleExists
+    (dispatcher : OcisDbDispatcher)
+    (request : RequestPacket)
+    : Async<ResponsePacket>
+    =
+    async {
+      let! result = dispatcher.DispatchGet request.Key
+
+      match result with
+      | Ok (Some _) ->
+        Logger.Debug $"EXISTS found: key length={request.Key.Length}"
+        return Protocol.CreateSuccessResponse None
+      | Ok None ->
+        Logger.Debug $"EXISTS not found: key length={request.Key.Length}"
+        return Protocol.CreateNotFoundResponse ()
+      | Error msg ->
+        Logger.Error $"EXISTS failed: {msg}"
+        return Protocol.CreateErrorResponse msg
+    }
+
   let handleUnknown (request : RequestPacket) : ResponsePacket =
     let errorMsg =
       $"Unknown command type: {int request.CommandType}"
@@ -108,6 +128,7 @@ module RequestHandler =
             return Protocol.CreateErrorResponse "SET command requires a value"
         | CommandType.Get -> return! handleGet dispatcher request
         | CommandType.Delete -> return! handleDelete dispatcher request
+        | CommandType.Exists -> return! handleExists dispatcher request
         | _ -> return handleUnknown request
       with ex ->
         let errorMsg =
@@ -136,10 +157,11 @@ module RequestHandler =
           || request.ValueLength > (8 * 1024 * 1024))
     then // Maximum 8MB value
       Error "Invalid value length for SET command"
-    // Validate GET and DELETE commands should not have a value
+    // Validate GET, DELETE and EXISTS commands should not have a value
     elif
       (request.CommandType = CommandType.Get
-       || request.CommandType = CommandType.Delete)
+       || request.CommandType = CommandType.Delete
+       || request.CommandType = CommandType.Exists)
       && request.ValueLength > 0
     then
       Error $"{request.CommandType} command should not have a value"
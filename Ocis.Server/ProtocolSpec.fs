@@ -12,11 +12,14 @@ let PROTOCOL_VERSION = 1uy
 [<Literal>]
 let HEADER_SIZE = 18
 
-/// Command type
+/// Command type. The other values are protocol extensions of the Rust
+/// SDK's reference server (Ocis.Client/sdk/rust/src/spec.rs); this server
+/// answers them as unknown commands.
 type CommandType =
   | Set = 1
   | Get = 2
   | Delete = 3
+  | Exists = 5
 
 /// Status code
 type StatusCode =